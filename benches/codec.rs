@@ -0,0 +1,120 @@
+//! Замеры кодирования и декодирования на типичных конфигурациях AmneziaVPN
+//!
+//! Запуск: `cargo bench --bench codec`
+
+use amnezia_config_decoder_rust::{
+    compress_data, decode, decode_base64, decompress_data, encode, encode_base64,
+    encode_with_options, EncodeContext, EncodeOptions,
+};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use serde_json::{json, Value};
+use std::hint::black_box;
+
+/// Конфигурация с `containers` контейнерами AWG, как её выгружает клиент
+fn config(containers: usize) -> Value {
+    let list: Vec<Value> = (0..containers)
+        .map(|n| {
+            let last_config = json!({
+                "H1": "1020325451", "H2": "3288052141", "H3": "1766607858", "H4": "2581650625",
+                "Jc": "4", "Jmin": "10", "Jmax": "50", "S1": "83", "S2": "47",
+                "client_ip": format!("10.8.{}.2", n % 256),
+                "client_priv_key": "mJ0gq8ZQq0Gmj3o1oU3bZl8hV8cJ0f9pQmZ7T0lC7Xk=",
+                "client_pub_key": "Rr5cL5bHcf0O0p3n6s3m8Ad3fP5D0Lz2tYh4RbB6nCw=",
+                "config": "[Interface]\nAddress = 10.8.1.2/32\nDNS = 1.1.1.1, 1.0.0.1\n",
+                "hostName": "vpn.example.com",
+                "port": 51820 + n,
+                "server_pub_key": "Xq1eK9wD3zR0sT5vU8yB2nM4pL6jH7gF0dS1aQ9wE3r="
+            });
+            json!({
+                "container": format!("amnezia-awg-{}", n),
+                "awg": {
+                    "last_config": last_config.to_string(),
+                    "port": (51820 + n).to_string(),
+                    "transport_proto": "udp"
+                }
+            })
+        })
+        .collect();
+    json!({
+        "containers": list,
+        "defaultContainer": "amnezia-awg-0",
+        "description": "Benchmark server",
+        "dns1": "1.1.1.1",
+        "dns2": "1.0.0.1",
+        "hostName": "vpn.example.com"
+    })
+}
+
+/// Небольшая, средняя и крупная конфигурации
+fn configs() -> [(&'static str, Value); 3] {
+    [("small", config(1)), ("medium", config(8)), ("large", config(64))]
+}
+
+fn bench_codec(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode");
+    for (name, config) in configs() {
+        group.throughput(Throughput::Bytes(config.to_string().len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &config, |b, config| {
+            b.iter(|| encode(black_box(config)).unwrap())
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("decode");
+    for (name, config) in configs() {
+        let url = encode(&config).unwrap();
+        group.throughput(Throughput::Bytes(url.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &url, |b, url| {
+            b.iter(|| decode(black_box(url)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+/// Кодирование пакета небольших конфигураций: по отдельности и через
+/// общий [`EncodeContext`]
+fn bench_batch(c: &mut Criterion) {
+    let items: Vec<Value> = (0..100).map(|n| json!({"hostName": format!("{}.example.com", n)})).collect();
+    let options = EncodeOptions::default();
+
+    let mut group = c.benchmark_group("batch");
+    group.throughput(Throughput::Elements(items.len() as u64));
+    group.bench_function("encode_with_options", |b| {
+        b.iter(|| {
+            for config in &items {
+                black_box(encode_with_options(config, &options).unwrap());
+            }
+        })
+    });
+    group.bench_function("encode_context", |b| {
+        let mut context = EncodeContext::new(options.clone());
+        b.iter(|| {
+            for config in &items {
+                black_box(context.encode(config).unwrap());
+            }
+        })
+    });
+    group.finish();
+}
+
+fn bench_helpers(c: &mut Criterion) {
+    let json = serde_json::to_vec_pretty(&config(8)).unwrap();
+    let compressed = compress_data(&json, flate2::Compression::default()).unwrap();
+    let encoded = encode_base64(&compressed);
+
+    let mut group = c.benchmark_group("helpers");
+    group.throughput(Throughput::Bytes(json.len() as u64));
+    group.bench_function("compress_data", |b| {
+        b.iter(|| compress_data(black_box(&json), flate2::Compression::default()).unwrap())
+    });
+    group.bench_function("decompress_data", |b| {
+        b.iter(|| decompress_data(black_box(&compressed)).unwrap())
+    });
+    group.throughput(Throughput::Bytes(compressed.len() as u64));
+    group.bench_function("encode_base64", |b| b.iter(|| encode_base64(black_box(&compressed))));
+    group.bench_function("decode_base64", |b| b.iter(|| decode_base64(black_box(&encoded)).unwrap()));
+    group.finish();
+}
+
+criterion_group!(benches, bench_codec, bench_batch, bench_helpers);
+criterion_main!(benches);
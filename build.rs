@@ -0,0 +1,17 @@
+//! Встраивает в сборку короткий хеш git коммита для `--version`
+
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+
+    // Вне git репозитория (например, сборка из архива) хеш просто не встраивается
+    let output = Command::new("git").args(["rev-parse", "--short", "HEAD"]).output();
+    if let Ok(output) = output
+        && output.status.success()
+    {
+        let commit = String::from_utf8_lossy(&output.stdout);
+        println!("cargo:rustc-env=GIT_COMMIT={}", commit.trim());
+    }
+}
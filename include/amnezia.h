@@ -0,0 +1,39 @@
+/*
+ * C интерфейс amnezia-config-decoder-rust. Сборка динамической библиотеки:
+ *   cargo rustc --lib --release --no-default-features --features ffi --crate-type cdylib
+ *
+ * Владение памятью:
+ *  - входные строки остаются у вызывающего: UTF-8, завершённые нулём;
+ *  - возвращённую строку освобождайте ровно один раз через
+ *    amnezia_string_free(), не через free();
+ *  - при ошибке возвращается NULL, код ошибки пишется в *error
+ *    (если error не NULL); при успехе туда пишется AMNEZIA_OK.
+ */
+#ifndef AMNEZIA_H
+#define AMNEZIA_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define AMNEZIA_OK 0
+#define AMNEZIA_ERR_NULL 1
+#define AMNEZIA_ERR_UTF8 2
+#define AMNEZIA_ERR_JSON 3
+#define AMNEZIA_ERR_ENCODE 4
+#define AMNEZIA_ERR_DECODE 5
+
+/* JSON конфигурация -> vpn:// URL */
+char *amnezia_encode(const char *json, int *error);
+
+/* vpn:// URL -> отформатированный JSON */
+char *amnezia_decode(const char *url, int *error);
+
+/* Освобождает строку от amnezia_encode/amnezia_decode; NULL игнорируется */
+void amnezia_string_free(char *string);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* AMNEZIA_H */
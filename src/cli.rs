@@ -0,0 +1,179 @@
+//! Описание аргументов командной строки для clap.
+//!
+//! Справка и версия выводятся самой программой (см. `usage.rs`), поэтому
+//! встроенные `--help`/`--version` clap отключены. Все флаги доступны и без
+//! подкоманды, и после любой из них: `prog -d URL` и `prog decode URL`
+//! равнозначны. Повтор флага с одним значением не ошибка — действует
+//! последний, как и для взаимоисключающих пар вроде `--compact`/`--pretty`
+
+use clap::error::{ContextKind, ContextValue, ErrorKind};
+use clap::{Arg, ArgAction, Command};
+
+/// Флаги, задающие режим работы; из нескольких действует последний
+pub const MODES: &[&str] = &["encode", "decode", "validate", "check", "repair"];
+
+/// Подкоманды и режим, который каждая задаёт
+pub const SUBCOMMANDS: &[(&str, &str)] = &[
+    ("encode", "encode"),
+    ("decode", "decode"),
+    ("validate", "validate"),
+    ("export", "decode"),
+];
+
+/// Полное описание командной строки со всеми подкомандами
+pub fn command() -> Command {
+    let mut command = base(Command::new(env!("CARGO_PKG_NAME")));
+    for (name, _) in SUBCOMMANDS {
+        let mut subcommand = Command::new(*name);
+        if *name == "export" {
+            // Формат — первый позиционный аргумент, до входных данных
+            subcommand = subcommand.arg(Arg::new("export-format").value_name("FORMAT").required(true));
+        }
+        command = command.subcommand(base(subcommand));
+    }
+    command.disable_help_subcommand(true)
+}
+
+/// Общие настройки и аргументы корневой команды и подкоманд
+fn base(command: Command) -> Command {
+    command
+        .disable_help_flag(true)
+        .disable_version_flag(true)
+        .args_override_self(true)
+        .args(args())
+}
+
+fn flag(id: &'static str) -> Arg {
+    Arg::new(id).long(id).action(ArgAction::SetTrue)
+}
+
+/// Флаг со значением; значение может начинаться с `-`, как раньше
+fn value(id: &'static str, name: &'static str) -> Arg {
+    Arg::new(id).long(id).value_name(name).allow_hyphen_values(true)
+}
+
+/// Повторяемый флаг со значением; значения собираются по порядку
+fn values(id: &'static str, name: &'static str) -> Arg {
+    value(id, name).action(ArgAction::Append)
+}
+
+fn args() -> Vec<Arg> {
+    let mode = |id: &'static str| {
+        let others = MODES.iter().copied().filter(|other| *other != id);
+        flag(id).overrides_with_all(others)
+    };
+    vec![
+        mode("encode").short('e'),
+        mode("decode").short('d'),
+        mode("validate"),
+        mode("check"),
+        mode("repair"),
+        flag("summary"),
+        flag("strict"),
+        flag("redact"),
+        values("redact-key", "KEY"),
+        value("schema", "FILE"),
+        flag("decode-nested"),
+        flag("encode-nested"),
+        values("nested-field", "PATH"),
+        values("input", "FILE").short('i'),
+        values("output", "FILE").short('o'),
+        value("input-env", "NAME"),
+        flag("from-clipboard"),
+        value("fetch", "URL"),
+        flag("secure-output"),
+        flag("tee"),
+        flag("in-place"),
+        flag("append"),
+        flag("preserve-order").overrides_with("sort-keys"),
+        flag("sort-keys").overrides_with("preserve-order"),
+        value("indent", "N").overrides_with("indent-tab"),
+        flag("indent-tab").overrides_with("indent"),
+        flag("compact").overrides_with("pretty"),
+        flag("pretty").overrides_with("compact"),
+        value("level", "LEVEL"),
+        value("wrap", "WIDTH"),
+        value("split", "N"),
+        flag("qr"),
+        value("qr-png", "FILE"),
+        flag("clipboard"),
+        value("qr-in", "FILE"),
+        flag("no-prefix"),
+        value("scheme", "SCHEME"),
+        value("format", "FORMAT").overrides_with_all(["no-compress", "deflate-raw"]),
+        flag("no-compress").overrides_with_all(["format", "deflate-raw"]),
+        flag("deflate-raw").overrides_with_all(["format", "no-compress"]),
+        flag("minify"),
+        flag("deterministic"),
+        value("hash", "ALGORITHM"),
+        value("base64", "ALPHABET"),
+        value("endian", "ORDER"),
+        value("format-version", "VERSION"),
+        flag("stats"),
+        flag("explain"),
+        flag("compare-size"),
+        flag("raw"),
+        flag("recursive"),
+        value("max-size", "SIZE"),
+        value("sign", "KEY"),
+        value("verify", "KEY"),
+        values("set", "PATH=VALUE"),
+        flag("template"),
+        values("var", "NAME=VALUE"),
+        flag("strict-vars"),
+        value("diff", "URL").num_args(2).value_names(["FIRST", "SECOND"]),
+        value("merge", "FILE").num_args(2).value_names(["BASE", "OVERRIDES"]),
+        value("merge-arrays", "MODE"),
+        value("export", "FORMAT"),
+        value("from", "FORMAT"),
+        value("to", "FORMAT"),
+        value("field", "PATH"),
+        value("stamp", "KEY"),
+        flag("nfc"),
+        flag("batch"),
+        flag("repl"),
+        flag("quiet").short('q'),
+        flag("crlf").overrides_with("lf"),
+        flag("lf").overrides_with("crlf"),
+        value("jobs", "N").short('j'),
+        flag("ndjson"),
+        flag("json-array"),
+        value("output-format", "FORMAT"),
+        value("name-field", "PATH"),
+        flag("report"),
+        flag("null").short('0').alias("stdin0"),
+        Arg::new("verbose").short('v').long("verbose").action(ArgAction::Count),
+        flag("no-color"),
+        flag("json-errors"),
+        value("lang", "LANG"),
+        flag("help").short('h'),
+        flag("version").short('V'),
+        flag("selftest"),
+        Arg::new("data").value_name("DATA").action(ArgAction::Append),
+    ]
+}
+
+/// Сообщает об ошибке разбора аргументов по-русски и завершает программу
+/// с кодом `code`. Для редких ошибок выводится текст clap
+pub fn exit_with_error(error: clap::Error, code: i32) -> ! {
+    let argument = match error.get(ContextKind::InvalidArg) {
+        // clap пишет аргумент вместе с именем значения: `--level <LEVEL>`
+        Some(ContextValue::String(argument)) => argument.split_whitespace().next().unwrap_or_default().to_string(),
+        _ => String::new(),
+    };
+    let mut text = match error.kind() {
+        ErrorKind::InvalidValue | ErrorKind::WrongNumberOfValues | ErrorKind::TooFewValues => {
+            format!("Ошибка: не указано значение для {}", argument)
+        }
+        ErrorKind::UnknownArgument => {
+            let mut text = format!("Ошибка: неизвестный аргумент '{}'", argument);
+            if let Some(ContextValue::String(suggested)) = error.get(ContextKind::SuggestedArg) {
+                text.push_str(&format!("\n   Возможно, имелся в виду {}", suggested));
+            }
+            text
+        }
+        _ => error.render().to_string().trim_end().to_string(),
+    };
+    text.push_str("\n   Справка: --help");
+    crate::fail("Usage", &text, code);
+}
@@ -0,0 +1,148 @@
+use crate::error::ConvertError;
+use serde_json::Value;
+use std::fmt;
+use std::path::Path;
+
+/// Текстовый формат представления конфигурации
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DataFormat {
+    /// JSON (по умолчанию)
+    #[default]
+    Json,
+    /// YAML
+    Yaml,
+    /// TOML: не умеет `null`, а документ обязан быть таблицей
+    Toml,
+}
+
+impl DataFormat {
+    /// Разбирает имя формата: `json`, `yaml`/`yml` или `toml`
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "json" => Some(DataFormat::Json),
+            "yaml" | "yml" => Some(DataFormat::Yaml),
+            "toml" => Some(DataFormat::Toml),
+            _ => None,
+        }
+    }
+
+    /// Определяет формат по расширению файла: `.json`, `.yaml`/`.yml`, `.toml`
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        path.extension().and_then(|ext| ext.to_str()).and_then(Self::parse)
+    }
+}
+
+impl fmt::Display for DataFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DataFormat::Json => "JSON",
+            DataFormat::Yaml => "YAML",
+            DataFormat::Toml => "TOML",
+        })
+    }
+}
+
+/// Разбирает конфигурацию из выбранного формата в JSON значение
+pub fn from_format(text: &str, format: DataFormat) -> Result<Value, ConvertError> {
+    match format {
+        DataFormat::Json => Ok(serde_json::from_str(text)?),
+        DataFormat::Yaml => Ok(serde_yaml::from_str(text)?),
+        DataFormat::Toml => Ok(toml::from_str(text)?),
+    }
+}
+
+/// Сериализует конфигурацию в выбранный формат; JSON — с отступами
+pub fn to_format(value: &Value, format: DataFormat) -> Result<String, ConvertError> {
+    match format {
+        DataFormat::Json => Ok(serde_json::to_string_pretty(value)?),
+        DataFormat::Yaml => Ok(serde_yaml::to_string(value)?),
+        DataFormat::Toml => {
+            check_toml(value)?;
+            Ok(toml::to_string_pretty(value)?)
+        }
+    }
+}
+
+/// Находит то, что TOML представить не может, раньше сериализатора, чтобы
+/// сообщить путь к полю вместо невнятной ошибки
+fn check_toml(value: &Value) -> Result<(), ConvertError> {
+    if !value.is_object() {
+        return Err(ConvertError::TomlRoot(type_name(value)));
+    }
+    find_null(value, String::new()).map_or(Ok(()), |path| Err(ConvertError::TomlNull(path)))
+}
+
+/// Возвращает путь к первому `null` через точку, как в `--field`
+fn find_null(value: &Value, path: String) -> Option<String> {
+    let join = |key: &str| if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) };
+    match value {
+        Value::Null => Some(if path.is_empty() { "(root)".to_string() } else { path.clone() }),
+        Value::Object(map) => map.iter().find_map(|(key, child)| find_null(child, join(key))),
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .find_map(|(index, child)| find_null(child, join(&index.to_string()))),
+        _ => None,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_to_yaml() {
+        let config = json!({
+            "containers": [{"container": "amnezia-awg", "awg": {"port": "51820"}}],
+            "defaultContainer": "amnezia-awg",
+            "dns1": null,
+            "hostName": "example.com"
+        });
+        let yaml = to_format(&config, DataFormat::Yaml).unwrap();
+        assert!(yaml.contains("hostName: example.com"));
+        assert!(yaml.contains("- container: amnezia-awg"));
+        assert!(yaml.contains("port: '51820'"));
+        assert_eq!(serde_yaml::from_str::<Value>(&yaml).unwrap(), config);
+    }
+
+    #[test]
+    fn test_to_toml() {
+        let config = json!({"hostName": "example.com", "containers": [{"container": "amnezia-awg"}]});
+        let text = to_format(&config, DataFormat::Toml).unwrap();
+        assert_eq!(toml::from_str::<Value>(&text).unwrap(), config);
+
+        assert!(matches!(
+            to_format(&json!({"containers": [{"dns": null}]}), DataFormat::Toml),
+            Err(ConvertError::TomlNull(path)) if path == "containers.0.dns"
+        ));
+        assert!(matches!(to_format(&json!([1]), DataFormat::Toml), Err(ConvertError::TomlRoot("an array"))));
+        assert_eq!(DataFormat::parse("YML"), Some(DataFormat::Yaml));
+        assert_eq!(DataFormat::parse("xml"), None);
+    }
+
+    #[test]
+    fn test_from_format() {
+        let expected = json!({"hostName": "example.com", "containers": [{"container": "amnezia-awg"}]});
+        let yaml = "hostName: example.com\ncontainers:\n  - container: amnezia-awg\n";
+        assert_eq!(from_format(yaml, DataFormat::Yaml).unwrap(), expected);
+        let text = "hostName = \"example.com\"\n[[containers]]\ncontainer = \"amnezia-awg\"\n";
+        assert_eq!(from_format(text, DataFormat::Toml).unwrap(), expected);
+
+        assert!(matches!(from_format("a = ", DataFormat::Toml), Err(ConvertError::TomlParse(_))));
+        assert!(matches!(from_format("{a: [", DataFormat::Yaml), Err(ConvertError::Yaml(_))));
+        assert_eq!(DataFormat::from_extension(Path::new("dir/config.yml")), Some(DataFormat::Yaml));
+        assert_eq!(DataFormat::from_extension(Path::new("link.txt")), None);
+    }
+}
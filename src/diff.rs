@@ -0,0 +1,113 @@
+use serde_json::Value;
+use std::fmt;
+
+/// Отличие между двумя конфигурациями
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// Поле есть только во второй конфигурации
+    Added { path: String, value: Value },
+    /// Поле есть только в первой конфигурации
+    Removed { path: String, value: Value },
+    /// Значение поля отличается
+    Changed { path: String, old: Value, new: Value },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Added { path, value } => write!(f, "+ {}: {}", display_path(path), value),
+            Change::Removed { path, value } => write!(f, "- {}: {}", display_path(path), value),
+            Change::Changed { path, old, new } => {
+                write!(f, "~ {}: {} -> {}", display_path(path), old, new)
+            }
+        }
+    }
+}
+
+fn display_path(path: &str) -> &str {
+    if path.is_empty() { "(root)" } else { path }
+}
+
+/// Рекурсивно сравнивает две JSON конфигурации.
+///
+/// Пути записываются через точку, как в `--field` (`containers.0.awg.port`);
+/// массивы сравниваются поэлементно по индексу
+pub fn diff(old: &Value, new: &Value) -> Vec<Change> {
+    let mut changes = Vec::new();
+    diff_into(old, new, String::new(), &mut changes);
+    changes
+}
+
+fn diff_into(old: &Value, new: &Value, path: String, changes: &mut Vec<Change>) {
+    match (old, new) {
+        (Value::Object(old_map), Value::Object(new_map)) => {
+            for (key, old_value) in old_map {
+                let child = join(&path, key);
+                match new_map.get(key) {
+                    Some(new_value) => diff_into(old_value, new_value, child, changes),
+                    None => changes.push(Change::Removed { path: child, value: old_value.clone() }),
+                }
+            }
+            for (key, new_value) in new_map {
+                if !old_map.contains_key(key) {
+                    changes.push(Change::Added { path: join(&path, key), value: new_value.clone() });
+                }
+            }
+        }
+        (Value::Array(old_items), Value::Array(new_items)) => {
+            for (index, old_value) in old_items.iter().enumerate() {
+                let child = join(&path, &index.to_string());
+                match new_items.get(index) {
+                    Some(new_value) => diff_into(old_value, new_value, child, changes),
+                    None => changes.push(Change::Removed { path: child, value: old_value.clone() }),
+                }
+            }
+            for (index, new_value) in new_items.iter().enumerate().skip(old_items.len()) {
+                let child = join(&path, &index.to_string());
+                changes.push(Change::Added { path: child, value: new_value.clone() });
+            }
+        }
+        _ if old != new => changes.push(Change::Changed {
+            path,
+            old: old.clone(),
+            new: new.clone(),
+        }),
+        _ => {}
+    }
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_diff() {
+        let old = json!({
+            "hostName": "a.example.com",
+            "dns1": "1.1.1.1",
+            "containers": [{"container": "amnezia-awg", "port": 51820}]
+        });
+        let new = json!({
+            "hostName": "b.example.com",
+            "containers": [{"container": "amnezia-awg", "port": 51821}, {"container": "amnezia-xray"}],
+            "description": "Home"
+        });
+
+        assert!(diff(&old, &old).is_empty());
+        assert_eq!(diff(&old, &new), vec![
+            Change::Changed { path: "hostName".into(), old: json!("a.example.com"), new: json!("b.example.com") },
+            Change::Removed { path: "dns1".into(), value: json!("1.1.1.1") },
+            Change::Changed { path: "containers.0.port".into(), old: json!(51820), new: json!(51821) },
+            Change::Added { path: "containers.1".into(), value: json!({"container": "amnezia-xray"}) },
+            Change::Added { path: "description".into(), value: json!("Home") },
+        ]);
+
+        let root = diff(&json!(1), &json!("1"));
+        assert_eq!(root[0].to_string(), r#"~ (root): 1 -> "1""#);
+    }
+}
@@ -0,0 +1,270 @@
+use std::string::FromUtf8Error;
+use thiserror::Error;
+
+/// Ошибки декодирования VPN URL
+#[derive(Debug, Error)]
+pub enum DecodeError {
+    #[error("Invalid VPN URL: missing prefix")]
+    MissingPrefix,
+
+    #[error("Invalid base64: {0}")]
+    Base64(#[from] base64::DecodeError),
+
+    #[error("Data too short for header")]
+    TooShort,
+
+    #[error("Data integrity check failed: expected {expected} bytes, got {got}")]
+    IntegrityMismatch { expected: usize, got: usize },
+
+    #[error("Decompression failed: {0}")]
+    Decompress(#[source] std::io::Error),
+
+    #[error("Compressed stream is truncated")]
+    TruncatedStream,
+
+    #[error("Adler-32 checksum mismatch: expected {expected:#010x}, got {actual:#010x}")]
+    ChecksumMismatch { expected: u32, actual: u32 },
+
+    #[error("Invalid UTF-8: {0}")]
+    Utf8(#[from] FromUtf8Error),
+
+    #[error("Invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Failed to read input: {0}")]
+    Input(#[source] std::io::Error),
+
+    #[error("Failed to write output: {0}")]
+    Output(#[source] std::io::Error),
+
+    #[error("HMAC signature mismatch: payload was tampered with or the key is wrong")]
+    SignatureMismatch,
+
+    #[error("URL is not signed, but a verification key was given")]
+    NotSigned,
+
+    #[error("Header declares {declared} bytes, which cannot come from {compressed} compressed bytes")]
+    ImplausibleLength { declared: u32, compressed: usize },
+
+    #[error("Decompressed data exceeds the {0} byte limit")]
+    TooLarge(usize),
+
+    #[error("Signed URLs cannot be repaired: the signature covers the header")]
+    RepairSigned,
+
+    #[error("More than {0} nested URL layers")]
+    TooManyLayers(usize),
+
+    #[error("No payload format matched: {}", describe_attempts(.0))]
+    NoMatchingFormat(Vec<(&'static str, DecodeError)>),
+}
+
+impl DecodeError {
+    /// Имя варианта ошибки, например `IntegrityMismatch`, — стабильный
+    /// идентификатор для программ, разбирающих ошибки
+    pub fn kind(&self) -> &'static str {
+        match self {
+            DecodeError::MissingPrefix => "MissingPrefix",
+            DecodeError::Base64(_) => "Base64",
+            DecodeError::TooShort => "TooShort",
+            DecodeError::IntegrityMismatch { .. } => "IntegrityMismatch",
+            DecodeError::Decompress(_) => "Decompress",
+            DecodeError::TruncatedStream => "TruncatedStream",
+            DecodeError::ChecksumMismatch { .. } => "ChecksumMismatch",
+            DecodeError::Utf8(_) => "Utf8",
+            DecodeError::Json(_) => "Json",
+            DecodeError::Input(_) => "Input",
+            DecodeError::Output(_) => "Output",
+            DecodeError::SignatureMismatch => "SignatureMismatch",
+            DecodeError::NotSigned => "NotSigned",
+            DecodeError::ImplausibleLength { .. } => "ImplausibleLength",
+            DecodeError::TooLarge(_) => "TooLarge",
+            DecodeError::RepairSigned => "RepairSigned",
+            DecodeError::TooManyLayers(_) => "TooManyLayers",
+            DecodeError::NoMatchingFormat(_) => "NoMatchingFormat",
+        }
+    }
+
+    /// Ошибка основного формата: для [`DecodeError::NoMatchingFormat`] —
+    /// ошибка первой попытки (обычно сжатых данных с заголовком, где и
+    /// проверяется целостность), для остальных — сама ошибка
+    pub fn primary_cause(&self) -> &DecodeError {
+        match self {
+            DecodeError::NoMatchingFormat(attempts) => attempts.first().map_or(self, |(_, error)| error),
+            _ => self,
+        }
+    }
+}
+
+/// Перечисляет ошибки всех попыток декодирования через точку с запятой
+fn describe_attempts(attempts: &[(&'static str, DecodeError)]) -> String {
+    attempts
+        .iter()
+        .map(|(format, error)| format!("{}: {}", format, error))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Ошибки кодирования конфигурации в VPN URL
+#[derive(Debug, Error)]
+pub enum EncodeError {
+    #[error("JSON serialization failed: {0}")]
+    Serialize(#[from] serde_json::Error),
+
+    #[error("Compression failed: {0}")]
+    Compress(#[from] std::io::Error),
+}
+
+impl EncodeError {
+    /// Имя варианта ошибки, как [`DecodeError::kind`]
+    pub fn kind(&self) -> &'static str {
+        match self {
+            EncodeError::Serialize(_) => "Serialize",
+            EncodeError::Compress(_) => "Compress",
+        }
+    }
+}
+
+/// Ошибки построения QR кода
+#[derive(Debug, Error)]
+pub enum QrError {
+    #[error("URL is too large for a single QR code ({length} bytes)")]
+    TooLarge { length: usize },
+
+    #[error("QR encoding failed: {0}")]
+    Encode(qrcode::types::QrError),
+
+    #[cfg(feature = "qr-image")]
+    #[error("Image processing failed: {0}")]
+    Image(#[from] image::ImageError),
+
+    #[error("No QR code found in image")]
+    NotFound,
+
+    #[error("QR code could not be read: {0}")]
+    Unreadable(String),
+
+    #[error("QR code does not contain a VPN URL: {0}")]
+    NotVpnUrl(String),
+}
+
+/// Ошибки работы с путями к полям конфигурации
+#[derive(Debug, Error)]
+pub enum PathError {
+    #[error("Invalid field path '{0}'")]
+    Invalid(String),
+
+    #[error("Field path '{path}' not found (missing at '{at}')")]
+    NotFound { path: String, at: String },
+}
+
+/// Ошибки подстановки переменных в шаблон конфигурации
+#[derive(Debug, Error)]
+pub enum TemplateError {
+    #[error("Unresolved template variables: {}", .0.join(", "))]
+    Unresolved(Vec<String>),
+
+    #[error("Unterminated placeholder at position {0}")]
+    Unterminated(usize),
+
+    #[error("Invalid template variable name '{0}'")]
+    InvalidName(String),
+}
+
+/// Ошибки деления URL на части и сборки частей
+#[derive(Debug, Error)]
+pub enum SplitError {
+    #[error("Number of parts must be from 2 to 255, got {0}")]
+    InvalidCount(usize),
+
+    #[error("Cannot split {len} bytes of payload into {parts} parts")]
+    TooManyParts { parts: usize, len: usize },
+
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+
+    #[error("No parts given")]
+    Empty,
+
+    #[error("Input {0} is not a part of a split URL")]
+    NotAPart(usize),
+
+    #[error("Parts belong to different URLs: id {expected:08x} and {got:08x}")]
+    MixedIds { expected: u32, got: u32 },
+
+    #[error("Parts disagree on the number of parts: {expected} and {got}")]
+    TotalMismatch { expected: u8, got: u8 },
+
+    #[error("Part {0} is given more than once")]
+    Duplicate(u8),
+
+    #[error("Missing parts: {}", .0.iter().map(u8::to_string).collect::<Vec<_>>().join(", "))]
+    Missing(Vec<u8>),
+}
+
+/// Ошибки экспорта контейнеров в форматы других клиентов
+#[derive(Debug, Error)]
+pub enum ExportError {
+    #[error("No {0} container found in config")]
+    NoContainer(&'static str),
+
+    #[error("Container is missing required field '{0}'")]
+    MissingField(String),
+
+    #[error("Invalid container config: {0}")]
+    InvalidConfig(String),
+
+    #[error("Failed to decode embedded payload: {0}")]
+    InnerPayload(#[from] DecodeError),
+}
+
+/// Ошибки проверки по JSON Schema
+#[derive(Debug, Error)]
+pub enum SchemaError {
+    #[error("Invalid JSON schema: {0}")]
+    Invalid(String),
+}
+
+/// Ошибки преобразования конфигурации в YAML/TOML и обратно
+#[derive(Debug, Error)]
+pub enum ConvertError {
+    #[error("JSON conversion failed: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("YAML conversion failed: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+
+    #[error("TOML serialization failed: {0}")]
+    Toml(#[from] toml::ser::Error),
+
+    #[error("Invalid TOML: {0}")]
+    TomlParse(#[from] toml::de::Error),
+
+    #[error("TOML cannot represent null (at '{0}')")]
+    TomlNull(String),
+
+    #[error("TOML document must be a table, got {0}")]
+    TomlRoot(&'static str),
+}
+
+/// Причина, по которой тип входных данных не удалось определить
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum UnknownInput {
+    #[error("input is empty")]
+    Empty,
+
+    #[error("starts with '{0}' which is not a recognized VPN scheme")]
+    UnsupportedScheme(String),
+
+    #[error("not valid JSON: {0}")]
+    InvalidJson(String),
+
+    #[error("unexpected character {ch:?} at position {position}: neither JSON nor base64")]
+    InvalidCharacter { ch: char, position: usize },
+
+    #[error("too short for a base64 payload ({0} characters, need at least {1})")]
+    TooShort(usize, usize),
+
+    #[error("not valid base64: {0}")]
+    InvalidBase64(String),
+}
@@ -0,0 +1,322 @@
+use crate::error::{DecodeError, ExportError};
+use crate::{decode_payload, decompress_data, decompress_gzip, encode_base64, PayloadSettings, GZIP_MAGIC};
+use base64::{
+    engine::general_purpose::{STANDARD_NO_PAD, URL_SAFE_NO_PAD},
+    Engine,
+};
+use serde_json::{Map, Value};
+use std::fmt::Write;
+
+/// Формат экспорта контейнера из конфигурации Amnezia
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Файл `wg-quick` (`[Interface]` / `[Peer]`)
+    WireGuard,
+    /// Профиль OpenVPN (`.ovpn`)
+    OpenVpn,
+    /// Ссылка Shadowsocks `ss://`
+    Shadowsocks,
+}
+
+/// Экспортирует контейнер конфигурации Amnezia в выбранный формат
+pub fn export(config: &Value, format: ExportFormat) -> Result<String, ExportError> {
+    match format {
+        ExportFormat::WireGuard => to_wireguard(config),
+        ExportFormat::OpenVpn => to_openvpn(config),
+        ExportFormat::Shadowsocks => to_shadowsocks(config),
+    }
+}
+
+/// Параметры обфускации AmneziaWG, которые переносятся в `[Interface]`
+const AWG_PARAMS: &[&str] = &["Jc", "Jmin", "Jmax", "S1", "S2", "H1", "H2", "H3", "H4"];
+
+/// Формирует конфигурацию `wg-quick` из контейнера WireGuard или AmneziaWG.
+///
+/// Поля Amnezia берутся из `last_config` контейнера: `client_priv_key` →
+/// `PrivateKey`, `client_ip` → `Address`, `server_pub_key` → `PublicKey`,
+/// `psk_key` → `PresharedKey`, `hostName`:`port` → `Endpoint`, `allowed_ips` →
+/// `AllowedIPs`; DNS — из `dns1`/`dns2` верхнего уровня. Для AmneziaWG также
+/// переносятся параметры обфускации (`Jc`, `S1`, `H1` и т.д.)
+pub fn to_wireguard(config: &Value) -> Result<String, ExportError> {
+    let (protocol, settings) = find_container(config, &["wireguard", "awg"])
+        .ok_or(ExportError::NoContainer("WireGuard"))?;
+    let settings = container_settings(settings)?;
+
+    let field = |name: &str| settings.get(name).and_then(value_to_string);
+    let required = |name: &str| field(name).ok_or_else(|| ExportError::MissingField(name.to_string()));
+
+    let mut address = required("client_ip")?;
+    if !address.contains('/') {
+        address.push_str("/32");
+    }
+
+    let host = field("hostName")
+        .or_else(|| config.get("hostName").and_then(value_to_string))
+        .ok_or_else(|| ExportError::MissingField("hostName".to_string()))?;
+    let port = required("port")?;
+
+    let allowed_ips = match settings.get("allowed_ips") {
+        Some(Value::Array(ips)) => ips.iter().filter_map(value_to_string).collect::<Vec<_>>().join(", "),
+        Some(value) => value_to_string(value).unwrap_or_default(),
+        None => "0.0.0.0/0, ::/0".to_string(),
+    };
+
+    let dns: Vec<String> = ["dns1", "dns2"]
+        .iter()
+        .filter_map(|key| config.get(*key).and_then(value_to_string))
+        .filter(|dns| !dns.is_empty())
+        .collect();
+
+    let mut out = String::new();
+    // Запись в String не может завершиться ошибкой
+    let _ = writeln!(out, "[Interface]");
+    let _ = writeln!(out, "PrivateKey = {}", required("client_priv_key")?);
+    let _ = writeln!(out, "Address = {}", address);
+    if !dns.is_empty() {
+        let _ = writeln!(out, "DNS = {}", dns.join(", "));
+    }
+    if let Some(mtu) = field("mtu") {
+        let _ = writeln!(out, "MTU = {}", mtu);
+    }
+    if protocol == "awg" {
+        for param in AWG_PARAMS {
+            if let Some(value) = field(param) {
+                let _ = writeln!(out, "{} = {}", param, value);
+            }
+        }
+    }
+
+    let _ = writeln!(out);
+    let _ = writeln!(out, "[Peer]");
+    let _ = writeln!(out, "PublicKey = {}", required("server_pub_key")?);
+    if let Some(psk) = field("psk_key") {
+        let _ = writeln!(out, "PresharedKey = {}", psk);
+    }
+    let _ = writeln!(out, "AllowedIPs = {}", allowed_ips);
+    let _ = writeln!(out, "Endpoint = {}:{}", host, port);
+    if let Some(keepalive) = field("persistent_keep_alive") {
+        let _ = writeln!(out, "PersistentKeepalive = {}", keepalive);
+    }
+
+    Ok(out)
+}
+
+/// Извлекает профиль `.ovpn` из контейнера OpenVPN.
+///
+/// Профиль хранится в поле `config` настроек контейнера: либо открытым
+/// текстом, либо в виде Base64 блоба, внутри которого gzip, zlib или
+/// полезная нагрузка с 4-байтовым заголовком длины, как у самого `vpn://`
+pub fn to_openvpn(config: &Value) -> Result<String, ExportError> {
+    let (_, settings) = find_container(config, &["openvpn"]).ok_or(ExportError::NoContainer("OpenVPN"))?;
+    let settings = container_settings(settings)?;
+    let blob = settings
+        .get("config")
+        .and_then(Value::as_str)
+        .ok_or_else(|| ExportError::MissingField("config".to_string()))?;
+
+    let mut profile = decode_inner_blob(blob)?;
+    if !profile.ends_with('\n') {
+        profile.push('\n');
+    }
+    Ok(profile)
+}
+
+/// Формирует ссылку `ss://` из контейнера Shadowsocks.
+///
+/// Ссылка — Base64 URL-safe от `method:password@host:port`. Метод берётся из
+/// `method` или `cipher`, адрес — из `server` или `hostName`, порт — из
+/// `server_port` или `port`
+pub fn to_shadowsocks(config: &Value) -> Result<String, ExportError> {
+    let (_, settings) = find_container(config, &["shadowsocks"])
+        .ok_or(ExportError::NoContainer("Shadowsocks"))?;
+    let settings = container_settings(settings)?;
+
+    let field = |names: &[&str]| names.iter().find_map(|name| settings.get(*name).and_then(value_to_string));
+    let required = |names: &[&str]| field(names).ok_or_else(|| ExportError::MissingField(names[0].to_string()));
+
+    let method = required(&["method", "cipher"])?;
+    let password = required(&["password"])?;
+    let host = field(&["server", "hostName"])
+        .or_else(|| config.get("hostName").and_then(value_to_string))
+        .ok_or_else(|| ExportError::MissingField("hostName".to_string()))?;
+    let port = required(&["server_port", "port"])?;
+
+    let userinfo = format!("{}:{}@{}:{}", method, password, host, port);
+    Ok(format!("ss://{}\n", encode_base64(userinfo.as_bytes())))
+}
+
+/// Декодирует вложенный блоб профиля. Текст с пробельными символами
+/// считается уже готовым профилем, иначе пробуем Base64 и распаковку
+fn decode_inner_blob(blob: &str) -> Result<String, ExportError> {
+    let blob = blob.trim();
+    if blob.contains(char::is_whitespace) {
+        return Ok(blob.to_string());
+    }
+
+    let unpadded = blob.trim_end_matches('=');
+    let bytes = URL_SAFE_NO_PAD
+        .decode(unpadded)
+        .or_else(|_| STANDARD_NO_PAD.decode(unpadded))
+        .map_err(DecodeError::from)?;
+
+    let text = if bytes.starts_with(&GZIP_MAGIC) {
+        decompress_gzip(&bytes)?
+    } else if let Ok(inflated) = decompress_data(&bytes) {
+        inflated
+    } else {
+        decode_payload(&bytes, &PayloadSettings::default(), Ok)?.0
+    };
+    Ok(String::from_utf8(text).map_err(DecodeError::from)?)
+}
+
+/// Находит контейнер с настройками одного из протоколов и возвращает
+/// имя протокола и его настройки
+fn find_container<'a>(config: &'a Value, protocols: &[&'static str]) -> Option<(&'static str, &'a Value)> {
+    let containers = config.get("containers")?.as_array()?;
+    containers.iter().find_map(|container| {
+        protocols
+            .iter()
+            .find_map(|protocol| container.get(*protocol).map(|settings| (*protocol, settings)))
+    })
+}
+
+/// Возвращает настройки протокола: содержимое `last_config` (JSON строка,
+/// Base64 блоб с JSON или объект), а при его отсутствии — сам объект настроек
+fn container_settings(settings: &Value) -> Result<Map<String, Value>, ExportError> {
+    let mut merged = settings.as_object().cloned().unwrap_or_default();
+    match settings.get("last_config") {
+        Some(Value::String(text)) => {
+            let last_config: Value = serde_json::from_str(text)
+                .or_else(|e| {
+                    decode_inner_blob(text)
+                        .ok()
+                        .and_then(|inner| serde_json::from_str(&inner).ok())
+                        .ok_or(e)
+                })
+                .map_err(|e| ExportError::InvalidConfig(format!("last_config: {}", e)))?;
+            if let Value::Object(map) = last_config {
+                merged.extend(map);
+            }
+        }
+        Some(Value::Object(map)) => merged.extend(map.clone()),
+        _ => {}
+    }
+    Ok(merged)
+}
+
+/// Представляет скалярное значение JSON строкой
+fn value_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Number(number) => Some(number.to_string()),
+        Value::Bool(flag) => Some(flag.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn wireguard_config() -> Value {
+        let last_config = json!({
+            "client_priv_key": "cPrivKey=",
+            "client_ip": "10.8.1.2",
+            "server_pub_key": "sPubKey=",
+            "psk_key": "pskKey=",
+            "hostName": "vpn.example.com",
+            "port": 51820,
+            "allowed_ips": ["0.0.0.0/0", "::/0"],
+            "persistent_keep_alive": "25"
+        });
+        json!({
+            "containers": [{
+                "container": "amnezia-wireguard",
+                "wireguard": {"last_config": last_config.to_string(), "port": "51820"}
+            }],
+            "defaultContainer": "amnezia-wireguard",
+            "dns1": "1.1.1.1",
+            "dns2": "1.0.0.1",
+            "hostName": "vpn.example.com"
+        })
+    }
+
+    #[test]
+    fn test_to_wireguard() {
+        let conf = to_wireguard(&wireguard_config()).unwrap();
+        assert_eq!(conf, "\
+[Interface]
+PrivateKey = cPrivKey=
+Address = 10.8.1.2/32
+DNS = 1.1.1.1, 1.0.0.1
+
+[Peer]
+PublicKey = sPubKey=
+PresharedKey = pskKey=
+AllowedIPs = 0.0.0.0/0, ::/0
+Endpoint = vpn.example.com:51820
+PersistentKeepalive = 25
+");
+    }
+
+    #[test]
+    fn test_to_openvpn() {
+        let profile = "client\ndev tun\nremote vpn.example.com 1194 udp\n";
+        let gzipped = crate::compress_gzip(profile.as_bytes(), flate2::Compression::default()).unwrap();
+
+        // Открытый текст и вложенный Base64 + gzip дают одинаковый профиль
+        for blob in [profile.to_string(), STANDARD_NO_PAD.encode(&gzipped)] {
+            let last_config = json!({"config": blob});
+            let config = json!({
+                "containers": [{"container": "amnezia-openvpn", "openvpn": {"last_config": last_config.to_string()}}]
+            });
+            assert_eq!(to_openvpn(&config).unwrap(), profile);
+        }
+
+        let config = json!({"containers": [{"container": "amnezia-wireguard", "wireguard": {}}]});
+        assert!(matches!(to_openvpn(&config), Err(ExportError::NoContainer("OpenVPN"))));
+    }
+
+    #[test]
+    fn test_to_shadowsocks() {
+        let last_config = json!({
+            "method": "chacha20-ietf-poly1305",
+            "password": "secret",
+            "server": "ss.example.com",
+            "server_port": "6789"
+        });
+        let expected = format!("ss://{}\n", URL_SAFE_NO_PAD.encode("chacha20-ietf-poly1305:secret@ss.example.com:6789"));
+
+        // JSON строкой и Base64 + gzip блобом дают одну и ту же ссылку
+        let gzipped = crate::compress_gzip(last_config.to_string().as_bytes(), flate2::Compression::default()).unwrap();
+        for blob in [last_config.to_string(), STANDARD_NO_PAD.encode(&gzipped)] {
+            let config = json!({
+                "containers": [{"container": "amnezia-shadowsocks", "shadowsocks": {"last_config": blob}}]
+            });
+            assert_eq!(to_shadowsocks(&config).unwrap(), expected);
+        }
+
+        // Поля прямо в настройках, адрес сервера — из верхнего уровня
+        let config = json!({
+            "hostName": "vpn.example.com",
+            "containers": [{"shadowsocks": {"cipher": "aes-256-gcm", "password": "p", "port": 8388}}]
+        });
+        let link = to_shadowsocks(&config).unwrap();
+        let decoded = URL_SAFE_NO_PAD.decode(link.trim().strip_prefix("ss://").unwrap()).unwrap();
+        assert_eq!(decoded, b"aes-256-gcm:p@vpn.example.com:8388");
+
+        assert!(matches!(to_shadowsocks(&wireguard_config()), Err(ExportError::NoContainer("Shadowsocks"))));
+        let config = json!({"containers": [{"shadowsocks": {"method": "aes-256-gcm", "port": 1}}]});
+        assert!(matches!(to_shadowsocks(&config), Err(ExportError::MissingField(field)) if field == "password"));
+    }
+
+    #[test]
+    fn test_to_wireguard_errors() {
+        let config = json!({"containers": [{"container": "amnezia-openvpn", "openvpn": {}}]});
+        assert!(matches!(to_wireguard(&config), Err(ExportError::NoContainer("WireGuard"))));
+
+        let config = json!({"containers": [{"wireguard": {"last_config": "{}"}}]});
+        assert!(matches!(to_wireguard(&config), Err(ExportError::MissingField(field)) if field == "client_ip"));
+    }
+}
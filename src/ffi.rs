@@ -0,0 +1,161 @@
+//! C интерфейс кодека для встраивания в C/Qt приложения (фича `ffi`).
+//!
+//! Объявления для C — в `include/amnezia.h`. Динамическая библиотека
+//! собирается отдельно, обычная сборка крейта её не создаёт:
+//!
+//! ```text
+//! cargo rustc --lib --release --no-default-features --features ffi --crate-type cdylib
+//! ```
+//!
+//! Правила владения памятью:
+//!
+//! - входные строки принадлежат вызывающему и только читаются; это должны быть
+//!   корректные C строки в UTF-8, завершённые нулём;
+//! - возвращённая строка выделена библиотекой и принадлежит вызывающему:
+//!   её нужно освободить ровно один раз через [`amnezia_string_free`], а не
+//!   `free()` из libc;
+//! - при ошибке возвращается `NULL`, а код ошибки записывается в `error`
+//!   (если указатель не `NULL`); при успехе туда записывается [`AMNEZIA_OK`]
+
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::ptr;
+
+/// Успех
+pub const AMNEZIA_OK: c_int = 0;
+/// Передан нулевой указатель на входную строку
+pub const AMNEZIA_ERR_NULL: c_int = 1;
+/// Входная строка не в UTF-8
+pub const AMNEZIA_ERR_UTF8: c_int = 2;
+/// Некорректный JSON при кодировании
+pub const AMNEZIA_ERR_JSON: c_int = 3;
+/// Ошибка кодирования
+pub const AMNEZIA_ERR_ENCODE: c_int = 4;
+/// Ошибка декодирования URL
+pub const AMNEZIA_ERR_DECODE: c_int = 5;
+
+/// Кодирует JSON конфигурацию в `vpn://` URL.
+///
+/// # Safety
+///
+/// `json` — `NULL` или указатель на C строку, завершённую нулём; `error` —
+/// `NULL` или указатель на `int`, доступный для записи
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn amnezia_encode(json: *const c_char, error: *mut c_int) -> *mut c_char {
+    // SAFETY: требования к указателям переданы вызывающему, см. `# Safety`
+    let result = unsafe { read_str(json) }.and_then(|json| {
+        let config = serde_json::from_str(crate::strip_json_comments(json)).map_err(|_| AMNEZIA_ERR_JSON)?;
+        crate::encode(&config).map_err(|_| AMNEZIA_ERR_ENCODE)
+    });
+    // SAFETY: см. `# Safety`
+    unsafe { into_c_string(result, error) }
+}
+
+/// Декодирует `vpn://` URL в отформатированный JSON.
+///
+/// # Safety
+///
+/// `url` — `NULL` или указатель на C строку, завершённую нулём; `error` —
+/// `NULL` или указатель на `int`, доступный для записи
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn amnezia_decode(url: *const c_char, error: *mut c_int) -> *mut c_char {
+    // SAFETY: требования к указателям переданы вызывающему, см. `# Safety`
+    let result = unsafe { read_str(url) }.and_then(|url| {
+        let config = crate::decode(url.trim()).map_err(|_| AMNEZIA_ERR_DECODE)?;
+        serde_json::to_string_pretty(&config).map_err(|_| AMNEZIA_ERR_DECODE)
+    });
+    // SAFETY: см. `# Safety`
+    unsafe { into_c_string(result, error) }
+}
+
+/// Освобождает строку, возвращённую [`amnezia_encode`] или [`amnezia_decode`].
+/// `NULL` допускается и игнорируется.
+///
+/// # Safety
+///
+/// `string` — `NULL` или указатель, полученный от этой библиотеки и ещё не
+/// освобождённый; после вызова указатель использовать нельзя
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn amnezia_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        // SAFETY: указатель получен из `CString::into_raw` в `into_c_string`
+        drop(unsafe { CString::from_raw(string) });
+    }
+}
+
+/// Читает C строку в UTF-8
+///
+/// # Safety
+///
+/// `text` — `NULL` или указатель на C строку, завершённую нулём
+unsafe fn read_str<'a>(text: *const c_char) -> Result<&'a str, c_int> {
+    if text.is_null() {
+        return Err(AMNEZIA_ERR_NULL);
+    }
+    // SAFETY: указатель не нулевой, остальное гарантирует вызывающий
+    unsafe { CStr::from_ptr(text) }.to_str().map_err(|_| AMNEZIA_ERR_UTF8)
+}
+
+/// Передаёт результат в C: строку во владение вызывающему, код — в `error`
+///
+/// # Safety
+///
+/// `error` — `NULL` или указатель на `int`, доступный для записи
+unsafe fn into_c_string(result: Result<String, c_int>, error: *mut c_int) -> *mut c_char {
+    // JSON и URL не содержат нулевых байтов: serde_json экранирует `\u0000`
+    let (string, code) = match result.map(|text| CString::new(text).expect("no interior NUL")) {
+        Ok(string) => (string.into_raw(), AMNEZIA_OK),
+        Err(code) => (ptr::null_mut(), code),
+    };
+    if !error.is_null() {
+        // SAFETY: указатель не нулевой и доступен для записи по контракту
+        unsafe { *error = code };
+    }
+    string
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Вызывает функцию через C интерфейс и забирает результат
+    fn call(
+        function: unsafe extern "C" fn(*const c_char, *mut c_int) -> *mut c_char,
+        input: &CStr,
+    ) -> Result<String, c_int> {
+        let mut error = -1;
+        let output = unsafe { function(input.as_ptr(), &mut error) };
+        if output.is_null() {
+            return Err(error);
+        }
+        assert_eq!(error, AMNEZIA_OK);
+        let text = unsafe { CStr::from_ptr(output) }.to_str().unwrap().to_string();
+        unsafe { amnezia_string_free(output) };
+        Ok(text)
+    }
+
+    #[test]
+    fn test_ffi_round_trip() {
+        let url = call(amnezia_encode, c"{\"hostName\": \"example.com\"}").unwrap();
+        assert!(url.starts_with(crate::PREFIX));
+
+        let url = CString::new(url).unwrap();
+        let json = call(amnezia_decode, &url).unwrap();
+        let config: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(config["hostName"], "example.com");
+    }
+
+    #[test]
+    fn test_ffi_errors() {
+        assert_eq!(call(amnezia_encode, c"{not json}"), Err(AMNEZIA_ERR_JSON));
+        assert_eq!(call(amnezia_decode, c"vpn://AAAA"), Err(AMNEZIA_ERR_DECODE));
+        assert_eq!(call(amnezia_decode, c"vpn://\xff"), Err(AMNEZIA_ERR_UTF8));
+
+        let mut error = -1;
+        assert!(unsafe { amnezia_decode(ptr::null(), &mut error) }.is_null());
+        assert_eq!(error, AMNEZIA_ERR_NULL);
+
+        // Без указателя на код ошибки и с освобождением NULL
+        assert!(unsafe { amnezia_encode(ptr::null(), ptr::null_mut()) }.is_null());
+        unsafe { amnezia_string_free(ptr::null_mut()) };
+    }
+}
@@ -0,0 +1,97 @@
+//! Подсветка синтаксиса JSON для вывода в терминал
+
+use std::io::IsTerminal;
+
+/// Цвета ANSI: ключи, строки, числа, `true`/`false`/`null`
+const KEY: &str = "\x1b[34m";
+const STRING: &str = "\x1b[32m";
+const NUMBER: &str = "\x1b[36m";
+const LITERAL: &str = "\x1b[35m";
+const RESET: &str = "\x1b[0m";
+
+/// Нужно ли подсвечивать вывод: stdout — терминал, а цвет не отключён
+/// флагом --no-color или переменной окружения `NO_COLOR` (любое непустое
+/// значение, см. no-color.org)
+pub fn color_enabled(no_color: bool) -> bool {
+    let no_color_env = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    !no_color && !no_color_env && std::io::stdout().is_terminal()
+}
+
+/// Раскрашивает сериализованный JSON. Текст разбирается посимвольно, без
+/// построения дерева, поэтому отступы и порядок ключей не меняются
+pub fn highlight_json(json: &str) -> String {
+    let mut output = String::with_capacity(json.len() * 2);
+    let mut chars = json.char_indices().peekable();
+
+    while let Some((start, ch)) = chars.next() {
+        match ch {
+            '"' => {
+                // Конец строки с учётом экранирования `\"`
+                let mut end = json.len();
+                let mut escaped = false;
+                for (index, ch) in chars.by_ref() {
+                    match ch {
+                        '\\' if !escaped => escaped = true,
+                        '"' if !escaped => {
+                            end = index + 1;
+                            break;
+                        }
+                        _ => escaped = false,
+                    }
+                }
+                // Строка, за которой следует `:`, — ключ объекта
+                let is_key = json[end..].trim_start().starts_with(':');
+                push_colored(&mut output, if is_key { KEY } else { STRING }, &json[start..end]);
+            }
+            '-' | '0'..='9' => {
+                let mut end = start + 1;
+                while let Some(&(index, ch)) = chars.peek() {
+                    if !matches!(ch, '0'..='9' | '.' | 'e' | 'E' | '+' | '-') {
+                        break;
+                    }
+                    end = index + 1;
+                    chars.next();
+                }
+                push_colored(&mut output, NUMBER, &json[start..end]);
+            }
+            't' | 'f' | 'n' => {
+                let mut end = start + 1;
+                while let Some(&(index, ch)) = chars.peek() {
+                    if !ch.is_ascii_alphabetic() {
+                        break;
+                    }
+                    end = index + 1;
+                    chars.next();
+                }
+                push_colored(&mut output, LITERAL, &json[start..end]);
+            }
+            other => output.push(other),
+        }
+    }
+    output
+}
+
+fn push_colored(output: &mut String, color: &str, text: &str) {
+    output.push_str(color);
+    output.push_str(text);
+    output.push_str(RESET);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlight_json() {
+        let json = "{\n  \"port\": 51820,\n  \"name\": \"a \\\"b\\\" : c\",\n  \"on\": [true, null, -1.5e3]\n}";
+        let highlighted = highlight_json(json);
+
+        assert!(highlighted.contains("\x1b[34m\"port\"\x1b[0m: \x1b[36m51820\x1b[0m"));
+        assert!(highlighted.contains("\x1b[32m\"a \\\"b\\\" : c\"\x1b[0m"));
+        assert!(highlighted.contains("[\x1b[35mtrue\x1b[0m, \x1b[35mnull\x1b[0m, \x1b[36m-1.5e3\x1b[0m]"));
+
+        // Без управляющих последовательностей остаётся исходный текст
+        let stripped = highlighted.replace(KEY, "").replace(STRING, "").replace(NUMBER, "").replace(LITERAL, "").replace(RESET, "");
+        assert_eq!(stripped, json);
+    }
+}
@@ -0,0 +1,2222 @@
+//! Кодирование и декодирование конфигураций AmneziaVPN в формате `vpn://`
+//!
+//! После схемы идёт Base64 URL-safe без padding от полезной нагрузки:
+//!
+//! - V1 (по умолчанию, как в AmneziaVPN): `[длина JSON: u32 BE][данные]`
+//! - V2: `[0xA2][длина JSON: u32 BE][данные]` — первый байт служит меткой
+//!   версии формата. В V1 такой первый байт означал бы JSON длиной более
+//!   2.7 ГБ, поэтому форматы не путаются
+//!
+//! - подписанный: `[0xA3][длина JSON: u32 BE][данные][HMAC-SHA256: 32 байта]`,
+//!   см. модуль [`sign`]
+//!
+//! Данные — JSON, сжатый zlib или gzip, либо несжатый JSON. При
+//! декодировании V2 и подпись распознаются по метке, V1 поддерживается всегда.
+//! Длина с порядком байтов Little Endian (см. [`Endian`]) тоже распознаётся
+//!
+//! Фича `cli` (по умолчанию) нужна только консольной утилите; библиотеке
+//! достаточно `default-features = false`. Отдельно включаются `qr-image`
+//! (PNG и чтение QR кодов), `schema` (JSON Schema) и `ffi` (C интерфейс)
+
+use base64::{
+    engine::general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD},
+    write::EncoderWriter,
+    Engine,
+};
+use flate2::write::{DeflateEncoder, GzEncoder, ZlibEncoder};
+use flate2::read::{DeflateDecoder, GzDecoder};
+use log::{debug, trace};
+use serde::de::IgnoredAny;
+use serde_json::{Value, from_str};
+use std::io::{Write, Read};
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+pub mod convert;
+pub mod diff;
+mod error;
+pub mod export;
+pub mod merge;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod nested;
+pub mod path;
+pub mod qr;
+pub mod redact;
+pub mod sign;
+pub mod split;
+pub mod template;
+pub mod validate;
+pub mod wasm;
+
+pub use error::{
+    ConvertError, DecodeError, EncodeError, ExportError, PathError, QrError, SchemaError, SplitError,
+    TemplateError, UnknownInput,
+};
+
+pub const PREFIX: &str = "vpn://";
+
+/// Метка версии в начале полезной нагрузки формата V2
+pub const V2_MAGIC: u8 = 0xA2;
+
+/// Магические байты начала gzip потока
+pub(crate) const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Формат сжатия полезной нагрузки
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    /// zlib, как в AmneziaVPN (по умолчанию)
+    #[default]
+    Zlib,
+    /// gzip, встречается у сторонних инструментов
+    Gzip,
+    /// «Сырой» deflate без заголовка и контрольной суммы zlib (`wbits=-15`
+    /// в Python/Qt); так сжимают данные некоторые сторонние генераторы
+    Deflate,
+    /// Без сжатия: заголовок + исходный JSON. Такие URL намного длиннее
+    /// и нужны в основном для отладки и просмотра содержимого
+    None,
+}
+
+/// Версия формата полезной нагрузки
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FormatVersion {
+    /// Только 4-байтовая длина, совместимо с AmneziaVPN (по умолчанию)
+    #[default]
+    V1,
+    /// Метка [`V2_MAGIC`] перед длиной
+    V2,
+}
+
+/// Порядок байтов в 4-байтовом заголовке длины
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Endian {
+    /// Big Endian, как в AmneziaVPN (по умолчанию)
+    #[default]
+    Big,
+    /// Little Endian, так пишет заголовок один из форков Amnezia
+    Little,
+}
+
+impl Endian {
+    /// Обратный порядок байтов
+    pub fn reversed(self) -> Self {
+        match self {
+            Endian::Big => Endian::Little,
+            Endian::Little => Endian::Big,
+        }
+    }
+}
+
+/// Алфавит Base64 при кодировании
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Base64Alphabet {
+    /// URL-safe (`-`/`_`) без padding, как в AmneziaVPN (по умолчанию)
+    #[default]
+    UrlSafe,
+    /// Стандартный (`+`/`/`) с padding `=`
+    Standard,
+}
+
+/// Параметры кодирования
+#[derive(Debug, Clone, Default)]
+pub struct EncodeOptions {
+    /// Уровень сжатия zlib (0–9); `None` — уровень по умолчанию
+    pub level: Option<u32>,
+    /// Сохранять исходный порядок ключей вместо сортировки.
+    /// Меняет байты на проводе (порядок сериализации), но не смысл конфигурации
+    pub preserve_order: bool,
+    /// Схема URL вместо `vpn://`, например `amnezia://`
+    pub scheme: Option<String>,
+    /// Формат сжатия
+    pub compression: Compression,
+    /// Версия формата полезной нагрузки
+    pub format_version: FormatVersion,
+    /// Алфавит Base64
+    pub base64: Base64Alphabet,
+    /// Порядок байтов заголовка длины
+    pub endian: Endian,
+    /// Сериализовать JSON без отступов. URL получается немного короче,
+    /// поэтому рекомендуется для обмена; по умолчанию JSON с отступами,
+    /// чтобы вывод `--no-compress` оставался читаемым
+    pub minify: bool,
+    /// Ключ HMAC-SHA256: если задан, полезная нагрузка подписывается
+    /// (подписанный формат заменяет `format_version`)
+    pub sign_key: Option<Vec<u8>>,
+}
+
+/// Уровень zlib в [`EncodeOptions::deterministic`]
+pub const DETERMINISTIC_LEVEL: u32 = 9;
+
+impl EncodeOptions {
+    /// Параметры воспроизводимого кодирования: одна и та же конфигурация
+    /// (с точностью до порядка ключей и форматирования исходного JSON) всегда
+    /// даёт побайтно одинаковый URL на любой платформе. Нормализация:
+    ///
+    /// - ключи всех объектов сортируются лексикографически по байтам UTF-8;
+    /// - JSON сериализуется компактно, без пробелов; числа и строки — в
+    ///   каноническом виде serde_json (`1e3` → `1000.0`, `"\u0041"` → `"A"`),
+    ///   строки в Unicode не нормализуются (для этого есть `--nfc`);
+    /// - zlib с уровнем [`DETERMINISTIC_LEVEL`] и стратегией по умолчанию из
+    ///   чисто Rust бэкенда miniz_oxide, без зависимости от системного zlib;
+    /// - формат V1, заголовок Big Endian, Base64 URL-safe без padding.
+    ///
+    /// Схема и ключ подписи задаются отдельно и на воспроизводимость не влияют
+    pub fn deterministic() -> Self {
+        Self {
+            level: Some(DETERMINISTIC_LEVEL),
+            preserve_order: false,
+            compression: Compression::Zlib,
+            format_version: FormatVersion::V1,
+            base64: Base64Alphabet::UrlSafe,
+            endian: Endian::Big,
+            minify: true,
+            ..Default::default()
+        }
+    }
+}
+
+/// Параметры декодирования
+#[derive(Debug, Clone)]
+pub struct DecodeOptions {
+    /// Ожидаемая схема URL; `None` — `vpn://`
+    pub scheme: Option<String>,
+    /// Декодировать повторно, пока результат — снова VPN URL
+    /// (случайно закодированный дважды), но не глубже [`MAX_LAYERS`]
+    pub recursive: bool,
+    /// Ключ HMAC-SHA256: если задан, URL обязан быть подписан этим ключом
+    pub verify_key: Option<Vec<u8>>,
+    /// Максимальный размер распакованного JSON в байтах; защищает от
+    /// «zip-бомб», которые из короткого URL разворачиваются в гигабайты
+    pub max_size: usize,
+    /// Порядок байтов заголовка, который проверяется первым; если длина не
+    /// сходится, пробуется обратный
+    pub endian: Endian,
+    /// Принимать данные, длина которых не совпадает с заголовком.
+    /// Отключает проверку целостности, см. [`repair_url`]
+    pub repair: bool,
+    /// Вход — только Base64 полезная нагрузка без схемы URL: схема не
+    /// ожидается и не удаляется. При рекурсивном декодировании относится
+    /// только к внешнему слою
+    pub no_prefix: bool,
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self {
+            scheme: None,
+            recursive: false,
+            verify_key: None,
+            max_size: DEFAULT_MAX_SIZE,
+            endian: Endian::Big,
+            repair: false,
+            no_prefix: false,
+        }
+    }
+}
+
+/// Параметры разбора полезной нагрузки, общие для всех форматов
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PayloadSettings {
+    /// Ограничение размера распакованных данных
+    pub(crate) max_size: usize,
+    /// Порядок байтов заголовка, который проверяется первым
+    pub(crate) endian: Endian,
+    /// Не сверять длину данных с заголовком
+    pub(crate) repair: bool,
+}
+
+impl Default for PayloadSettings {
+    fn default() -> Self {
+        Self { max_size: DEFAULT_MAX_SIZE, endian: Endian::Big, repair: false }
+    }
+}
+
+impl From<&DecodeOptions> for PayloadSettings {
+    fn from(options: &DecodeOptions) -> Self {
+        Self { max_size: options.max_size, endian: options.endian, repair: options.repair }
+    }
+}
+
+/// Ограничение размера распакованных данных по умолчанию: 16 МиБ
+pub const DEFAULT_MAX_SIZE: usize = 16 * 1024 * 1024;
+
+/// Максимальное число вложенных слоёв при рекурсивном декодировании
+pub const MAX_LAYERS: usize = 5;
+
+/// Размеры промежуточных данных при кодировании
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncodeStats {
+    /// Длина исходного JSON, байт
+    pub json_len: usize,
+    /// Длина сжатых данных (без заголовка), байт
+    pub compressed_len: usize,
+    /// Длина итогового URL, символов
+    pub url_len: usize,
+}
+
+impl EncodeStats {
+    /// Коэффициент сжатия: во сколько раз сжатые данные меньше исходного JSON
+    pub fn ratio(&self) -> f64 {
+        self.json_len as f64 / self.compressed_len.max(1) as f64
+    }
+}
+
+/// Размеры промежуточных данных при декодировании
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeStats {
+    /// Длина данных после декодирования Base64, байт
+    pub payload_len: usize,
+    /// Длина JSON после распаковки, байт
+    pub json_len: usize,
+    /// Сколько слоёв `vpn://` было снято (1 — обычный URL)
+    pub layers: usize,
+}
+
+/// Формат полезной нагрузки, распознанный при декодировании
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Заголовок длины и данные
+    V1,
+    /// Метка [`V2_MAGIC`], заголовок длины и данные
+    V2,
+    /// JSON без заголовка (старые инструменты)
+    Plain,
+}
+
+/// Подробный результат декодирования
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodeResult {
+    /// Декодированная конфигурация
+    pub value: Value,
+    /// Формат полезной нагрузки
+    pub format: Format,
+    /// Формат сжатия
+    pub compression: Compression,
+    /// Была ли полезная нагрузка подписана
+    pub signed: bool,
+    /// Алфавит Base64 в URL
+    pub alphabet: Base64Alphabet,
+    /// Размеры промежуточных данных
+    pub stats: DecodeStats,
+}
+
+/// Преобразует JSON конфигурацию в VPN URL
+pub fn encode(config: &Value) -> Result<String, EncodeError> {
+    encode_with_options(config, &EncodeOptions::default())
+}
+
+/// Преобразует JSON конфигурацию в VPN URL с заданными параметрами
+pub fn encode_with_options(config: &Value, options: &EncodeOptions) -> Result<String, EncodeError> {
+    encode_with_stats(config, options).map(|(url, _)| url)
+}
+
+/// Преобразует JSON конфигурацию в VPN URL и возвращает размеры промежуточных данных
+pub fn encode_with_stats(
+    config: &Value,
+    options: &EncodeOptions,
+) -> Result<(String, EncodeStats), EncodeError> {
+    let mut url = Vec::new();
+    let stats = encode_writer_with_options(config, options, &mut url)?;
+    let url = String::from_utf8(url).expect("VPN URL consists of ASCII characters only");
+    Ok((url, stats))
+}
+
+/// Читает JSON конфигурацию из `reader` и записывает VPN URL в `writer`
+pub fn encode_from_reader<R: Read, W: Write>(reader: R, writer: W) -> Result<EncodeStats, EncodeError> {
+    encode_from_reader_with_options(reader, &EncodeOptions::default(), writer)
+}
+
+/// Читает JSON конфигурацию из `reader` и записывает VPN URL в `writer`
+/// с заданными параметрами
+pub fn encode_from_reader_with_options<R: Read, W: Write>(
+    reader: R,
+    options: &EncodeOptions,
+    writer: W,
+) -> Result<EncodeStats, EncodeError> {
+    let config: Value = serde_json::from_reader(reader)?;
+    encode_writer_with_options(&config, options, writer)
+}
+
+/// Потоково кодирует JSON конфигурацию в VPN URL, записывая его в `writer`
+pub fn encode_writer<W: Write>(config: &Value, writer: W) -> Result<EncodeStats, EncodeError> {
+    encode_writer_with_options(config, &EncodeOptions::default(), writer)
+}
+
+/// Потоково кодирует JSON конфигурацию в VPN URL с заданными параметрами.
+///
+/// JSON сериализуется сразу в компрессор, а сжатые данные — сразу в Base64
+/// писатель, без промежуточных строки JSON и объединённого буфера
+pub fn encode_writer_with_options<W: Write>(
+    config: &Value,
+    options: &EncodeOptions,
+    writer: W,
+) -> Result<EncodeStats, EncodeError> {
+    let level = options.level.map(flate2::Compression::new).unwrap_or_default();
+    let mut compressor = Compressor::new(options.compression, level, Vec::new());
+    encode_with_compressor(config, options, &mut compressor, Vec::new(), writer).map(|(stats, _)| stats)
+}
+
+/// Контекст для кодирования множества конфигураций с одними параметрами.
+///
+/// Между вызовами сохраняет состояние компрессора и буфер сжатых данных,
+/// поэтому в пакетном режиме не выделяет их заново для каждого элемента
+pub struct EncodeContext {
+    options: EncodeOptions,
+    compressor: Compressor,
+    buffer: Vec<u8>,
+}
+
+impl EncodeContext {
+    pub fn new(options: EncodeOptions) -> Self {
+        let level = options.level.map(flate2::Compression::new).unwrap_or_default();
+        let compressor = Compressor::new(options.compression, level, Vec::new());
+        EncodeContext { options, compressor, buffer: Vec::new() }
+    }
+
+    /// Кодирует конфигурацию так же, как [`encode_with_options`]
+    pub fn encode(&mut self, config: &Value) -> Result<String, EncodeError> {
+        let mut url = Vec::new();
+        let buffer = std::mem::take(&mut self.buffer);
+        let (_, buffer) = encode_with_compressor(config, &self.options, &mut self.compressor, buffer, &mut url)?;
+        self.buffer = buffer;
+        Ok(String::from_utf8(url).expect("VPN URL consists of ASCII characters only"))
+    }
+}
+
+/// Кодирует конфигурацию, используя готовый компрессор. `spare` становится
+/// новым буфером компрессора; возвращается буфер со сжатыми данными, чтобы
+/// вызывающий мог переиспользовать его
+fn encode_with_compressor<W: Write>(
+    config: &Value,
+    options: &EncodeOptions,
+    compressor: &mut Compressor,
+    mut spare: Vec<u8>,
+    writer: W,
+) -> Result<(EncodeStats, Vec<u8>), EncodeError> {
+    let sorted;
+    let config = if options.preserve_order {
+        config
+    } else {
+        let mut copy = config.clone();
+        sort_keys(&mut copy);
+        sorted = copy;
+        &sorted
+    };
+
+    // 1–2. Сериализация в JSON (с отступами или компактно) прямо в компрессор
+    let level = options.level.map(flate2::Compression::new).unwrap_or_default();
+    let mut counter = CountingWriter::new(&mut *compressor);
+    if options.minify {
+        serde_json::to_writer(&mut counter, config)?;
+    } else {
+        serde_json::to_writer_pretty(&mut counter, config)?;
+    }
+    let original_data_len = counter.count;
+    spare.clear();
+    let compressed_data = compressor.finish_reset(spare, level)?;
+
+    // 3. Создание заголовка (4 байта, по умолчанию Big Endian)
+    let header = create_header_with(original_data_len as u32, options.endian);
+
+    // 4–6. Префикс, затем Base64 URL-safe (без padding) от заголовка и сжатых данных
+    let prefix = options.scheme.as_deref().unwrap_or(PREFIX);
+    let mut output = CountingWriter::new(writer);
+    output.write_all(prefix.as_bytes())?;
+    {
+        let engine = match options.base64 {
+            Base64Alphabet::UrlSafe => &URL_SAFE_NO_PAD,
+            Base64Alphabet::Standard => &STANDARD,
+        };
+        let mut base64_writer = EncoderWriter::new(&mut output, engine);
+        if let Some(key) = &options.sign_key {
+            let signed = [&header[..], &compressed_data].concat();
+            base64_writer.write_all(&[sign::SIGNED_MAGIC])?;
+            base64_writer.write_all(&signed)?;
+            base64_writer.write_all(&sign::compute_tag(key, &signed))?;
+        } else {
+            if options.format_version == FormatVersion::V2 {
+                base64_writer.write_all(&[V2_MAGIC])?;
+            }
+            base64_writer.write_all(&header)?;
+            base64_writer.write_all(&compressed_data)?;
+        }
+        base64_writer.finish()?;
+    }
+
+    debug!(
+        "Закодировано: JSON {} байт, сжато {:?} до {} байт, URL {} символов",
+        original_data_len,
+        options.compression,
+        compressed_data.len(),
+        output.count
+    );
+    let stats = EncodeStats {
+        json_len: original_data_len,
+        compressed_len: compressed_data.len(),
+        url_len: output.count,
+    };
+    Ok((stats, compressed_data))
+}
+
+/// Компрессор для выбранного формата сжатия
+enum Compressor {
+    Zlib(ZlibEncoder<Vec<u8>>),
+    Gzip(GzEncoder<Vec<u8>>),
+    Deflate(DeflateEncoder<Vec<u8>>),
+    None(Vec<u8>),
+}
+
+impl Compressor {
+    fn new(compression: Compression, level: flate2::Compression, buffer: Vec<u8>) -> Self {
+        match compression {
+            Compression::Zlib => Compressor::Zlib(ZlibEncoder::new(buffer, level)),
+            Compression::Gzip => Compressor::Gzip(GzEncoder::new(buffer, level)),
+            Compression::Deflate => Compressor::Deflate(DeflateEncoder::new(buffer, level)),
+            Compression::None => Compressor::None(buffer),
+        }
+    }
+
+    /// Завершает поток и возвращает сжатые данные, а компрессор готовит к
+    /// следующему потоку с пустым буфером `spare`. Состояние zlib/deflate
+    /// сбрасывается без повторного выделения памяти
+    fn finish_reset(&mut self, spare: Vec<u8>, level: flate2::Compression) -> std::io::Result<Vec<u8>> {
+        match self {
+            Compressor::Zlib(encoder) => encoder.reset(spare),
+            Compressor::Deflate(encoder) => encoder.reset(spare),
+            Compressor::Gzip(encoder) => {
+                std::mem::replace(encoder, GzEncoder::new(spare, level)).finish()
+            }
+            Compressor::None(data) => Ok(std::mem::replace(data, spare)),
+        }
+    }
+}
+
+impl Write for Compressor {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Compressor::Zlib(encoder) => encoder.write(buf),
+            Compressor::Gzip(encoder) => encoder.write(buf),
+            Compressor::Deflate(encoder) => encoder.write(buf),
+            Compressor::None(data) => data.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Compressor::Zlib(encoder) => encoder.flush(),
+            Compressor::Gzip(encoder) => encoder.flush(),
+            Compressor::Deflate(encoder) => encoder.flush(),
+            Compressor::None(data) => data.flush(),
+        }
+    }
+}
+
+/// Писатель, подсчитывающий количество записанных байт
+struct CountingWriter<W> {
+    inner: W,
+    count: usize,
+}
+
+impl<W> CountingWriter<W> {
+    fn new(inner: W) -> Self {
+        CountingWriter { inner, count: 0 }
+    }
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count += written;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Декодирует VPN URL обратно в JSON конфигурацию
+pub fn decode(vpn_url: &str) -> Result<Value, DecodeError> {
+    decode_with_options(vpn_url, &DecodeOptions::default())
+}
+
+/// Декодирует VPN URL обратно в JSON конфигурацию с заданными параметрами
+pub fn decode_with_options(vpn_url: &str, options: &DecodeOptions) -> Result<Value, DecodeError> {
+    decode_with_stats(vpn_url, options).map(|(value, _)| value)
+}
+
+/// Декодирует VPN URL и возвращает размеры промежуточных данных
+pub fn decode_with_stats(
+    vpn_url: &str,
+    options: &DecodeOptions,
+) -> Result<(Value, DecodeStats), DecodeError> {
+    decode_detailed_with_options(vpn_url, options).map(|result| (result.value, result.stats))
+}
+
+/// Декодирует VPN URL и сообщает, в каком формате он был закодирован
+pub fn decode_detailed(vpn_url: &str) -> Result<DecodeResult, DecodeError> {
+    decode_detailed_with_options(vpn_url, &DecodeOptions::default())
+}
+
+/// Декодирует VPN URL с заданными параметрами и сообщает, в каком формате
+/// он был закодирован. При рекурсивном декодировании формат относится к
+/// самому внутреннему слою
+pub fn decode_detailed_with_options(
+    vpn_url: &str,
+    options: &DecodeOptions,
+) -> Result<DecodeResult, DecodeError> {
+    let prefix = options.scheme.as_deref().unwrap_or(PREFIX);
+    let mut url = vpn_url.to_string();
+    let mut layers = 0;
+    loop {
+        let payload = if layers > 0 && options.no_prefix {
+            // Вложенные слои — полноценные URL со схемой
+            decode_url_payload(&url, &DecodeOptions { no_prefix: false, ..options.clone() })?
+        } else {
+            decode_url_payload(&url, options)?
+        };
+        let ((value, json_len), format, compression) = decode_payload(&payload.data, &options.into(), |json| {
+            let json_len = json.len();
+            Ok((parse_json(json)?, json_len))
+        })?;
+        layers += 1;
+
+        // Результат — снова URL: конфигурация была закодирована повторно
+        match value {
+            Value::String(inner) if options.recursive && inner.starts_with(prefix) => {
+                if layers >= MAX_LAYERS {
+                    return Err(DecodeError::TooManyLayers(MAX_LAYERS));
+                }
+                url = inner;
+            }
+            value => {
+                let stats = DecodeStats {
+                    payload_len: payload.data.len(),
+                    json_len,
+                    layers,
+                };
+                return Ok(DecodeResult {
+                    value,
+                    format,
+                    compression,
+                    signed: payload.signed,
+                    alphabet: payload.alphabet,
+                    stats,
+                });
+            }
+        }
+    }
+}
+
+/// Декодирует VPN URL и записывает JSON в `writer` байт в байт так, как он
+/// хранится в URL, без разбора в `Value` и повторной сериализации
+pub fn decode_to_writer<W: Write>(vpn_url: &str, writer: W) -> Result<usize, DecodeError> {
+    decode_to_writer_with_options(vpn_url, &DecodeOptions::default(), writer)
+}
+
+/// Декодирует VPN URL в `writer` байт в байт с заданными параметрами.
+/// Возвращает количество записанных байт
+pub fn decode_to_writer_with_options<W: Write>(
+    vpn_url: &str,
+    options: &DecodeOptions,
+    mut writer: W,
+) -> Result<usize, DecodeError> {
+    let payload = decode_url_payload(vpn_url, options)?;
+    let (json, ..) = decode_payload(&payload.data, &options.into(), |json| {
+        // Проверяем, что это JSON, не строя дерево значений
+        let json_string = String::from_utf8(json)?;
+        from_str::<IgnoredAny>(&json_string)?;
+        Ok(json_string.into_bytes())
+    })?;
+
+    writer.write_all(&json).map_err(DecodeError::Output)?;
+    Ok(json.len())
+}
+
+/// Читает VPN URL из `reader` и записывает JSON в `writer` так, как он
+/// хранится в URL. Возвращает количество записанных байт
+pub fn decode_from_reader<R: Read, W: Write>(reader: R, writer: W) -> Result<usize, DecodeError> {
+    decode_from_reader_with_options(reader, &DecodeOptions::default(), writer)
+}
+
+/// Читает VPN URL из `reader` и записывает JSON в `writer` с заданными
+/// параметрами. Пробелы и переводы строк вокруг URL игнорируются
+pub fn decode_from_reader_with_options<R: Read, W: Write>(
+    mut reader: R,
+    options: &DecodeOptions,
+    writer: W,
+) -> Result<usize, DecodeError> {
+    let mut vpn_url = String::new();
+    reader.read_to_string(&mut vpn_url).map_err(DecodeError::Input)?;
+    decode_to_writer_with_options(vpn_url.trim(), options, writer)
+}
+
+/// Результат [`repair_url`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Repair {
+    /// URL с исправленным заголовком (или исходный, если исправлять нечего)
+    pub url: String,
+    /// Длина из исходного заголовка; `None` — у формата нет заголовка
+    pub declared: Option<u32>,
+    /// Фактическая длина JSON после распаковки
+    pub actual: usize,
+}
+
+impl Repair {
+    /// Был ли заголовок исправлен
+    pub fn changed(&self) -> bool {
+        self.declared.is_some_and(|declared| declared as usize != self.actual)
+    }
+}
+
+/// Пересчитывает заголовок длины по фактически распакованным данным.
+///
+/// Проверка целостности при этом отключается: данные считаются верными, а
+/// неверным — заголовок (например, после ручной правки полезной нагрузки).
+/// Сжатые данные, формат и алфавит Base64 сохраняются. Подписанные URL не
+/// исправляются: подпись распространяется и на заголовок
+pub fn repair_url(vpn_url: &str, options: &DecodeOptions) -> Result<Repair, DecodeError> {
+    let payload = decode_url_payload(vpn_url.trim(), options)?;
+    if payload.signed {
+        return Err(DecodeError::RepairSigned);
+    }
+
+    let settings = PayloadSettings { repair: true, ..PayloadSettings::from(options) };
+    let (actual, format, _) = decode_payload(&payload.data, &settings, |json| {
+        let json_len = json.len();
+        parse_json(json)?;
+        Ok(json_len)
+    })?;
+
+    let offset = match format {
+        Format::V1 => 0,
+        Format::V2 => 1,
+        Format::Plain => {
+            let url = vpn_url.trim().to_string();
+            return Ok(Repair { url, declared: None, actual });
+        }
+    };
+    let declared = read_header_with(&payload.data[offset..], options.endian)?;
+    let mut data = payload.data;
+    data[offset..offset + 4].copy_from_slice(&create_header_with(actual as u32, options.endian));
+
+    let encoded = match payload.alphabet {
+        Base64Alphabet::UrlSafe => URL_SAFE_NO_PAD.encode(&data),
+        Base64Alphabet::Standard => STANDARD.encode(&data),
+    };
+    let prefix = options.scheme.as_deref().unwrap_or(PREFIX);
+    Ok(Repair { url: format!("{}{}", prefix, encoded), declared: Some(declared), actual })
+}
+
+/// Разбор слоёв URL, см. [`explain_url`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Explanation {
+    /// Декодированная конфигурация
+    pub value: Value,
+    /// Длина Base64 текста после схемы, символов
+    pub encoded_len: usize,
+    /// Полезная нагрузка после Base64, без тега подписи
+    pub payload: Vec<u8>,
+    /// Формат полезной нагрузки
+    pub format: Format,
+    /// Формат сжатия
+    pub compression: Compression,
+    /// Была ли полезная нагрузка подписана
+    pub signed: bool,
+    /// Алфавит Base64 в URL
+    pub alphabet: Base64Alphabet,
+    /// Длина из заголовка в порядке байтов из настроек; `None` — у формата
+    /// нет заголовка
+    pub declared: Option<u32>,
+    /// Длина данных после заголовка (сжатых или нет), байт
+    pub compressed_len: usize,
+    /// Длина JSON после распаковки, байт
+    pub json_len: usize,
+    /// Совпала ли длина из заголовка (в любом порядке байтов) с длиной
+    /// JSON; `None` — проверять нечего
+    pub integrity: Option<bool>,
+}
+
+impl Explanation {
+    /// Смещение заголовка длины в полезной нагрузке: после метки V2
+    pub fn header_offset(&self) -> usize {
+        usize::from(self.format == Format::V2)
+    }
+}
+
+/// Декодирует URL, запоминая размеры и заголовок каждого слоя: длину
+/// Base64, заголовок длины, сжатые и распакованные данные.
+///
+/// Проверка целостности не прерывает разбор, а отражается в
+/// [`Explanation::integrity`], чтобы можно было посмотреть и на повреждённый
+/// URL. Вложенные слои (`--recursive`) не раскрываются
+pub fn explain_url(vpn_url: &str, options: &DecodeOptions) -> Result<Explanation, DecodeError> {
+    let payload = decode_url_payload(vpn_url.trim(), options)?;
+    let settings = PayloadSettings { repair: true, ..PayloadSettings::from(options) };
+    let ((value, json_len), format, compression) = decode_payload(&payload.data, &settings, |json| {
+        let json_len = json.len();
+        Ok((parse_json(json)?, json_len))
+    })?;
+
+    let (declared, compressed_len, integrity) = match format {
+        Format::Plain => (None, payload.data.len(), None),
+        Format::V1 | Format::V2 => {
+            let header = &payload.data[usize::from(format == Format::V2)..];
+            let lengths = declared_lengths(header, options.endian)?;
+            let matches = lengths.iter().any(|&(_, len)| len as usize == json_len);
+            (Some(lengths[0].1), header.len() - 4, Some(matches))
+        }
+    };
+    Ok(Explanation {
+        value,
+        encoded_len: payload.encoded_len,
+        payload: payload.data,
+        format,
+        compression,
+        signed: payload.signed,
+        alphabet: payload.alphabet,
+        declared,
+        compressed_len,
+        json_len,
+        integrity,
+    })
+}
+
+/// Полезная нагрузка URL после Base64, без метки и тега подписи
+struct UrlPayload {
+    data: Vec<u8>,
+    signed: bool,
+    alphabet: Base64Alphabet,
+    /// Длина Base64 текста без пробелов, символов
+    encoded_len: usize,
+}
+
+/// Удаляет схему URL, декодирует Base64 и снимает подпись
+fn decode_url_payload(vpn_url: &str, options: &DecodeOptions) -> Result<UrlPayload, DecodeError> {
+    // Удаление префикса
+    let prefix = options.scheme.as_deref().unwrap_or(PREFIX);
+    let encoded_data = if options.no_prefix {
+        vpn_url
+    } else {
+        vpn_url.strip_prefix(prefix).ok_or(DecodeError::MissingPrefix)?
+    };
+
+    // Отбрасываем хвост `?query` / `#fragment` из ссылок для обмена
+    let encoded_data = split_url_suffix(encoded_data).0;
+
+    // URL из мессенджеров бывают разбиты пробелами и переносами строк
+    let (decoded, alphabet, encoded_len) = if encoded_data.contains(char::is_whitespace) {
+        let compacted: String = encoded_data.split_whitespace().collect();
+        let (decoded, alphabet) = decode_base64_detect(&compacted)?;
+        (decoded, alphabet, compacted.len())
+    } else {
+        let (decoded, alphabet) = decode_base64_detect(encoded_data)?;
+        (decoded, alphabet, encoded_data.len())
+    };
+
+    debug!("Base64 ({:?}): {} байт", alphabet, decoded.len());
+
+    let (data, signed) = verify_signature(decoded, options.verify_key.as_deref())?;
+    if signed {
+        let state = if options.verify_key.is_some() { "проверена" } else { "не проверялась" };
+        debug!("Подпись HMAC: {}", state);
+    }
+    Ok(UrlPayload { data, signed, alphabet, encoded_len })
+}
+
+/// Снимает подпись с полезной нагрузки, проверяя тег до распаковки.
+/// Возвращает данные и признак того, что они были подписаны.
+///
+/// Без ключа подписанный URL декодируется без проверки; с ключом
+/// неподписанный URL отвергается
+fn verify_signature(decoded: Vec<u8>, key: Option<&[u8]>) -> Result<(Vec<u8>, bool), DecodeError> {
+    let signed = match decoded.split_first() {
+        Some((&sign::SIGNED_MAGIC, rest)) if rest.len() >= 4 + sign::TAG_LEN => rest,
+        _ => {
+            return match key {
+                Some(_) => Err(DecodeError::NotSigned),
+                None => Ok((decoded, false)),
+            };
+        }
+    };
+
+    let (payload, tag) = signed.split_at(signed.len() - sign::TAG_LEN);
+    if let Some(key) = key
+        && !sign::verify_tag(key, payload, tag)
+    {
+        return Err(DecodeError::SignatureMismatch);
+    }
+    Ok((payload.to_vec(), true))
+}
+
+/// Делит часть URL после схемы на Base64 данные и хвост `?...` / `#...`
+fn split_url_suffix(data: &str) -> (&str, &str) {
+    match data.find(['?', '#']) {
+        Some(index) => data.split_at(index),
+        None => (data, ""),
+    }
+}
+
+/// Возвращает параметры запроса из ссылки вида `vpn://<base64>?name=Home`.
+/// Значения декодируются из percent-encoding, фрагмент `#...` игнорируется
+pub fn url_params(vpn_url: &str) -> Vec<(String, String)> {
+    let (_, suffix) = split_url_suffix(vpn_url);
+    let Some(query) = suffix.strip_prefix('?') else {
+        return Vec::new();
+    };
+    let query = query.split('#').next().unwrap_or_default();
+
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect()
+}
+
+/// Декодирует percent-encoding (`%20`, `+` как пробел); некорректные
+/// последовательности остаются как есть
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Перебирает форматы полезной нагрузки по порядку, пока JSON не будет принят `parse`.
+/// Возвращает результат `parse` и распознанные формат и сжатие
+pub(crate) fn decode_payload<T>(
+    data: &[u8],
+    settings: &PayloadSettings,
+    parse: impl Fn(Vec<u8>) -> Result<T, DecodeError>,
+) -> Result<(T, Format, Compression), DecodeError> {
+    let mut attempts = Vec::new();
+
+    // Метка V2: сначала пробуем данные после неё, затем всё целиком как V1
+    let candidates = match data.split_first() {
+        Some((&V2_MAGIC, body)) => vec![(V2_PAYLOAD_DECODERS, Format::V2, body), (PAYLOAD_DECODERS, Format::V1, data)],
+        _ => vec![(PAYLOAD_DECODERS, Format::V1, data)],
+    };
+
+    for (decoders, version, body) in candidates {
+        for &(name, decoder) in decoders {
+            match decoder(body, settings).and_then(|(json, compression)| Ok((parse(json)?, compression))) {
+                Ok((result, compression)) => {
+                    let format = if name == "plain" { Format::Plain } else { version };
+                    debug!("Формат полезной нагрузки: {} ({:?}, {:?})", name, format, compression);
+                    return Ok((result, format, compression));
+                }
+                Err(e) => {
+                    trace!("Формат {} не подошёл: {}", name, e);
+                    attempts.push((name, e));
+                }
+            }
+        }
+    }
+
+    Err(DecodeError::NoMatchingFormat(attempts))
+}
+
+/// Способ извлечения JSON байтов из полезной нагрузки после Base64;
+/// возвращает JSON и формат сжатия, в котором он хранился
+type PayloadDecoder = fn(&[u8], &PayloadSettings) -> Result<(Vec<u8>, Compression), DecodeError>;
+
+/// Форматы полезной нагрузки в порядке попыток декодирования:
+/// сначала заголовок + сжатие, затем заголовок + «сырой» deflate, если
+/// zlib не подошёл, затем заголовок + несжатый JSON, затем (для обратной
+/// совместимости) чистый JSON
+const PAYLOAD_DECODERS: &[(&str, PayloadDecoder)] = &[
+    ("compressed", try_decode_compressed),
+    ("raw deflate", try_decode_deflate),
+    ("uncompressed", try_decode_uncompressed),
+    ("plain", try_decode_plain),
+];
+
+/// Форматы полезной нагрузки V2 (после метки версии)
+const V2_PAYLOAD_DECODERS: &[(&str, PayloadDecoder)] = &[
+    ("v2 compressed", try_decode_compressed),
+    ("v2 raw deflate", try_decode_deflate),
+    ("v2 uncompressed", try_decode_uncompressed),
+];
+
+/// Разбивает закодированный URL на строки по `width` символов.
+///
+/// Декодер игнорирует пробельные символы внутри URL, поэтому результат
+/// декодируется так же, как исходная строка. `width == 0` — без переноса
+pub fn wrap_url(url: &str, width: usize) -> String {
+    if width == 0 {
+        return url.to_string();
+    }
+    // URL состоит только из ASCII, поэтому делим по байтам
+    url.as_bytes()
+        .chunks(width)
+        .map(|line| std::str::from_utf8(line).expect("VPN URL consists of ASCII characters only"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Рекурсивно сортирует ключи всех объектов в лексикографическом порядке
+pub fn sort_keys(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.sort_keys();
+            map.values_mut().for_each(sort_keys);
+        }
+        Value::Array(items) => items.iter_mut().for_each(sort_keys),
+        _ => {}
+    }
+}
+
+/// Приводит все строковые значения к нормальной форме Unicode NFC: имена
+/// серверов с комбинируемыми символами, которые выглядят одинаково, но
+/// различаются побайтно, становятся идентичными. Ключи объектов не меняются
+pub fn normalize_nfc(value: &mut Value) {
+    match value {
+        Value::String(text) if !is_nfc(text) => *text = text.nfc().collect(),
+        Value::Object(map) => map.values_mut().for_each(normalize_nfc),
+        Value::Array(items) => items.iter_mut().for_each(normalize_nfc),
+        _ => {}
+    }
+}
+
+/// SHA-256 нормализованной конфигурации в виде hex строки: ключи
+/// отсортированы, JSON компактный. URL с одной и той же конфигурацией дают
+/// одинаковый хеш независимо от сжатия, формата и порядка ключей
+pub fn content_hash(config: &Value) -> String {
+    use sha2::{Digest, Sha256};
+
+    let mut normalized = config.clone();
+    sort_keys(&mut normalized);
+    let digest = Sha256::digest(normalized.to_string().as_bytes());
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Сжимает данные используя zlib с указанным уровнем сжатия
+pub fn compress_data(data: &[u8], level: flate2::Compression) -> Result<Vec<u8>, EncodeError> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), level);
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+/// Распаковывает данные используя zlib с ограничением [`DEFAULT_MAX_SIZE`]
+pub fn decompress_data(data: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    decompress_data_limited(data, DEFAULT_MAX_SIZE)
+}
+
+/// Распаковывает данные используя zlib, но не больше `max_size` байт.
+///
+/// Заголовок и контрольная сумма Adler-32 проверяются явно, чтобы обрыв
+/// потока и повреждение данных давали разные, понятные ошибки
+pub fn decompress_data_limited(data: &[u8], max_size: usize) -> Result<Vec<u8>, DecodeError> {
+    // Заголовок zlib: CMF + FLG, метод deflate, без предустановленного словаря
+    let (cmf, flg) = match data {
+        [cmf, flg, ..] => (*cmf, *flg),
+        _ => return Err(DecodeError::TruncatedStream),
+    };
+    if cmf & 0x0f != 8 || (u16::from(cmf) << 8 | u16::from(flg)) % 31 != 0 || flg & 0x20 != 0 {
+        return Err(DecodeError::Decompress(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "invalid zlib header",
+        )));
+    }
+
+    // Поток deflate
+    let mut decoder = DeflateDecoder::new(&data[2..]);
+    let decompressed = read_limited(&mut decoder, max_size)?;
+
+    // Завершающая контрольная сумма Adler-32 (Big Endian)
+    let consumed = 2 + decoder.total_in() as usize;
+    let trailer = data
+        .get(consumed..consumed + 4)
+        .ok_or(DecodeError::TruncatedStream)?;
+    let expected = u32::from_be_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+    let actual = adler2::adler32_slice(&decompressed);
+    if expected != actual {
+        return Err(DecodeError::ChecksumMismatch { expected, actual });
+    }
+
+    Ok(decompressed)
+}
+
+/// Отличает обрыв сжатого потока от прочих ошибок распаковки
+fn map_stream_error(error: std::io::Error) -> DecodeError {
+    if error.kind() == std::io::ErrorKind::UnexpectedEof {
+        DecodeError::TruncatedStream
+    } else {
+        DecodeError::Decompress(error)
+    }
+}
+
+/// Читает поток целиком, но не больше `max_size` байт: лишний байт сверх
+/// лимита означает, что данные слишком велики
+fn read_limited(reader: impl Read, max_size: usize) -> Result<Vec<u8>, DecodeError> {
+    let mut decompressed = Vec::new();
+    reader
+        .take(max_size as u64 + 1)
+        .read_to_end(&mut decompressed)
+        .map_err(map_stream_error)?;
+    if decompressed.len() > max_size {
+        return Err(DecodeError::TooLarge(max_size));
+    }
+    Ok(decompressed)
+}
+
+/// Сжимает данные «сырым» deflate без заголовка zlib
+pub fn compress_deflate(data: &[u8], level: flate2::Compression) -> Result<Vec<u8>, EncodeError> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), level);
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+/// Распаковывает «сырой» deflate, но не больше `max_size` байт. Контрольной
+/// суммы у такого потока нет, целостность проверяется только по длине
+pub fn decompress_deflate_limited(data: &[u8], max_size: usize) -> Result<Vec<u8>, DecodeError> {
+    read_limited(DeflateDecoder::new(data), max_size)
+}
+
+/// Сжимает данные используя gzip с указанным уровнем сжатия
+pub fn compress_gzip(data: &[u8], level: flate2::Compression) -> Result<Vec<u8>, EncodeError> {
+    let mut encoder = GzEncoder::new(Vec::new(), level);
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+/// Распаковывает данные используя gzip с ограничением [`DEFAULT_MAX_SIZE`]
+pub fn decompress_gzip(data: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    decompress_gzip_limited(data, DEFAULT_MAX_SIZE)
+}
+
+/// Распаковывает данные используя gzip, но не больше `max_size` байт
+pub fn decompress_gzip_limited(data: &[u8], max_size: usize) -> Result<Vec<u8>, DecodeError> {
+    read_limited(GzDecoder::new(data), max_size)
+}
+
+/// Создает 4-байтовый заголовок с длиной данных (Big Endian)
+pub fn create_header(length: u32) -> [u8; 4] {
+    create_header_with(length, Endian::Big)
+}
+
+/// Создает 4-байтовый заголовок с длиной данных в заданном порядке байтов
+pub fn create_header_with(length: u32, endian: Endian) -> [u8; 4] {
+    match endian {
+        Endian::Big => length.to_be_bytes(),
+        Endian::Little => length.to_le_bytes(),
+    }
+}
+
+/// Считывает длину из первых 4 байт данных (Big Endian). Если данных
+/// меньше 4 байт, возвращает [`DecodeError::TooShort`]
+pub fn read_header(data: &[u8]) -> Result<u32, DecodeError> {
+    read_header_with(data, Endian::Big)
+}
+
+/// Считывает длину из первых 4 байт данных в заданном порядке байтов
+pub fn read_header_with(data: &[u8], endian: Endian) -> Result<u32, DecodeError> {
+    let header = *data.first_chunk::<4>().ok_or(DecodeError::TooShort)?;
+    Ok(match endian {
+        Endian::Big => u32::from_be_bytes(header),
+        Endian::Little => u32::from_le_bytes(header),
+    })
+}
+
+/// Длины из заголовка: сначала в порядке байтов из настроек, затем в обратном
+fn declared_lengths(data: &[u8], endian: Endian) -> Result<[(Endian, u32); 2], DecodeError> {
+    let reversed = endian.reversed();
+    Ok([(endian, read_header_with(data, endian)?), (reversed, read_header_with(data, reversed)?)])
+}
+
+/// Кодирует данные в Base64 URL-safe без padding
+pub fn encode_base64(data: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(data)
+}
+
+/// Декодирует Base64 URL-safe (автоматически обрабатывает отсутствие padding).
+///
+/// Принимаются все четыре распространённых варианта: URL-safe и стандартный
+/// (`+`/`/`) алфавиты, каждый с padding и без; при неудаче возвращается
+/// ошибка URL-safe декодирования без padding
+pub fn decode_base64(data: &str) -> Result<Vec<u8>, DecodeError> {
+    decode_base64_detect(data).map(|(decoded, _)| decoded)
+}
+
+/// Декодирует Base64 как [`decode_base64`] и сообщает, какой алфавит подошёл
+fn decode_base64_detect(data: &str) -> Result<(Vec<u8>, Base64Alphabet), DecodeError> {
+    match URL_SAFE_NO_PAD.decode(data).or_else(|e| URL_SAFE.decode(data).map_err(|_| e)) {
+        Ok(decoded) => Ok((decoded, Base64Alphabet::UrlSafe)),
+        Err(e) => STANDARD
+            .decode(data)
+            .or_else(|_| STANDARD_NO_PAD.decode(data))
+            .map(|decoded| (decoded, Base64Alphabet::Standard))
+            .map_err(|_| DecodeError::Base64(e)),
+    }
+}
+
+/// Пытается декодировать данные с заголовком и сжатием
+fn try_decode_compressed(data: &[u8], settings: &PayloadSettings) -> Result<(Vec<u8>, Compression), DecodeError> {
+    let (expected_lens, body) = split_compressed_header(data, settings)?;
+    let max_size = settings.max_size;
+    
+    // Распаковываем оставшиеся данные: gzip узнаём по магическим байтам, иначе zlib
+    let (decompressed, compression) = if body.starts_with(&GZIP_MAGIC) {
+        (decompress_gzip_limited(body, max_size)?, Compression::Gzip)
+    } else {
+        (decompress_data_limited(body, max_size)?, Compression::Zlib)
+    };
+    
+    check_decompressed_len(decompressed, compression, &expected_lens, settings)
+}
+
+/// Пытается декодировать данные с заголовком и «сырым» deflate без обёртки zlib
+fn try_decode_deflate(data: &[u8], settings: &PayloadSettings) -> Result<(Vec<u8>, Compression), DecodeError> {
+    let (expected_lens, body) = split_compressed_header(data, settings)?;
+    let decompressed = decompress_deflate_limited(body, settings.max_size)?;
+    check_decompressed_len(decompressed, Compression::Deflate, &expected_lens, settings)
+}
+
+/// Длина из заголовка и порядок байтов, в котором она прочитана
+type HeaderLength = (Endian, usize);
+
+/// Отделяет заголовок длины от сжатых данных и возвращает допустимые
+/// длины в обоих порядках байтов (предпочтительный — первым). Заведомо
+/// невозможный или превышающий лимит размер отвергается сразу (кроме режима
+/// восстановления), но сам поток всё равно распаковывается с ограничением
+fn split_compressed_header<'a>(
+    data: &'a [u8],
+    settings: &PayloadSettings,
+) -> Result<(Vec<HeaderLength>, &'a [u8]), DecodeError> {
+    let declared = declared_lengths(data, settings.endian)?;
+    let body = &data[4..];
+
+    let mut lengths = Vec::with_capacity(declared.len());
+    let mut first_error = None;
+    for (endian, declared) in declared {
+        let checked = plausible_length(declared, body.len()).and_then(|len| match len > settings.max_size {
+            true => Err(DecodeError::TooLarge(settings.max_size)),
+            false => Ok(len),
+        });
+        match checked {
+            Ok(len) => lengths.push((endian, len)),
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    match first_error {
+        Some(e) if lengths.is_empty() && !settings.repair => Err(e),
+        _ => Ok((lengths, body)),
+    }
+}
+
+/// Проверяет, что длина распакованных данных совпадает с заголовком в
+/// одном из порядков байтов
+fn check_decompressed_len(
+    decompressed: Vec<u8>,
+    compression: Compression,
+    expected_lens: &[HeaderLength],
+    settings: &PayloadSettings,
+) -> Result<(Vec<u8>, Compression), DecodeError> {
+    trace!("Распаковано {:?}: {} байт", compression, decompressed.len());
+
+    // Проверка целостности
+    match expected_lens.iter().find(|(_, len)| *len == decompressed.len()) {
+        Some((endian, len)) => trace!("Длина из заголовка ({:?} Endian) совпадает: {} байт", endian, len),
+        None if settings.repair => debug!("Длина не совпадает с заголовком, принято без проверки: {} байт", decompressed.len()),
+        None => {
+            return Err(DecodeError::IntegrityMismatch {
+                expected: expected_lens[0].1,
+                got: decompressed.len(),
+            });
+        }
+    }
+
+    Ok((decompressed, compression))
+}
+
+/// Максимальная степень сжатия deflate: один блок кодирует не больше
+/// 258 байт на каждые 2 бита, то есть чуть больше 1032:1
+const MAX_DEFLATE_RATIO: usize = 1032;
+
+/// Проверяет, что заявленная в заголовке длина достижима при распаковке
+/// `compressed_len` байт, и переводит её в `usize` без переполнения
+fn plausible_length(declared: u32, compressed_len: usize) -> Result<usize, DecodeError> {
+    let implausible = || DecodeError::ImplausibleLength { declared, compressed: compressed_len };
+    let expected_len = usize::try_from(declared).map_err(|_| implausible())?;
+    // Небольшой запас на заголовки gzip/zlib у совсем коротких потоков
+    let limit = compressed_len.saturating_add(64).saturating_mul(MAX_DEFLATE_RATIO);
+    if expected_len > limit {
+        return Err(implausible());
+    }
+    Ok(expected_len)
+}
+
+/// Пытается декодировать данные с заголовком, за которым следует несжатый JSON
+fn try_decode_uncompressed(data: &[u8], settings: &PayloadSettings) -> Result<(Vec<u8>, Compression), DecodeError> {
+    let declared = declared_lengths(data, settings.endian)?;
+    let body = &data[4..];
+
+    // Проверка целостности в обоих порядках байтов
+    if !settings.repair && !declared.iter().any(|&(_, len)| len as usize == body.len()) {
+        return Err(DecodeError::IntegrityMismatch {
+            expected: declared[0].1 as usize,
+            got: body.len(),
+        });
+    }
+
+    Ok((body.to_vec(), Compression::None))
+}
+
+/// Пытается декодировать данные как чистый Base64 JSON (без сжатия)
+fn try_decode_plain(data: &[u8], _settings: &PayloadSettings) -> Result<(Vec<u8>, Compression), DecodeError> {
+    Ok((data.to_vec(), Compression::None))
+}
+
+/// Десериализует JSON из распакованных байтов
+fn parse_json(bytes: Vec<u8>) -> Result<Value, DecodeError> {
+    let json_string = String::from_utf8(bytes)?;
+    Ok(from_str(&json_string)?)
+}
+
+/// Автоматически определяет тип входных данных
+pub fn detect_input_type(input: &str) -> InputType {
+    let trimmed = input.strip_prefix(BOM).unwrap_or(input).trim();
+    
+    // Проверка на VPN URL
+    if trimmed.starts_with(PREFIX) {
+        return InputType::VpnUrl;
+    }
+    
+    // Проверка на JSON по скобкам: даже некорректный объект лучше отдать
+    // кодированию, чтобы пользователь увидел ошибку разбора JSON
+    let body = strip_json_comments(trimmed);
+    if body.starts_with('{') && body.ends_with('}') {
+        return InputType::JsonObject;
+    }
+    if body.starts_with('[') && body.ends_with(']') {
+        return InputType::JsonArray;
+    }
+    // Любой другой корректный JSON (`"text"`, `42`, `true`) тоже кодируется;
+    // проверка идёт до Base64, иначе `12345678` сошло бы за блоб
+    if from_str::<IgnoredAny>(body).is_ok() {
+        return InputType::JsonScalar;
+    }
+
+    if looks_like_base64(trimmed) {
+        return InputType::Base64Blob;
+    }
+    
+    InputType::Unknown
+}
+
+/// Объясняет, почему [`detect_input_type`] вернул [`InputType::Unknown`];
+/// `None`, если тип распознан
+pub fn explain_unknown_input(input: &str) -> Option<UnknownInput> {
+    if detect_input_type(input) != InputType::Unknown {
+        return None;
+    }
+    let trimmed = input.strip_prefix(BOM).unwrap_or(input).trim();
+    if trimmed.is_empty() {
+        return Some(UnknownInput::Empty);
+    }
+
+    // Другая схема: `ss://`, `https://`, `wireguard://`...
+    if let Some((scheme, _)) = trimmed.split_once("://")
+        && !scheme.is_empty()
+        && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    {
+        return Some(UnknownInput::UnsupportedScheme(format!("{}://", scheme)));
+    }
+
+    // Похоже на JSON, но скобки не сходятся — покажем ошибку разбора
+    let body = strip_json_comments(trimmed);
+    if body.starts_with(['{', '[']) {
+        let error = from_str::<IgnoredAny>(body).err().map(|e| e.to_string()).unwrap_or_default();
+        return Some(UnknownInput::InvalidJson(error));
+    }
+
+    let unpadded = trimmed.trim_end_matches('=');
+    if let Some((position, ch)) = unpadded
+        .char_indices()
+        .find(|&(_, c)| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '+' | '/')))
+    {
+        return Some(UnknownInput::InvalidCharacter { ch, position });
+    }
+    if unpadded.len() < MIN_BASE64_BLOB_LEN {
+        return Some(UnknownInput::TooShort(unpadded.len(), MIN_BASE64_BLOB_LEN));
+    }
+    let error = URL_SAFE_NO_PAD.decode(unpadded).err().map(|e| e.to_string()).unwrap_or_default();
+    Some(UnknownInput::InvalidBase64(error))
+}
+
+/// Метка порядка байт UTF-8, которую добавляют некоторые редакторы
+const BOM: char = '\u{feff}';
+
+/// Убирает BOM, пробелы и строки-комментарии `//` и `#` до и после JSON,
+/// оставляя то, что можно передать в `serde_json`. Комментарии внутри
+/// JSON не поддерживаются
+pub fn strip_json_comments(input: &str) -> &str {
+    let is_comment = |line: &str| line.starts_with("//") || line.starts_with('#');
+    let mut text = input.strip_prefix(BOM).unwrap_or(input).trim();
+    while is_comment(text) {
+        text = text.split_once('\n').map_or("", |(_, rest)| rest).trim_start();
+    }
+    while let Some((head, last)) = text.rsplit_once('\n')
+        && is_comment(last.trim())
+    {
+        text = head.trim_end();
+    }
+    text
+}
+
+/// Минимальная длина строки, которую имеет смысл считать Base64 блобом:
+/// 4-байтовый заголовок и хотя бы 2 байта данных
+const MIN_BASE64_BLOB_LEN: usize = 8;
+
+/// Проверяет, похожа ли строка на Base64 (URL-safe или стандартный алфавит,
+/// padding допускается) и декодируется ли она
+fn looks_like_base64(text: &str) -> bool {
+    let unpadded = text.trim_end_matches('=');
+    unpadded.len() >= MIN_BASE64_BLOB_LEN
+        && unpadded
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'+' | b'/'))
+        && (URL_SAFE_NO_PAD.decode(unpadded).is_ok() || STANDARD_NO_PAD.decode(unpadded).is_ok())
+}
+
+/// Тип входных данных
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputType {
+    /// Строка со схемой `vpn://`
+    VpnUrl,
+    /// JSON объект `{...}`
+    JsonObject,
+    /// JSON массив `[...]`
+    JsonArray,
+    /// Другое значение JSON: строка, число, `true`/`false`/`null`
+    JsonScalar,
+    /// Base64 без схемы URL, например скопированный без `vpn://`
+    Base64Blob,
+    Unknown,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, to_string_pretty};
+
+    #[test]
+    fn test_encode_decode() {
+        let config = json!({
+            "server": "example.com",
+            "port": 8080,
+            "protocol": "wireguard",
+            "key": "test_key_12345"
+        });
+
+        let encoded = encode(&config).unwrap();
+        assert!(encoded.starts_with(PREFIX));
+
+        let decoded = decode(&encoded).unwrap();
+        assert_eq!(config, decoded);
+    }
+
+    #[test]
+    fn test_detect_input_type() {
+        // JSON детект
+        assert_eq!(detect_input_type(r#"{"key": "value"}"#), InputType::JsonObject);
+        assert_eq!(detect_input_type(r#"{"server":"test.com"}"#), InputType::JsonObject);
+        assert_eq!(detect_input_type(r#"[1, 2, 3]"#), InputType::JsonArray);
+        
+        // VPN URL детект
+        assert_eq!(detect_input_type("vpn://AAAAHXic"), InputType::VpnUrl);
+        assert_eq!(detect_input_type("vpn://test123"), InputType::VpnUrl);
+        
+        // Base64 без схемы
+        let blob = encode(&json!({"a": 1})).unwrap().trim_start_matches(PREFIX).to_string();
+        assert_eq!(detect_input_type(&blob), InputType::Base64Blob);
+        assert_eq!(detect_input_type("AAAAHXic+/8="), InputType::Base64Blob);
+
+        // BOM и комментарии вокруг JSON
+        assert_eq!(detect_input_type("\u{feff}{\"a\": 1}\n"), InputType::JsonObject);
+        assert_eq!(detect_input_type("# AmneziaVPN\n// сервер\n[1]\n# конец"), InputType::JsonArray);
+        assert_eq!(detect_input_type("\u{feff}vpn://AAAAHXic"), InputType::VpnUrl);
+
+        // Скалярный JSON кодируется, даже если похож на Base64
+        assert_eq!(detect_input_type(r#""hello""#), InputType::JsonScalar);
+        assert_eq!(detect_input_type("42"), InputType::JsonScalar);
+        assert_eq!(detect_input_type("true"), InputType::JsonScalar);
+        assert_eq!(detect_input_type("12345678"), InputType::JsonScalar);
+        assert_eq!(explain_unknown_input("12345678"), None);
+
+        // Unknown
+        assert_eq!(detect_input_type("short"), InputType::Unknown);
+        assert_eq!(detect_input_type("random text"), InputType::Unknown);
+        assert_eq!(detect_input_type(""), InputType::Unknown);
+    }
+
+    #[test]
+    fn test_explain_unknown_input() {
+        let reason = |input: &str| explain_unknown_input(input).map(|reason| reason.to_string());
+        assert_eq!(reason("vpn://AAAA"), None);
+        assert_eq!(reason(" \n"), Some("input is empty".to_string()));
+        assert_eq!(
+            reason("ss://YWVzLTI1Ni1nY206cGFzcw@example.com:8388"),
+            Some("starts with 'ss://' which is not a recognized VPN scheme".to_string())
+        );
+        assert_eq!(
+            reason(r#"{"hostName": "example.com""#),
+            Some("not valid JSON: EOF while parsing an object at line 1 column 26".to_string())
+        );
+        assert_eq!(
+            reason("random text"),
+            Some("unexpected character ' ' at position 6: neither JSON nor base64".to_string())
+        );
+        assert_eq!(
+            reason("short"),
+            Some("too short for a base64 payload (5 characters, need at least 8)".to_string())
+        );
+        assert!(reason("AAAAHXicA").unwrap().starts_with("not valid base64: "));
+    }
+
+    #[test]
+    fn test_strip_json_comments() {
+        assert_eq!(strip_json_comments("\u{feff}  {\"a\": 1}  "), "{\"a\": 1}");
+        let commented = "// выгружено из клиента\n#  v2\n\n{\n  \"url\": \"https://example.com\"\n}\n// конец\n";
+        assert_eq!(strip_json_comments(commented), "{\n  \"url\": \"https://example.com\"\n}");
+        assert_eq!(strip_json_comments("# только комментарий"), "");
+    }
+
+    #[test]
+    fn test_helper_functions() {
+        let data = b"Hello, World!";
+        
+        // Тест сжатия/распаковки
+        let compressed = compress_data(data, flate2::Compression::default()).unwrap();
+        let decompressed = decompress_data(&compressed).unwrap();
+        assert_eq!(data, decompressed.as_slice());
+
+        let compressed = compress_gzip(data, flate2::Compression::default()).unwrap();
+        assert!(compressed.starts_with(&GZIP_MAGIC));
+        let decompressed = decompress_gzip(&compressed).unwrap();
+        assert_eq!(data, decompressed.as_slice());
+        
+        // Тест заголовка
+        let len = 12345u32;
+        let header = create_header(len);
+        let read_len = read_header(&header).unwrap();
+        assert_eq!(len, read_len);
+        // Короткий срез — ошибка, а не паника
+        assert!(matches!(read_header(&header[..2]), Err(DecodeError::TooShort)));
+        assert!(matches!(read_header(&[]), Err(DecodeError::TooShort)));
+        
+        // Тест Base64
+        let encoded = encode_base64(data);
+        let decoded = decode_base64(&encoded).unwrap();
+        assert_eq!(data, decoded.as_slice());
+    }
+
+    #[test]
+    fn test_compression_level() {
+        // Большая конфигурация с разнообразными данными
+        let peers: Vec<Value> = (0..500)
+            .map(|i| json!({
+                "name": format!("peer-{}", i * 7919 % 1000),
+                "address": format!("10.{}.{}.{}/32", i % 7, i * 31 % 255, i * 17 % 253),
+                "key": format!("{:x}{:x}", i * 2654435761u64, i * 40503u64 + 12345),
+            }))
+            .collect();
+        let config = json!({ "peers": peers });
+
+        let fast = encode_with_options(&config, &EncodeOptions { level: Some(1), ..Default::default() }).unwrap();
+        let best = encode_with_options(&config, &EncodeOptions { level: Some(9), ..Default::default() }).unwrap();
+        assert!(best.len() < fast.len());
+
+        // Декодер читает URL независимо от уровня сжатия
+        assert_eq!(decode(&fast).unwrap(), config);
+        assert_eq!(decode(&best).unwrap(), config);
+    }
+
+    #[test]
+    fn test_preserve_order() {
+        let config: Value = from_str(r#"{"zeta": 1, "alpha": {"y": 2, "b": 3}, "mid": 4}"#).unwrap();
+        let keys = |value: &Value| value.as_object().unwrap().keys().cloned().collect::<Vec<_>>();
+
+        let options = EncodeOptions { preserve_order: true, ..Default::default() };
+        let decoded = decode(&encode_with_options(&config, &options).unwrap()).unwrap();
+        assert_eq!(keys(&decoded), vec!["zeta", "alpha", "mid"]);
+        assert_eq!(keys(&decoded["alpha"]), vec!["y", "b"]);
+
+        // По умолчанию ключи сортируются, как и раньше
+        let decoded = decode(&encode(&config).unwrap()).unwrap();
+        assert_eq!(keys(&decoded), vec!["alpha", "mid", "zeta"]);
+    }
+
+    #[test]
+    fn test_custom_scheme() {
+        let config = json!({"hostName": "example.com"});
+        let scheme = Some("amnezia://".to_string());
+
+        let options = EncodeOptions { scheme: scheme.clone(), ..Default::default() };
+        let encoded = encode_with_options(&config, &options).unwrap();
+        assert!(encoded.starts_with("amnezia://"));
+
+        let options = DecodeOptions { scheme, ..Default::default() };
+        assert_eq!(decode_with_options(&encoded, &options).unwrap(), config);
+        assert!(matches!(decode(&encoded), Err(DecodeError::MissingPrefix)));
+    }
+
+    #[test]
+    fn test_gzip_format() {
+        let config = json!({"hostName": "example.com", "containers": []});
+
+        // Кодирование в gzip и обратно
+        let options = EncodeOptions { compression: Compression::Gzip, ..Default::default() };
+        let encoded = encode_with_options(&config, &options).unwrap();
+        assert_ne!(encoded, encode(&config).unwrap());
+        assert_eq!(decode(&encoded).unwrap(), config);
+
+        // Полезная нагрузка стороннего инструмента: заголовок + gzip
+        let json = br#"{"hostName":"example.com","containers":[]}"#;
+        let mut payload = create_header(json.len() as u32).to_vec();
+        payload.extend(compress_gzip(json, flate2::Compression::best()).unwrap());
+        let url = format!("{}{}", PREFIX, encode_base64(&payload));
+        assert_eq!(decode(&url).unwrap(), config);
+    }
+
+    #[test]
+    fn test_no_compress() {
+        let config = json!({"hostName": "example.com", "port": 51820});
+        let options = EncodeOptions { compression: Compression::None, ..Default::default() };
+        let encoded = encode_with_options(&config, &options).unwrap();
+
+        // После заголовка лежит исходный JSON
+        let payload = decode_base64(encoded.strip_prefix(PREFIX).unwrap()).unwrap();
+        assert_eq!(read_header(&payload).unwrap() as usize, payload.len() - 4);
+        assert_eq!(from_str::<Value>(std::str::from_utf8(&payload[4..]).unwrap()).unwrap(), config);
+
+        assert_eq!(decode(&encoded).unwrap(), config);
+    }
+
+    #[test]
+    fn test_truncated_stream() {
+        let data: Vec<u8> = (0..5000u32).map(|i| (i * 7 % 251) as u8).collect();
+        let compressed = compress_data(&data, flate2::Compression::default()).unwrap();
+
+        // Обрыв внутри deflate потока и внутри контрольной суммы
+        for cut in [compressed.len() / 2, compressed.len() - 2] {
+            assert!(matches!(decompress_data(&compressed[..cut]), Err(DecodeError::TruncatedStream)));
+        }
+
+        // Повреждённая контрольная сумма
+        let mut corrupted = compressed.clone();
+        *corrupted.last_mut().unwrap() ^= 1;
+        assert!(matches!(decompress_data(&corrupted), Err(DecodeError::ChecksumMismatch { .. })));
+
+        // Через decode ошибка видна в попытке сжатого формата
+        let mut payload = create_header(data.len() as u32).to_vec();
+        payload.extend_from_slice(&compressed[..compressed.len() / 2]);
+        let url = format!("{}{}", PREFIX, encode_base64(&payload));
+        match decode(&url) {
+            Err(DecodeError::NoMatchingFormat(attempts)) => {
+                assert!(matches!(attempts[0].1, DecodeError::TruncatedStream));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_stats() {
+        let config = json!({"hostName": "example.com", "dns1": "1.1.1.1", "dns2": "1.0.0.1"});
+        let (url, encode_stats) = encode_with_stats(&config, &EncodeOptions::default()).unwrap();
+        assert_eq!(encode_stats.json_len, to_string_pretty(&config).unwrap().len());
+        assert_eq!(encode_stats.url_len, url.len());
+
+        let (decoded, decode_stats) = decode_with_stats(&url, &DecodeOptions::default()).unwrap();
+        assert_eq!(decoded, config);
+        assert_eq!(decode_stats.payload_len, 4 + encode_stats.compressed_len);
+        assert_eq!(decode_stats.json_len, encode_stats.json_len);
+    }
+
+    #[test]
+    fn test_encode_writer_matches_buffered() {
+        let config = json!({
+            "hostName": "example.com",
+            "containers": [{"container": "amnezia-awg", "awg": {"port": "51820"}}],
+        });
+
+        // Буферизованный и потоковый варианты дают одинаковый URL
+        let buffered = encode(&config).unwrap();
+        let mut streamed = Vec::new();
+        let stats = encode_writer(&config, &mut streamed).unwrap();
+        assert_eq!(String::from_utf8(streamed).unwrap(), buffered);
+        assert_eq!(stats.url_len, buffered.len());
+
+        // Выходной поток напрямую декодируется обратно
+        let options = EncodeOptions { compression: Compression::Gzip, ..Default::default() };
+        let mut streamed = Vec::new();
+        encode_writer_with_options(&config, &options, &mut streamed).unwrap();
+        assert_eq!(decode(std::str::from_utf8(&streamed).unwrap()).unwrap(), config);
+
+        // Эталон: пошаговое кодирование через вспомогательные функции
+        let mut sorted = config.clone();
+        sort_keys(&mut sorted);
+        let json = to_string_pretty(&sorted).unwrap();
+        let mut payload = create_header(json.len() as u32).to_vec();
+        payload.extend(compress_data(json.as_bytes(), flate2::Compression::default()).unwrap());
+        assert_eq!(buffered, format!("{}{}", PREFIX, encode_base64(&payload)));
+    }
+
+    #[test]
+    fn test_decode_to_writer_raw() {
+        // Кодировщик сохраняет JSON как есть: отступы и запись чисел сохраняются
+        let original = "{ \"hostName\" : \"example.com\",\n  \"mtu\": 1.0e3 }";
+        let mut payload = create_header(original.len() as u32).to_vec();
+        payload.extend(compress_data(original.as_bytes(), flate2::Compression::default()).unwrap());
+        let url = format!("{}{}", PREFIX, encode_base64(&payload));
+
+        let mut raw = Vec::new();
+        let written = decode_to_writer(&url, &mut raw).unwrap();
+        assert_eq!(written, original.len());
+        assert_eq!(raw, original.as_bytes());
+
+        // Повторная сериализация через Value меняет байты
+        assert_ne!(serde_json::to_string(&decode(&url).unwrap()).unwrap(), original);
+
+        // URL нашего кодировщика отдаёт ровно сериализованный при кодировании JSON
+        let config = json!({"hostName": "example.com", "port": 51820});
+        let mut raw = Vec::new();
+        decode_to_writer(&encode(&config).unwrap(), &mut raw).unwrap();
+        assert_eq!(raw, to_string_pretty(&config).unwrap().as_bytes());
+    }
+
+    #[test]
+    fn test_url_with_line_breaks() {
+        let config = json!({"hostName": "example.com", "containers": ["a", "b"]});
+        let url = encode(&config).unwrap();
+
+        // Переносы и пробелы внутри Base64, как после копирования из чата
+        let (prefix, payload) = url.split_at(PREFIX.len() + 10);
+        let (middle, tail) = payload.split_at(7);
+        let broken = format!("{}\r\n{} \t{}\n", prefix, middle, tail);
+        assert_eq!(decode(&broken).unwrap(), config);
+    }
+
+    #[test]
+    fn test_wrap_url() {
+        let config = json!({"hostName": "example.com", "description": "x".repeat(200)});
+        let url = encode(&config).unwrap();
+
+        let wrapped = wrap_url(&url, 76);
+        assert!(wrapped.lines().count() > 1);
+        assert!(wrapped.lines().all(|line| line.len() <= 76));
+        assert_eq!(wrapped.replace('\n', ""), url);
+        assert_eq!(decode(&wrapped).unwrap(), config);
+
+        assert_eq!(wrap_url(&url, 0), url);
+    }
+
+    #[test]
+    fn test_base64_alphabets() {
+        // Данные, в Base64 которых встречаются `+`/`/` и `-`/`_`
+        let data = [0xfb, 0xff, 0xbf, 0xfe];
+        assert_eq!(decode_base64("-_-__g").unwrap(), data);
+        assert_eq!(decode_base64("+/+//g==").unwrap(), data);
+        assert_eq!(decode_base64("+/+//g").unwrap(), data);
+        assert_eq!(decode_base64("-_-__g==").unwrap(), data);
+        assert!(matches!(decode_base64("+/+//g=").unwrap_err(), DecodeError::Base64(_)));
+        assert!(matches!(decode_base64("-_-__g=").unwrap_err(), DecodeError::Base64(_)));
+
+        // URL-safe payload с padding, как у некоторых генераторов
+        let url = encode(&json!({"hostName": "example.com"})).unwrap();
+        let payload = url.strip_prefix(PREFIX).unwrap();
+        let padded = format!("{}{}{}", PREFIX, payload, "=".repeat((4 - payload.len() % 4) % 4));
+        assert_ne!(padded, url);
+        assert_eq!(decode(&padded).unwrap()["hostName"], "example.com");
+
+        let config = json!({"hostName": "example.com", "blob": "~~~~????>>>>"});
+        for base64 in [Base64Alphabet::UrlSafe, Base64Alphabet::Standard] {
+            let options = EncodeOptions { base64, level: Some(0), ..Default::default() };
+            let url = encode_with_options(&config, &options).unwrap();
+            let payload = url.strip_prefix(PREFIX).unwrap();
+            match base64 {
+                Base64Alphabet::UrlSafe => assert!(!payload.contains(['+', '/', '='])),
+                Base64Alphabet::Standard => assert!(!payload.contains(['-', '_'])),
+            }
+            assert_eq!(decode(&url).unwrap(), config);
+        }
+    }
+
+    #[test]
+    fn test_decode_detailed() {
+        let config = json!({"hostName": "example.com", "port": 443});
+        let detailed = |options: EncodeOptions| decode_detailed(&encode_with_options(&config, &options).unwrap()).unwrap();
+
+        let result = detailed(EncodeOptions::default());
+        assert_eq!(result.value, config);
+        assert_eq!(
+            (result.format, result.compression, result.signed, result.alphabet),
+            (Format::V1, Compression::Zlib, false, Base64Alphabet::UrlSafe)
+        );
+
+        let result = detailed(EncodeOptions { compression: Compression::Gzip, ..Default::default() });
+        assert_eq!((result.format, result.compression), (Format::V1, Compression::Gzip));
+
+        let result = detailed(EncodeOptions { compression: Compression::None, ..Default::default() });
+        assert_eq!((result.format, result.compression), (Format::V1, Compression::None));
+
+        let result = detailed(EncodeOptions { format_version: FormatVersion::V2, ..Default::default() });
+        assert_eq!((result.format, result.compression), (Format::V2, Compression::Zlib));
+
+        let result = detailed(EncodeOptions { sign_key: Some(b"key".to_vec()), ..Default::default() });
+        assert_eq!((result.format, result.signed), (Format::V1, true));
+
+        let result = detailed(EncodeOptions { base64: Base64Alphabet::Standard, ..Default::default() });
+        assert_eq!(result.alphabet, Base64Alphabet::Standard);
+
+        // Чистый JSON без заголовка
+        let plain = format!("{}{}", PREFIX, encode_base64(config.to_string().as_bytes()));
+        let result = decode_detailed(&plain).unwrap();
+        assert_eq!(result.value, config);
+        assert_eq!((result.format, result.compression), (Format::Plain, Compression::None));
+    }
+
+    #[test]
+    fn test_minify() {
+        let config = json!({
+            "containers": [{"container": "amnezia-awg", "awg": {"port": "443", "transport_proto": "udp"}}],
+            "defaultContainer": "amnezia-awg",
+            "hostName": "example.com"
+        });
+        for compression in [Compression::Zlib, Compression::None] {
+            let pretty = EncodeOptions { compression, ..Default::default() };
+            let minify = EncodeOptions { compression, minify: true, ..Default::default() };
+            let (pretty_url, pretty_stats) = encode_with_stats(&config, &pretty).unwrap();
+            let (minified_url, minified_stats) = encode_with_stats(&config, &minify).unwrap();
+
+            assert!(minified_url.len() <= pretty_url.len());
+            assert!(minified_stats.json_len < pretty_stats.json_len);
+            assert_eq!(decode(&minified_url).unwrap(), config);
+        }
+    }
+
+    #[test]
+    fn test_signed_url() {
+        let config = json!({"hostName": "example.com", "port": 443});
+        let sign = EncodeOptions { sign_key: Some(b"secret".to_vec()), ..Default::default() };
+        let url = encode_with_options(&config, &sign).unwrap();
+
+        let payload = decode_base64(url.strip_prefix(PREFIX).unwrap()).unwrap();
+        assert_eq!(payload[0], sign::SIGNED_MAGIC);
+
+        // Верный ключ и декодирование без ключа
+        let verify = |key: &[u8]| DecodeOptions { verify_key: Some(key.to_vec()), ..Default::default() };
+        assert_eq!(decode_with_options(&url, &verify(b"secret")).unwrap(), config);
+        assert_eq!(decode(&url).unwrap(), config);
+
+        // Неверный ключ
+        assert!(matches!(
+            decode_with_options(&url, &verify(b"wrong")),
+            Err(DecodeError::SignatureMismatch)
+        ));
+
+        // Изменённая полезная нагрузка
+        let mut tampered = payload.clone();
+        tampered[8] ^= 0x01;
+        let tampered = format!("{}{}", PREFIX, encode_base64(&tampered));
+        assert!(matches!(
+            decode_with_options(&tampered, &verify(b"secret")),
+            Err(DecodeError::SignatureMismatch)
+        ));
+
+        // Неподписанный URL при заданном ключе
+        let unsigned = encode(&config).unwrap();
+        assert!(matches!(
+            decode_with_options(&unsigned, &verify(b"secret")),
+            Err(DecodeError::NotSigned)
+        ));
+    }
+
+    #[test]
+    fn test_format_versions() {
+        let config = json!({"hostName": "example.com", "containers": [1, 2, 3]});
+
+        for compression in [Compression::Zlib, Compression::Gzip, Compression::None] {
+            let v1 = EncodeOptions { compression, ..Default::default() };
+            let v2 = EncodeOptions { compression, format_version: FormatVersion::V2, ..Default::default() };
+
+            let url_v1 = encode_with_options(&config, &v1).unwrap();
+            let url_v2 = encode_with_options(&config, &v2).unwrap();
+            assert_eq!(decode(&url_v1).unwrap(), config);
+            assert_eq!(decode(&url_v2).unwrap(), config);
+
+            // V2 начинается с метки версии, дальше — та же полезная нагрузка V1
+            let payload_v1 = decode_base64(url_v1.strip_prefix(PREFIX).unwrap()).unwrap();
+            let payload_v2 = decode_base64(url_v2.strip_prefix(PREFIX).unwrap()).unwrap();
+            assert_eq!(payload_v2[0], V2_MAGIC);
+            assert_eq!(&payload_v2[1..], &payload_v1[..]);
+        }
+    }
+
+    #[test]
+    fn test_decode_recursive() {
+        let config = json!({"hostName": "example.com", "port": 443});
+        let twice = encode(&Value::String(encode(&config).unwrap())).unwrap();
+
+        // Без --recursive получаем внутренний URL как строку
+        assert!(decode(&twice).unwrap().as_str().unwrap().starts_with(PREFIX));
+
+        let options = DecodeOptions { recursive: true, ..Default::default() };
+        let (value, stats) = decode_with_stats(&twice, &options).unwrap();
+        assert_eq!(value, config);
+        assert_eq!(stats.layers, 2);
+
+        // Слишком глубокая вложенность
+        let mut url = encode(&config).unwrap();
+        for _ in 0..MAX_LAYERS {
+            url = encode(&Value::String(url)).unwrap();
+        }
+        assert!(matches!(
+            decode_with_stats(&url, &options),
+            Err(DecodeError::TooManyLayers(MAX_LAYERS))
+        ));
+    }
+
+    #[test]
+    fn test_url_with_query() {
+        let config = json!({"hostName": "example.com"});
+        let url = encode(&config).unwrap();
+
+        for suffix in ["", "?name=Home", "?name=My%20Home&id=1", "#frag", "?name=Home#frag"] {
+            let shared = format!("{}{}", url, suffix);
+            assert_eq!(decode(&shared).unwrap(), config, "suffix {:?}", suffix);
+        }
+
+        assert!(url_params(&url).is_empty());
+        assert_eq!(url_params(&format!("{}?name=Home", url)), vec![("name".into(), "Home".into())]);
+        assert_eq!(
+            url_params(&format!("{}?name=My%20Home+2&flag#frag", url)),
+            vec![("name".into(), "My Home 2".into()), ("flag".into(), String::new())]
+        );
+    }
+
+    #[test]
+    fn test_decode_error_variants() {
+        assert!(matches!(decode("AAAAHXic"), Err(DecodeError::MissingPrefix)));
+        assert!(matches!(decode("vpn://не base64!"), Err(DecodeError::Base64(_))));
+    }
+
+    #[test]
+    fn test_decode_plain_payload() {
+        // Base64 от чистого JSON без заголовка и сжатия, как у сторонних генераторов
+        let url = "vpn://eyJob3N0TmFtZSI6ImV4YW1wbGUuY29tIiwicG9ydCI6NTE4MjB9";
+        let decoded = decode(url).unwrap();
+        assert_eq!(decoded, json!({"hostName": "example.com", "port": 51820}));
+    }
+
+    #[test]
+    fn test_reader_writer() {
+        let json = br#"{"hostName": "example.com", "port": 443}"#;
+        let mut url = Vec::new();
+        let stats = encode_from_reader(&json[..], &mut url).unwrap();
+        assert_eq!(stats.url_len, url.len());
+
+        // URL из файла обычно заканчивается переводом строки
+        url.push(b'\n');
+        let mut decoded = Vec::new();
+        let written = decode_from_reader(url.as_slice(), &mut decoded).unwrap();
+        assert_eq!(written, decoded.len());
+        assert_eq!(
+            serde_json::from_slice::<Value>(&decoded).unwrap(),
+            json!({"hostName": "example.com", "port": 443})
+        );
+
+        assert!(matches!(encode_from_reader(&b"{oops"[..], std::io::sink()), Err(EncodeError::Serialize(_))));
+        assert!(matches!(decode_from_reader(&b"\xff"[..], std::io::sink()), Err(DecodeError::Input(_))));
+    }
+
+    #[test]
+    fn test_decompression_limit() {
+        // 4 МиБ нулей сжимаются в несколько килобайт
+        let bomb = vec![0u8; 4 * 1024 * 1024];
+        let compressed = compress_data(&bomb, flate2::Compression::best()).unwrap();
+        assert!(compressed.len() < 8 * 1024);
+        assert!(matches!(
+            decompress_data_limited(&compressed, 1024 * 1024),
+            Err(DecodeError::TooLarge(1048576))
+        ));
+        let gzipped = compress_gzip(&bomb, flate2::Compression::best()).unwrap();
+        assert!(matches!(decompress_gzip_limited(&gzipped, 1024), Err(DecodeError::TooLarge(1024))));
+        assert_eq!(decompress_data_limited(&compressed, bomb.len()).unwrap().len(), bomb.len());
+
+        // Заголовок врёт о размере, но распаковка всё равно ограничена
+        let config = json!({"padding": "x".repeat(4096)});
+        let json = serde_json::to_vec(&config).unwrap();
+        let payload = [&create_header(16)[..], &compress_data(&json, flate2::Compression::best()).unwrap()].concat();
+        let url = format!("{}{}", PREFIX, encode_base64(&payload));
+        let options = DecodeOptions { max_size: 1024, ..Default::default() };
+        match decode_with_options(&url, &options) {
+            Err(DecodeError::NoMatchingFormat(attempts)) => {
+                assert!(matches!(attempts[0].1, DecodeError::TooLarge(1024)));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        // Заявленный в заголовке размер сверх лимита отвергается сразу
+        let url = encode(&config).unwrap();
+        assert!(decode_with_options(&url, &options).is_err());
+        assert_eq!(decode(&url).unwrap(), config);
+    }
+
+    #[test]
+    fn test_implausible_header_length() {
+        let compressed = compress_data(b"{}", flate2::Compression::default()).unwrap();
+        let payload = [&create_header(u32::MAX)[..], &compressed].concat();
+        let unlimited = PayloadSettings { max_size: usize::MAX, ..Default::default() };
+        assert!(matches!(
+            try_decode_compressed(&payload, &unlimited),
+            Err(DecodeError::ImplausibleLength { declared: u32::MAX, compressed: 10 })
+        ));
+
+        // Реальная степень сжатия в пределах допустимой
+        assert_eq!(plausible_length(4 * 1024 * 1024, 4096).unwrap(), 4 * 1024 * 1024);
+        assert!(plausible_length(2, 0).is_ok());
+    }
+
+    #[test]
+    fn test_encode_context() {
+        let configs = [json!({"a": 1}), json!({"hostName": "example.com", "port": 443}), json!([])];
+        for compression in [Compression::Zlib, Compression::Gzip, Compression::Deflate, Compression::None] {
+            let options = EncodeOptions { compression, level: Some(9), ..Default::default() };
+            let mut context = EncodeContext::new(options.clone());
+            // Повторное использование даёт те же URL, что и одиночное кодирование
+            for config in configs.iter().chain(configs.iter()) {
+                assert_eq!(context.encode(config).unwrap(), encode_with_options(config, &options).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn test_header_endian() {
+        assert_eq!(create_header_with(0x0102_0304, Endian::Big), [1, 2, 3, 4]);
+        assert_eq!(create_header_with(0x0102_0304, Endian::Little), [4, 3, 2, 1]);
+        assert_eq!(read_header_with(&[4, 3, 2, 1, 0xff], Endian::Little).unwrap(), 0x0102_0304);
+        assert!(matches!(read_header_with(&[4, 3], Endian::Little), Err(DecodeError::TooShort)));
+
+        let config = json!({"hostName": "example.com", "containers": [{"container": "amnezia-awg"}]});
+        for compression in [Compression::Zlib, Compression::Gzip, Compression::Deflate, Compression::None] {
+            let little = EncodeOptions { compression, endian: Endian::Little, ..Default::default() };
+            let url = encode_with_options(&config, &little).unwrap();
+            let payload = decode_base64(url.strip_prefix(PREFIX).unwrap()).unwrap();
+            assert_eq!(read_header_with(&payload, Endian::Little).unwrap() as usize, to_string_pretty(&config).unwrap().len());
+
+            // Little Endian заголовок распознаётся при декодировании по умолчанию,
+            // а Big Endian — при --endian little
+            assert_eq!(decode(&url).unwrap(), config);
+            let big_url = encode_with_options(&config, &EncodeOptions { compression, ..Default::default() }).unwrap();
+            let options = DecodeOptions { endian: Endian::Little, ..Default::default() };
+            assert_eq!(decode_with_options(&big_url, &options).unwrap(), config);
+            assert_eq!(decode_with_options(&url, &options).unwrap(), config);
+        }
+
+        // Длина не сходится ни в одном порядке байтов
+        let json = to_string_pretty(&config).unwrap();
+        let header = create_header_with(json.len() as u32 + 1, Endian::Little);
+        let payload = [&header[..], &compress_data(json.as_bytes(), flate2::Compression::default()).unwrap()].concat();
+        assert!(decode(&format!("{}{}", PREFIX, encode_base64(&payload))).is_err());
+    }
+
+    #[test]
+    fn test_explain_url() {
+        let config = json!({"hostName": "example.com"});
+        let json = to_string_pretty(&config).unwrap();
+        let url = encode(&config).unwrap();
+
+        let explanation = explain_url(&url, &DecodeOptions::default()).unwrap();
+        assert_eq!(explanation.value, config);
+        assert_eq!(explanation.encoded_len, url.len() - PREFIX.len());
+        assert_eq!(explanation.format, Format::V1);
+        assert_eq!(explanation.compression, Compression::Zlib);
+        assert_eq!(explanation.declared, Some(json.len() as u32));
+        assert_eq!(explanation.json_len, json.len());
+        assert_eq!(explanation.compressed_len, explanation.payload.len() - 4);
+        assert_eq!(explanation.integrity, Some(true));
+
+        // Повреждённый заголовок не мешает разбору
+        let compressed = compress_data(json.as_bytes(), flate2::Compression::default()).unwrap();
+        let payload = [&[V2_MAGIC][..], &create_header(json.len() as u32 + 3), &compressed].concat();
+        let broken = format!("{}{}", PREFIX, encode_base64(&payload));
+        let explanation = explain_url(&broken, &DecodeOptions::default()).unwrap();
+        assert_eq!(explanation.format, Format::V2);
+        assert_eq!(explanation.header_offset(), 1);
+        assert_eq!(explanation.declared, Some(json.len() as u32 + 3));
+        assert_eq!(explanation.compressed_len, compressed.len());
+        assert_eq!(explanation.integrity, Some(false));
+    }
+
+    #[test]
+    fn test_repair_url() {
+        let config = json!({"hostName": "example.com", "containers": []});
+        let json = to_string_pretty(&config).unwrap();
+        let compressed = compress_data(json.as_bytes(), flate2::Compression::default()).unwrap();
+
+        // Заголовок намеренно неверен: на 10 байт больше, V1 и V2
+        for magic in [&[][..], &[V2_MAGIC][..]] {
+            let payload = [magic, &create_header(json.len() as u32 + 10), &compressed].concat();
+            let broken = format!("{}{}", PREFIX, encode_base64(&payload));
+            assert!(matches!(decode(&broken), Err(DecodeError::NoMatchingFormat(_))));
+
+            let repair = repair_url(&broken, &DecodeOptions::default()).unwrap();
+            assert!(repair.changed());
+            assert_eq!(repair.declared, Some(json.len() as u32 + 10));
+            assert_eq!(repair.actual, json.len());
+            assert_eq!(decode(&repair.url).unwrap(), config);
+
+            // Без восстановления такой URL не декодируется, с ним — декодируется
+            let options = DecodeOptions { repair: true, ..Default::default() };
+            assert_eq!(decode_with_options(&broken, &options).unwrap(), config);
+        }
+
+        // Неправдоподобный заголовок тоже исправляется
+        let payload = [&create_header(u32::MAX - 1)[..], &compressed].concat();
+        let repair = repair_url(&format!("{}{}", PREFIX, encode_base64(&payload)), &DecodeOptions::default()).unwrap();
+        assert_eq!(decode(&repair.url).unwrap(), config);
+
+        // Корректный URL не меняется
+        let url = encode(&config).unwrap();
+        let repair = repair_url(&url, &DecodeOptions::default()).unwrap();
+        assert!(!repair.changed());
+        assert_eq!(repair.url, url);
+
+        let options = EncodeOptions { sign_key: Some(b"key".to_vec()), ..Default::default() };
+        let signed = encode_with_options(&config, &options).unwrap();
+        assert!(matches!(repair_url(&signed, &DecodeOptions::default()), Err(DecodeError::RepairSigned)));
+    }
+
+    #[test]
+    fn test_deterministic_encode() {
+        // Одинаковые по смыслу конфигурации: другой порядок ключей, пробелы и запись чисел
+        let first: Value = from_str(r#"{"hostName": "example.com", "awg": {"mtu": 1280, "port": "51820"}, "jc": 1e1}"#).unwrap();
+        let second: Value = from_str("{\"jc\":10.0,\n \"awg\":{\"port\":\"51820\",\"mtu\":1280},\"hostName\":\"example.com\"}").unwrap();
+
+        let options = EncodeOptions::deterministic();
+        let url = encode_with_options(&first, &options).unwrap();
+        assert_eq!(url, encode_with_options(&second, &options).unwrap());
+        assert_eq!(url, encode_with_options(&first, &options).unwrap());
+        assert_eq!(decode(&url).unwrap(), second);
+
+        // JSON внутри компактный и с отсортированными ключами
+        let mut raw = Vec::new();
+        decode_to_writer(&url, &mut raw).unwrap();
+        assert_eq!(
+            String::from_utf8(raw).unwrap(),
+            r#"{"awg":{"mtu":1280,"port":"51820"},"hostName":"example.com","jc":10.0}"#
+        );
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let config = json!({"hostName": "example.com", "containers": [{"container": "amnezia-awg"}]});
+        let hash = content_hash(&config);
+        assert_eq!(hash.len(), 64);
+        // SHA-256 от `{"containers":[{"container":"amnezia-awg"}],"hostName":"example.com"}`
+        assert_eq!(hash, "5337dbf5b380c13f8ae70459120b0efc9e4f6ab9f35352bd98229fbc7c16c924");
+
+        // Разные способы кодирования одной конфигурации дают один хеш
+        let gzip = EncodeOptions { compression: Compression::Gzip, preserve_order: true, ..Default::default() };
+        for options in [EncodeOptions::default(), gzip, EncodeOptions::deterministic()] {
+            let url = encode_with_options(&config, &options).unwrap();
+            assert_eq!(content_hash(&decode(&url).unwrap()), hash);
+        }
+        assert_ne!(content_hash(&json!({"hostName": "example.org"})), hash);
+    }
+
+    #[test]
+    fn test_no_prefix() {
+        let config = json!({"hostName": "example.com"});
+        let url = encode(&config).unwrap();
+        let blob = url.strip_prefix(PREFIX).unwrap();
+
+        let options = DecodeOptions { no_prefix: true, ..Default::default() };
+        assert_eq!(decode_with_options(blob, &options).unwrap(), config);
+        assert!(matches!(decode(blob), Err(DecodeError::MissingPrefix)));
+        // Со схемой такой вход уже не является чистым Base64
+        assert!(decode_with_options(&url, &options).is_err());
+
+        // Вложенный слой декодируется со схемой
+        let twice = encode(&Value::String(url.clone())).unwrap();
+        let options = DecodeOptions { recursive: true, ..options };
+        assert_eq!(decode_with_options(twice.strip_prefix(PREFIX).unwrap(), &options).unwrap(), config);
+    }
+
+    #[test]
+    fn test_raw_deflate() {
+        let config = json!({"hostName": "example.com", "port": 51820});
+        let json = serde_json::to_vec_pretty(&config).unwrap();
+
+        // zlib-обёртка и «сырой» deflate с одинаковым заголовком длины
+        let header = create_header(json.len() as u32);
+        let zlib = [&header[..], &compress_data(&json, flate2::Compression::default()).unwrap()].concat();
+        let raw = [&header[..], &compress_deflate(&json, flate2::Compression::default()).unwrap()].concat();
+        for (payload, compression) in [(zlib, Compression::Zlib), (raw, Compression::Deflate)] {
+            let result = decode_detailed(&format!("{}{}", PREFIX, encode_base64(&payload))).unwrap();
+            assert_eq!(result.value, config);
+            assert_eq!((result.format, result.compression), (Format::V1, compression));
+        }
+
+        // Кодирование «сырым» deflate и обратно, в том числе V2
+        for format_version in [FormatVersion::V1, FormatVersion::V2] {
+            let options = EncodeOptions { compression: Compression::Deflate, format_version, ..Default::default() };
+            let url = encode_with_options(&config, &options).unwrap();
+            let result = decode_detailed(&url).unwrap();
+            assert_eq!(result.value, config);
+            assert_eq!(result.compression, Compression::Deflate);
+        }
+    }
+
+    #[test]
+    fn test_normalize_nfc() {
+        // «й» как «и» + комбинируемый знак краткой и как один символ U+0439
+        let decomposed = "Сервер-\u{0438}\u{0306}";
+        let composed = "Сервер-\u{0439}";
+        let mut config = serde_json::json!({
+            "hostName": decomposed,
+            "containers": [{"description": format!("e\u{0301} {}", decomposed)}],
+            "mtu": 1280
+        });
+        normalize_nfc(&mut config);
+        assert_eq!(config["hostName"], composed);
+        assert_eq!(config["containers"][0]["description"], format!("\u{e9} {}", composed));
+        assert_eq!(config["mtu"], 1280);
+    }
+
+    #[test]
+    fn test_decode_reports_all_attempts() {
+        let url = format!("vpn://{}", encode_base64(b"\x00\x00\x00\x05garbage"));
+        match decode(&url) {
+            Err(DecodeError::NoMatchingFormat(attempts)) => {
+                let formats: Vec<&str> = attempts.iter().map(|(format, _)| *format).collect();
+                assert_eq!(formats, vec!["compressed", "raw deflate", "uncompressed", "plain"]);
+                assert!(matches!(attempts[0].1, DecodeError::Decompress(_)));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
\ No newline at end of file
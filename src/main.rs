@@ -1,260 +1,2168 @@
-use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
-use flate2::write::ZlibEncoder;
-use flate2::read::ZlibDecoder;
-use flate2::Compression;
-use serde_json::{Value, to_string_pretty, from_str};
-use std::io::{Write, Read};
-
-const PREFIX: &str = "vpn://";
-
-/// Преобразует JSON конфигурацию в VPN URL
-pub fn encode(config: &Value) -> Result<String, Box<dyn std::error::Error>> {
-    // 1. Сериализация в JSON с отступами
-    let json_string = to_string_pretty(config)?;
-    let original_data = json_string.as_bytes();
-    let original_data_len = original_data.len() as u32;
-    
-    // 2. Сжатие данных
-    let compressed_data = compress_data(original_data)?;
-    
-    // 3. Создание заголовка (4 байта, Big Endian)
-    let header = create_header(original_data_len);
-    
-    // 4. Объединение заголовка и сжатых данных
-    let mut combined = header.to_vec();
-    combined.extend_from_slice(&compressed_data);
-    
-    // 5. Base64 URL-safe кодирование (без padding)
-    let encoded = encode_base64(&combined);
+use amnezia_config_decoder_rust::{
+    decode_to_writer_with_options, explain_unknown_input, decode_with_options, decode_with_stats, detect_input_type,
+    content_hash, explain_url, repair_url, url_params, wrap_url,
+    encode_with_options, encode_with_stats, normalize_nfc, sort_keys, strip_json_comments, Compression, DecodeError, DecodeOptions, DecodeStats, EncodeStats,
+    Explanation, Format, Base64Alphabet, EncodeContext, EncodeOptions, Endian, FormatVersion, InputType, PREFIX,
+};
+use amnezia_config_decoder_rust::convert::{from_format, to_format, DataFormat};
+use amnezia_config_decoder_rust::diff::{diff, Change};
+use amnezia_config_decoder_rust::export::{export, ExportFormat};
+use amnezia_config_decoder_rust::merge::{merge, ArrayMerge};
+use amnezia_config_decoder_rust::nested::{collapse_nested, collapse_value, expand_nested, MAX_NESTED_DEPTH};
+use amnezia_config_decoder_rust::path::{self, select};
+use amnezia_config_decoder_rust::qr::{read_qr_image, render_qr_unicode, save_qr_png};
+use amnezia_config_decoder_rust::redact::{is_sensitive, redact, redact_profile, MASK};
+use amnezia_config_decoder_rust::split::{is_part, join_parts, split_url, MAX_PARTS};
+use amnezia_config_decoder_rust::template::substitute;
+use amnezia_config_decoder_rust::validate::{validate_config, validate_schema};
+use amnezia_config_decoder_rust::{EncodeError, QrError};
+use log::{info, warn};
+use serde_json::{Value, to_string, to_string_pretty, from_str};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use highlight::{color_enabled, highlight_json};
+use usage::{usage, Lang};
+
+mod cli;
+mod highlight;
+mod usage;
+
+/// Параметры командной строки
+#[derive(Debug, Default, Clone)]
+struct Options {
+    explicit_mode: Option<String>,
+    /// Все файлы из повторяемых -i / -o
+    input_files: Vec<String>,
+    output_files: Vec<String>,
+    /// Вход и выход текущего задания
+    input_file: Option<String>,
+    output_file: Option<String>,
+    direct_input: Vec<String>,
+    encode: EncodeOptions,
+    decode: DecodeOptions,
+    compact: bool,
+    /// Отступ для --indent / --indent-tab; `None` — два пробела
+    indent: Option<String>,
+    preserve_order: bool,
+    deterministic: bool,
+    /// Вывести хеш конфигурации (--hash sha256) вместо результата
+    hash: bool,
+    strict: bool,
+    batch: bool,
+    /// Интерактивный режим: каждая строка stdin обрабатывается отдельно
+    repl: bool,
+    ndjson: bool,
+    json_array: bool,
+    output_format: BatchFormat,
+    /// Поле с именем конфигурации для --output-format; `None` —
+    /// `description`, затем `name`
+    name_field: Option<String>,
+    /// Вывести в stderr сводку пакетной обработки (--report)
+    report: bool,
+    qr: bool,
+    qr_png: Option<String>,
+    clipboard: bool,
+    qr_in: Option<String>,
+    stats: bool,
+    /// На сколько частей разделить закодированный URL (--split)
+    split: Option<usize>,
+    /// Показать слои URL перед выводом JSON (--explain)
+    explain: bool,
+    compare_size: bool,
+    raw: bool,
+    field: Option<String>,
+    sets: Vec<(String, Value)>,
+    /// Подставить переменные в текст входа до разбора (--template)
+    template: bool,
+    /// Значения переменных из --var в порядке флагов
+    vars: Vec<(String, String)>,
+    strict_vars: bool,
+    stamp: Option<String>,
+    nfc: bool,
+    diff: Option<(String, String)>,
+    /// Файлы --merge: базовая конфигурация и переопределения
+    merge: Option<(String, String)>,
+    merge_arrays: ArrayMerge,
+    export: Option<ExportFormat>,
+    to: DataFormat,
+    from: Option<DataFormat>,
+    wrap: usize,
+    null: bool,
+    schema: Option<String>,
+    redact: bool,
+    decode_nested: bool,
+    encode_nested: bool,
+    /// Пути из --nested-field; пусто — поля из `NESTED_FIELDS`
+    nested_fields: Vec<String>,
+    redact_keys: Vec<String>,
+    summary: bool,
+    input_env: Option<String>,
+    from_clipboard: bool,
+    /// HTTP(S) адрес из --fetch, откуда загружается вход
+    fetch: Option<String>,
+    secure_output: bool,
+    tee: bool,
+    in_place: bool,
+    /// Дописывать результат в конец файла -o (--append)
+    append: bool,
+    /// Число потоков пакетного режима; 0 — по числу ядер
+    jobs: usize,
+    /// Отключить подсветку JSON в терминале (--no-color)
+    no_color: bool,
+    /// Не показывать индикатор прогресса пакетного режима (--quiet)
+    quiet: bool,
+    /// Переводы строк вывода из --crlf / --lf; `None` — по умолчанию
+    /// платформы для файлов и LF для stdout
+    line_ending: Option<LineEnding>,
+    verbose: u8,
+}
+
+/// Стиль перевода строк в выводе
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LineEnding {
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    /// Перевод строк, принятый в текстовых файлах платформы
+    fn platform() -> Self {
+        if cfg!(windows) { LineEnding::Crlf } else { LineEnding::Lf }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+        }
+    }
+}
+
+/// Вид закодированных записей в выводе пакетного режима (--output-format)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum BatchFormat {
+    /// Только URL (по умолчанию)
+    #[default]
+    Plain,
+    /// `имя<TAB>URL`; запись без имени выводится одним URL
+    UrlList,
+    /// JSON массив объектов `{"name": ..., "url": ...}`
+    Json,
+}
+
+/// Результат обработки одного элемента
+enum Processed {
+    /// URL и имя конфигурации, если оно нужно для --output-format
+    Encoded { url: String, name: Option<String> },
+    /// Конфигурация и длина JSON после распаковки для --report
+    Decoded { value: Value, json_len: usize },
+    /// Пустая строка NDJSON: выводится пустой строкой, чтобы не сбить нумерацию
+    Skipped,
+}
+
+/// Ошибка обработки элемента пакета
+#[derive(Debug)]
+struct ItemError {
+    /// Имя варианта ошибки; для декодирования — основной причины,
+    /// см. [`DecodeError::primary_cause`]
+    kind: &'static str,
+    message: String,
+}
+
+impl ItemError {
+    fn new(kind: &'static str, message: impl Into<String>) -> Self {
+        ItemError { kind, message: message.into() }
+    }
+}
+
+impl std::fmt::Display for ItemError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Номер записи пакета (с 1) и результат её обработки
+type BatchResult = (usize, Result<Processed, ItemError>);
+
+/// Коды выхода программы
+mod exit_code {
+    /// Успех
+    pub const OK: i32 = 0;
+    /// Прочие ошибки; для --diff — конфигурации различаются; в пакетном
+    /// режиме — хотя бы одна запись не обработана
+    pub const FAILURE: i32 = 1;
+    /// Неверные аргументы командной строки
+    pub const USAGE: i32 = 2;
+    /// Входные данные не найдены, не читаются или тип не распознан
+    pub const INPUT: i32 = 3;
+    /// Ошибка декодирования URL
+    pub const DECODE: i32 = 4;
+    /// Конфигурация не прошла проверку (--validate --strict, --schema, --export)
+    /// или не представима в формате из --to
+    pub const VALIDATION: i32 = 5;
+    /// Некорректный JSON или ошибка кодирования
+    pub const ENCODE: i32 = 6;
+    /// Ошибка записи результата (файл, буфер обмена, QR код)
+    pub const OUTPUT: i32 = 7;
+}
+
+fn main() {
+    if let Err(e) = run_jobs() {
+        exit_with_run_error(e.as_ref());
+    }
+}
+
+/// Сообщает об ошибке, дошедшей до `main`, и завершает программу. Закрытый
+/// читателем stdout (`| head`) — не ошибка: программа молча завершается
+fn exit_with_run_error(e: &(dyn std::error::Error + 'static)) -> ! {
+    if e.downcast_ref::<std::io::Error>().is_some_and(|e| e.kind() == std::io::ErrorKind::BrokenPipe) {
+        std::process::exit(exit_code::OK);
+    }
+    let (kind, text, code) = classify_error(e);
+    fail(kind, &format!("❌ {}: {}", text, e), code);
+}
+
+/// Вид, описание и код выхода ошибки, дошедшей до `main`. К ошибкам записи
+/// результата (код 7) относятся только ошибки ввода-вывода
+fn classify_error(e: &(dyn std::error::Error + 'static)) -> (&'static str, &'static str, i32) {
+    if e.is::<std::io::Error>() || e.is::<tempfile::PersistError>() {
+        ("Output", "Ошибка записи результата", exit_code::OUTPUT)
+    } else if e.is::<serde_json::Error>() {
+        ("Serialize", "Ошибка сериализации результата", exit_code::FAILURE)
+    } else if e.is::<rayon::ThreadPoolBuildError>() {
+        ("ThreadPool", "Не удалось запустить потоки обработки", exit_code::FAILURE)
+    } else {
+        ("Failure", "Ошибка", exit_code::FAILURE)
+    }
+}
+
+/// Разбирает аргументы и выполняет все задания
+fn run_jobs() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().collect();
+    let options = parse_args(&args);
+    init_logger(options.verbose);
+
+    if let Some((first, second)) = &options.diff {
+        run_diff(first, second, &options);
+    }
+
+    // Части URL из --split в нескольких -i собираются в одно задание
+    if let Some(url) = join_input_parts(&options) {
+        let mut job = options.clone();
+        job.input_file = None;
+        job.output_file = options.output_files.first().cloned();
+        job.direct_input = vec![url];
+        return run(job);
+    }
+
+    // Каждый файл -i обрабатывается отдельным заданием, в свой -o или в общий вывод
+    for (input_file, output_file) in plan_jobs(&options) {
+        let mut job = options.clone();
+        job.input_file = input_file;
+        job.output_file = output_file;
+        run(job)?;
+    }
+    // Ошибки отдельных записей пакета не прерывают работу, но видны по коду выхода
+    if ITEMS_FAILED.load(Ordering::Relaxed) {
+        std::process::exit(exit_code::FAILURE);
+    }
+    Ok(())
+}
+
+/// Если файлы -i — части одного URL, собирает и возвращает его. Файлы
+/// читаются заранее, поэтому stdin (`-i -`) здесь не рассматривается
+fn join_input_parts(options: &Options) -> Option<String> {
+    let inputs = &options.input_files;
+    let decoding = options.explicit_mode.as_deref().is_none_or(|mode| mode == "decode");
+    if !decoding || options.in_place || options.batch || inputs.len() < 2 || inputs.iter().any(|input| input == STDIO_PATH) {
+        return None;
+    }
+    let contents: Vec<String> = inputs.iter().map(|input| read_file(input).ok()).collect::<Option<_>>()?;
+    let prefix = options.decode.scheme.as_deref().unwrap_or(PREFIX);
+    if !contents.iter().any(|content| is_part(content, prefix)) {
+        return None;
+    }
+    let urls: Vec<&str> = contents.iter().map(|content| content.trim()).collect();
+    Some(join_parts_or_exit(&urls, prefix))
+}
+
+/// Сопоставляет файлы -i и -o. Несколько -o допустимы только в том же
+/// количестве, что и -i; с --in-place каждый -i служит и выходом
+fn plan_jobs(options: &Options) -> Vec<(Option<String>, Option<String>)> {
+    let inputs = &options.input_files;
+    let outputs = &options.output_files;
+    if options.in_place {
+        if options.append {
+            fail("Usage", "❌ Ошибка: --in-place несовместим с --append", exit_code::USAGE);
+        }
+        if inputs.is_empty() || !outputs.is_empty() || inputs.iter().any(|input| input == STDIO_PATH) {
+            fail("Usage", "❌ Ошибка: --in-place требует -i с файлом и несовместим с -o", exit_code::USAGE);
+        }
+        return inputs.iter().map(|input| (Some(input.clone()), Some(input.clone()))).collect();
+    }
+    if inputs.len() <= 1 && outputs.len() <= 1 {
+        return vec![(inputs.first().cloned(), outputs.first().cloned())];
+    }
+    if !outputs.is_empty() && outputs.len() != inputs.len() {
+        let text = format!(
+            "❌ Ошибка: количество -o ({}) не совпадает с количеством -i ({})",
+            outputs.len(),
+            inputs.len()
+        );
+        fail("Usage", &text, exit_code::USAGE);
+    }
+    inputs
+        .iter()
+        .enumerate()
+        .map(|(index, input)| (Some(input.clone()), outputs.get(index).cloned()))
+        .collect()
+}
+
+/// Выполняет одно задание: читает вход, определяет режим и обрабатывает его
+fn run(mut options: Options) -> Result<(), Box<dyn std::error::Error>> {
+    if options.repl {
+        return run_repl(&options);
+    }
+
+    // Получаем входные данные
+    let input = if let Some(path) = &options.qr_in {
+        let url = read_qr_or_exit(path);
+        options.explicit_mode.get_or_insert_with(|| "decode".to_string());
+        url
+    } else if let Some((base, overrides)) = &options.merge {
+        if options.explicit_mode.as_deref().is_some_and(|mode| mode != "encode") {
+            fail("Usage", "❌ Ошибка: --merge только кодирует объединённую конфигурацию", exit_code::USAGE);
+        }
+        let merged = merge_files_or_exit(base, overrides, &options);
+        // Объединённая конфигурация дальше кодируется как обычный JSON
+        options.explicit_mode = Some("encode".to_string());
+        options.from = None;
+        to_string(&merged)?
+    } else {
+        let input_env = options.input_env.as_deref().unwrap_or(DEFAULT_INPUT_ENV);
+        let input = get_input(
+            options.input_file.clone(),
+            options.direct_input.clone(),
+            options.fetch.as_deref(),
+            options.from_clipboard,
+            input_env,
+        );
+        match input {
+            Ok(input) => input,
+            Err(e) => {
+                fail("Input", &format!("❌ Ошибка: не удалось прочитать входные данные ({})", e), exit_code::INPUT);
+            }
+        }
+    };
+
+    let input = if options.template {
+        match substitute(&input, &options.vars, options.strict_vars) {
+            Ok(input) => input,
+            Err(e) => {
+                fail("Input", &format!("❌ Ошибка: не удалось заполнить шаблон ({})", e), exit_code::INPUT);
+            }
+        }
+    } else {
+        input
+    };
+
+    if options.batch {
+        return run_batch(&input, &options);
+    }
     
-    // 6. Добавление префикса
-    Ok(format!("{}{}", PREFIX, encoded))
+    // Формат входа: из --from или по расширению файла из -i
+    let input_format = options.from.or_else(|| {
+        options.input_file.as_deref().and_then(|path| DataFormat::from_extension(Path::new(path)))
+    });
+
+    // Определяем режим работы: -e/-d, затем --from и расширение файла,
+    // затем содержимое входа
+    let mode = if let Some(explicit) = options.explicit_mode.clone() {
+        if input_format.is_none_or(|format| format == DataFormat::Json)
+            && let Some(hint) = mode_mismatch_hint(&explicit, &input, &options)
+        {
+            warn!("💡 {}", hint);
+        }
+        explicit
+    } else if let Some(mode) = hinted_mode(input_format, options.input_file.as_deref()) {
+        info!("🔍 Автодетект: режим выбран по формату входа ({})", mode);
+        mode.to_string()
+    } else {
+        // Автодетект
+        match detect_mode(&input, &options) {
+            Some("decode") => {
+                info!("🔍 Автодетект: обнаружен VPN URL, выполняется декодирование");
+                "decode".to_string()
+            }
+            Some(_) => {
+                info!("🔍 Автодетект: обнаружен JSON, выполняется кодирование");
+                "encode".to_string()
+            }
+            None => {
+                let text = match explain_unknown_input(&input) {
+                    Some(reason) => format!("❌ Ошибка: не удалось определить тип входных данных: {}", reason),
+                    None => "❌ Ошибка: не удалось определить тип входных данных".to_string(),
+                };
+                let hint = "   Используйте -e для кодирования или -d для декодирования";
+                fail("UnknownInput", &format!("{}\n{}", text, hint), exit_code::INPUT);
+            }
+        }
+    };
+
+    let input = match add_missing_scheme(&input, &options) {
+        Some(url) if mode == "decode" => {
+            info!("🔍 Входные данные без схемы URL, декодируются как Base64");
+            url
+        }
+        _ => input,
+    };
+
+    if options.compact && mode == "encode" {
+        warn!("⚠️  Флаг --compact игнорируется при кодировании");
+    }
+    if options.tee && ((options.output_file.is_none() && !options.clipboard) || options.secure_output) {
+        warn!("⚠️  Флаг --tee используется только вместе с -o или --clipboard");
+    }
+    if options.append && options.output_file.as_deref().is_none_or(|filename| filename == STDIO_PATH) {
+        warn!("⚠️  Флаг --append используется только вместе с -o FILE");
+    }
+    if (options.qr || options.qr_png.is_some()) && mode != "encode" {
+        warn!("⚠️  Флаги --qr/--qr-png используются только при кодировании");
+    }
+
+    // Разбор слоёв выводится до любого вида вывода декодирования
+    if options.explain && mode == "decode" {
+        explain_or_exit(&input, &options.decode);
+    }
+
+    // Выполняем операцию
+    match mode.as_str() {
+        "encode" => {
+            let mut config = match input_format {
+                Some(format @ (DataFormat::Yaml | DataFormat::Toml)) => from_format(&input, format)
+                    .unwrap_or_else(|e| {
+                        fail("InvalidInput", &format!("❌ Ошибка: некорректный {} ({})", format, e), exit_code::ENCODE);
+                    }),
+                _ => {
+                    let text = strip_json_comments(&input);
+                    from_str(text).unwrap_or_else(|e| {
+                        fail("InvalidJson", &format!("❌ Ошибка: {}", describe_json_error(&input, text, &e, true)), exit_code::ENCODE);
+                    })
+                }
+            };
+            apply_sets(&mut config, &options.sets);
+            if options.nfc {
+                normalize_nfc(&mut config);
+            }
+            if options.encode_nested {
+                encode_nested_or_exit(&mut config, &options.nested_fields);
+            }
+            if let Some(key) = &options.stamp
+                && !stamp(&mut config, key)
+            {
+                warn!("⚠️  --stamp игнорируется: конфигурация не является JSON объектом");
+            }
+            if options.hash {
+                // Хеш той конфигурации, которая попала бы в URL
+                return write_output(&options, &content_hash(&config));
+            }
+            if options.compare_size {
+                match compare_sizes(&config, &options.encode) {
+                    Ok(table) => eprintln!("{}", table),
+                    Err(e) => warn!("⚠️  Не удалось сравнить размеры: {}", e),
+                }
+            }
+            let (encoded, stats) = encode_or_exit(&config, &options.encode);
+            // С --split статистика относится к исходному URL до деления
+            if options.stats {
+                eprintln!("📊 JSON: {} байт", stats.json_len);
+                eprintln!("   Сжатые данные: {} байт", stats.compressed_len);
+                eprintln!("   URL: {} символов", stats.url_len);
+                eprintln!("   Коэффициент сжатия: {:.2}", stats.ratio());
+            }
+            if let Some(parts) = options.split {
+                // Каждая часть — на своей строке и со своим QR кодом
+                let parts = split_or_exit(&encoded, parts, &options);
+                write_output(&options, &parts.join("\n"))?;
+                for part in &parts {
+                    write_qr(part, &options);
+                }
+                return Ok(());
+            }
+            write_output(&options, &wrap_url(&encoded, options.wrap))?;
+            write_qr(&encoded, &options);
+        }
+        "decode" if options.raw => {
+            let url = input.trim();
+            if writes_to_stdout(&options) {
+                // В stdout без промежуточного буфера и без завершающего перевода строки
+                let stdout = std::io::stdout().lock();
+                if let Err(e) = decode_to_writer_with_options(url, &options.decode, stdout) {
+                    exit_with_decode_error(&e);
+                }
+            } else {
+                let mut raw = Vec::new();
+                if let Err(e) = decode_to_writer_with_options(url, &options.decode, &mut raw) {
+                    exit_with_decode_error(&e);
+                }
+                write_output(&options, &String::from_utf8_lossy(&raw))?;
+            }
+        }
+        "decode" if options.export.is_some() => {
+            let (mut config, _) = decode_or_exit(&input, &options.decode);
+            check_schema(&config, &options);
+            if options.redact {
+                // Блобы раскрываются, чтобы секреты в них тоже замаскировались
+                expand_nested(&mut config, MAX_NESTED_DEPTH);
+                redact(&mut config, &options.redact_keys);
+            }
+            let format = options.export.expect("checked by match guard");
+            match export(&config, format) {
+                // Профиль .ovpn может лежать в блобе, недоступном redact
+                Ok(exported) if options.redact => {
+                    write_output(&options, redact_profile(&exported, &options.redact_keys).trim_end())?
+                }
+                Ok(exported) => write_output(&options, exported.trim_end())?,
+                Err(e) => {
+                    fail("Validation", &format!("❌ Ошибка экспорта: {}", e), exit_code::VALIDATION);
+                }
+            }
+        }
+        "decode" if options.hash => {
+            let (config, _) = decode_or_exit(&input, &options.decode);
+            check_schema(&config, &options);
+            write_output(&options, &content_hash(&config))?;
+        }
+        "decode" if options.summary => {
+            let (config, _) = decode_or_exit(&input, &options.decode);
+            check_schema(&config, &options);
+            write_output(&options, &format_summary(&config))?;
+        }
+        "decode" if !options.sets.is_empty() => {
+            // Декодирование → изменение полей → повторное кодирование
+            let (mut config, _) = decode_or_exit(&input, &options.decode);
+            check_schema(&config, &options);
+            apply_sets(&mut config, &options.sets);
+            let (encoded, _) = encode_or_exit(&config, &options.encode);
+            write_output(&options, &wrap_url(&encoded, options.wrap))?;
+        }
+        "decode" => {
+            let (mut decoded, stats) = decode_or_exit(&input, &options.decode);
+            check_schema(&decoded, &options);
+            if options.decode_nested {
+                let expanded = expand_nested(&mut decoded, MAX_NESTED_DEPTH);
+                info!("🔍 Раскрыто вложенных блобов: {}", expanded);
+            }
+            if !options.preserve_order {
+                sort_keys(&mut decoded);
+            }
+            if options.redact {
+                redact(&mut decoded, &options.redact_keys);
+            }
+            let output = render_decoded(&decoded, &options).unwrap_or_else(|e| {
+                let code = if e.kind == "Convert" { exit_code::VALIDATION } else { exit_code::FAILURE };
+                fail(e.kind, &format!("❌ Ошибка: {}", e), code);
+            });
+            // Подсвечивается только полный JSON и только в терминале
+            let json_output = options.field.is_none() && options.to == DataFormat::Json;
+            if json_output && color_enabled(options.no_color) {
+                write_output_with(&options, &output, &highlight_json(&output))?;
+            } else {
+                write_output(&options, &output)?;
+            }
+            if options.stats {
+                eprintln!("📊 Данные после Base64: {} байт", stats.payload_len);
+                eprintln!("   JSON после распаковки: {} байт", stats.json_len);
+            }
+        }
+        "check" => {
+            // Конфигурация не выводится: в логах CI не должно быть секретов
+            match check_url(&input, &options.decode) {
+                Ok(()) => eprintln!("OK"),
+                Err(e) => exit_with_decode_error(&e),
+            }
+        }
+        "repair" => match repair_url(&input, &options.decode) {
+            Ok(repair) => {
+                if let Some(declared) = repair.declared
+                    && repair.changed()
+                {
+                    eprintln!("⚠️  ВНИМАНИЕ: проверка целостности отключена (--repair)!");
+                    eprintln!(
+                        "   Заголовок заявлял {} байт, распаковано {} байт: распакованные данные",
+                        declared, repair.actual
+                    );
+                    eprintln!("   приняты как верные, заголовок пересчитан. Проверьте конфигурацию перед использованием");
+                } else {
+                    info!("🔍 Заголовок корректен, исправлять нечего");
+                }
+                write_output(&options, &wrap_url(&repair.url, options.wrap))?;
+            }
+            Err(e) => exit_with_decode_error(&e),
+        },
+        "validate" => {
+            let (decoded, _) = decode_or_exit(&input, &options.decode);
+            check_schema(&decoded, &options);
+            let issues = validate_config(&decoded);
+            if issues.is_empty() {
+                eprintln!("✅ Конфигурация прошла проверку");
+            } else {
+                let marker = if options.strict { "❌" } else { "⚠️ " };
+                let text = format!("{} Найдено проблем в конфигурации: {}", marker, issues.len());
+                let text = issues.iter().fold(text, |text, issue| format!("{}\n   - {}", text, issue));
+                if options.strict {
+                    fail("Validation", &text, exit_code::VALIDATION);
+                }
+                eprintln!("{}", text);
+            }
+        }
+        _ => unreachable!(),
+    }
+
+    Ok(())
+}
+
+/// Настраивает журнал: без -v только предупреждения и ошибки, -v — этапы
+/// обработки (debug), -vv — все подробности (trace). `RUST_LOG` имеет приоритет
+fn init_logger(verbose: u8) {
+    let level = match verbose {
+        0 => log::LevelFilter::Warn,
+        1 => log::LevelFilter::Debug,
+        _ => log::LevelFilter::Trace,
+    };
+    env_logger::Builder::new()
+        .filter_level(level)
+        .parse_default_env()
+        .format(|buf, record| {
+            use std::io::Write;
+            match record.level() {
+                log::Level::Debug | log::Level::Trace => {
+                    writeln!(buf, "[{}] {}", record.level().as_str().to_lowercase(), record.args())
+                }
+                _ => writeln!(buf, "{}", record.args()),
+            }
+        })
+        .init();
+}
+
+/// Разбирает аргументы командной строки
+fn parse_args(args: &[String]) -> Options {
+    // Ошибки разбора самих аргументов тоже должны выводиться в JSON
+    if args.iter().any(|arg| arg == "--json-errors") {
+        JSON_ERRORS.store(true, Ordering::Relaxed);
+    }
+    let matches = cli::command()
+        .try_get_matches_from(args)
+        .unwrap_or_else(|e| cli::exit_with_error(e, exit_code::USAGE));
+    // Подкоманда задаёт режим, флаги режима после неё его уточняют
+    let (matches, subcommand_mode) = match matches.subcommand() {
+        Some((name, matches)) => {
+            let mode = cli::SUBCOMMANDS.iter().find(|(command, _)| *command == name).map(|(_, mode)| *mode);
+            (matches, mode)
+        }
+        None => (&matches, None),
+    };
+    let flag = |id: &str| matches.get_flag(id);
+    let value = |id: &str| matches.get_one::<String>(id).cloned();
+    let values = |id: &str| -> Vec<String> { matches.get_many::<String>(id).into_iter().flatten().cloned().collect() };
+
+    // --version и --selftest завершают программу сразу, даже вместе с --help
+    if flag("version") {
+        println!("{}", version_string());
+        std::process::exit(exit_code::OK);
+    }
+    if flag("selftest") {
+        match selftest() {
+            Ok(()) => {
+                println!("PASS");
+                std::process::exit(exit_code::OK);
+            }
+            Err(e) => {
+                println!("FAIL: {}", e);
+                std::process::exit(exit_code::FAILURE);
+            }
+        }
+    }
+
+    let explicit_mode = cli::MODES.iter().find(|mode| flag(mode)).copied().or(subcommand_mode);
+    let mut options = Options { explicit_mode: explicit_mode.map(str::to_string), ..Default::default() };
+    // У подкоманды export формат — позиционный аргумент
+    let export_format = matches.try_get_one::<String>("export-format").ok().flatten();
+    if let Some(format) = export_format.or(matches.get_one("export")) {
+        options.export = Some(parse_export_format(format));
+    }
+
+    options.summary = flag("summary");
+    options.strict = flag("strict");
+    options.redact_keys = values("redact-key");
+    options.redact = flag("redact") || !options.redact_keys.is_empty();
+    options.schema = value("schema");
+    options.decode_nested = flag("decode-nested");
+    options.nested_fields = values("nested-field");
+    options.encode_nested = flag("encode-nested") || !options.nested_fields.is_empty();
+    options.input_files = values("input");
+    options.output_files = values("output");
+    options.input_env = value("input-env");
+    options.from_clipboard = flag("from-clipboard");
+    options.fetch = value("fetch");
+    options.secure_output = flag("secure-output");
+    options.tee = flag("tee");
+    options.in_place = flag("in-place");
+    options.append = flag("append");
+    // Явная сортировка (--sort-keys) отменяет --preserve-order, указанный раньше
+    options.preserve_order = flag("preserve-order");
+    if let Some(width) = value("indent") {
+        options.indent = Some(" ".repeat(parse_indent(&width)));
+    } else if flag("indent-tab") {
+        options.indent = Some("\t".to_string());
+    }
+    options.compact = flag("compact");
+    options.encode.level = value("level").map(|level| parse_level(&level));
+    if let Some(width) = value("wrap") {
+        options.wrap = parse_wrap(&width);
+    }
+    options.qr = flag("qr");
+    options.qr_png = value("qr-png");
+    options.clipboard = flag("clipboard");
+    options.qr_in = value("qr-in");
+    options.decode.no_prefix = flag("no-prefix");
+    if let Some(scheme) = value("scheme") {
+        let scheme = parse_scheme(&scheme);
+        options.encode.scheme = Some(scheme.clone());
+        options.decode.scheme = Some(scheme);
+    }
+    if let Some(format) = value("format") {
+        options.encode.compression = parse_compression(&format);
+    } else if flag("no-compress") {
+        options.encode.compression = Compression::None;
+    } else if flag("deflate-raw") {
+        options.encode.compression = Compression::Deflate;
+    }
+    options.encode.minify = flag("minify");
+    options.deterministic = flag("deterministic");
+    if let Some(algorithm) = value("hash") {
+        if !algorithm.eq_ignore_ascii_case("sha256") {
+            fail("Usage", &format!("Ошибка: алгоритм --hash должен быть sha256, получено '{}'", algorithm), exit_code::USAGE);
+        }
+        options.hash = true;
+    }
+    if let Some(alphabet) = value("base64") {
+        options.encode.base64 = parse_base64_alphabet(&alphabet);
+    }
+    if let Some(order) = value("endian") {
+        let endian = parse_endian(&order);
+        options.encode.endian = endian;
+        options.decode.endian = endian;
+    }
+    if let Some(version) = value("format-version") {
+        options.encode.format_version = parse_format_version(&version);
+    }
+    options.stats = flag("stats");
+    options.explain = flag("explain");
+    options.compare_size = flag("compare-size");
+    options.raw = flag("raw");
+    options.decode.recursive = flag("recursive");
+    if let Some(size) = value("max-size") {
+        options.decode.max_size = parse_max_size(&size);
+    }
+    options.encode.sign_key = value("sign").map(String::into_bytes);
+    options.decode.verify_key = value("verify").map(String::into_bytes);
+    options.sets = values("set").iter().map(|assignment| parse_set(assignment)).collect();
+    options.vars = values("var").iter().map(|assignment| parse_var(assignment)).collect();
+    options.strict_vars = flag("strict-vars");
+    options.template = flag("template") || options.strict_vars || !options.vars.is_empty();
+    let pair = |id: &str| match values(id).as_slice() {
+        [first, second] => Some((first.clone(), second.clone())),
+        _ => None,
+    };
+    options.diff = pair("diff");
+    options.merge = pair("merge");
+    if let Some(mode) = value("merge-arrays") {
+        options.merge_arrays = ArrayMerge::parse(&mode).unwrap_or_else(|| {
+            fail("Usage", &format!("Ошибка: режим --merge-arrays должен быть replace или concat, получено '{}'", mode), exit_code::USAGE);
+        });
+    }
+    options.from = value("from").map(|format| parse_data_format(&format, "--from"));
+    if let Some(format) = value("to") {
+        options.to = parse_data_format(&format, "--to");
+    }
+    options.field = value("field");
+    options.stamp = value("stamp");
+    options.nfc = flag("nfc");
+    options.repl = flag("repl");
+    options.quiet = flag("quiet");
+    options.line_ending = if flag("crlf") {
+        Some(LineEnding::Crlf)
+    } else if flag("lf") {
+        Some(LineEnding::Lf)
+    } else {
+        None
+    };
+    if let Some(jobs) = value("jobs") {
+        options.jobs = parse_jobs(&jobs);
+    }
+    options.split = value("split").map(|parts| parse_split(&parts));
+    options.ndjson = flag("ndjson");
+    options.json_array = flag("json-array");
+    if let Some(format) = value("output-format") {
+        options.output_format = parse_batch_format(&format);
+    }
+    options.name_field = value("name-field");
+    options.null = flag("null");
+    options.report = flag("report");
+    options.batch = flag("batch") || options.ndjson || options.null || options.report || value("output-format").is_some();
+    options.verbose = matches.get_count("verbose");
+    options.no_color = flag("no-color");
+    options.direct_input = values("data");
+
+    let help = flag("help");
+    let lang = value("lang").map(|value| {
+        Lang::parse(&value).unwrap_or_else(|| {
+            fail("Usage", &format!("Ошибка: язык справки должен быть ru или en, получено '{}'", value), exit_code::USAGE);
+        })
+    });
+
+    if help {
+        println!("{}", usage(&args[0], lang.unwrap_or_else(Lang::detect)));
+        std::process::exit(exit_code::OK);
+    }
+
+    if options.raw && options.redact {
+        // --raw выводит JSON байт в байт, замаскировать в нём ничего нельзя
+        fail("Usage", "Ошибка: --raw несовместим с --redact", exit_code::USAGE);
+    }
+    if options.raw && options.schema.is_some() {
+        // --raw не разбирает JSON, проверять по схеме нечего
+        fail("Usage", "Ошибка: --raw несовместим с --schema", exit_code::USAGE);
+    }
+
+    if options.ndjson {
+        match options.explicit_mode.as_deref() {
+            None | Some("encode") => options.explicit_mode = Some("encode".to_string()),
+            Some(mode) => {
+                fail("Usage", &format!("Ошибка: --ndjson работает только при кодировании, а не с --{}", mode), exit_code::USAGE);
+            }
+        }
+    }
+
+    if options.split.is_some() {
+        match options.explicit_mode.as_deref() {
+            None | Some("encode") => options.explicit_mode = Some("encode".to_string()),
+            Some(mode) => {
+                fail("Usage", &format!("Ошибка: --split работает только при кодировании, а не с --{}", mode), exit_code::USAGE);
+            }
+        }
+        if options.qr_png.is_some() {
+            fail("Usage", "Ошибка: --split несовместим с --qr-png, используйте --qr", exit_code::USAGE);
+        }
+    }
+
+    if options.explain {
+        match options.explicit_mode.as_deref() {
+            None | Some("decode") => options.explicit_mode = Some("decode".to_string()),
+            Some(mode) => {
+                fail("Usage", &format!("Ошибка: --explain работает только при декодировании, а не с --{}", mode), exit_code::USAGE);
+            }
+        }
+    }
+
+    if options.template {
+        match options.explicit_mode.as_deref() {
+            None | Some("encode") => options.explicit_mode = Some("encode".to_string()),
+            Some(mode) => {
+                fail("Usage", &format!("Ошибка: --template работает только при кодировании, а не с --{}", mode), exit_code::USAGE);
+            }
+        }
+    }
+
+    if options.decode.no_prefix {
+        // Блоб без схемы может не распознаться автодетектом (например, с переносами строк)
+        options.explicit_mode.get_or_insert_with(|| "decode".to_string());
+    }
+
+    options.encode.preserve_order = options.preserve_order;
+    if options.deterministic {
+        // Перекрывает --level, --format, --minify, --preserve-order и т.п.
+        // независимо от порядка флагов
+        let encode = std::mem::take(&mut options.encode);
+        options.encode = EncodeOptions {
+            scheme: encode.scheme,
+            sign_key: encode.sign_key,
+            ..EncodeOptions::deterministic()
+        };
+    }
+    options
+}
+
+/// Выводит QR код закодированного URL в терминал и/или PNG файл
+fn try_write_qr(url: &str, options: &Options) -> Result<(), QrError> {
+    if options.qr {
+        println!("{}", render_qr_unicode(url)?);
+    }
+    if let Some(path) = &options.qr_png {
+        save_qr_png(url, path)?;
+        info!("🖼️  QR код сохранён в {}", path);
+    }
+    Ok(())
+}
+
+/// Выводит QR код или завершает программу с понятным сообщением об ошибке
+fn write_qr(url: &str, options: &Options) {
+    match try_write_qr(url, options) {
+        Ok(()) => {}
+        Err(QrError::TooLarge { length }) => {
+            let text = format!(
+                "❌ Ошибка: URL слишком большой для одного QR кода ({} байт)\n   {}",
+                length, "Уменьшите конфигурацию или повысьте уровень сжатия (--level 9)"
+            );
+            fail("QrTooLarge", &text, exit_code::OUTPUT);
+        }
+        Err(e) => {
+            fail("Output", &format!("❌ Ошибка: не удалось создать QR код ({})", e), exit_code::OUTPUT);
+        }
+    }
+}
+
+/// Считывает VPN URL из QR кода на изображении или завершает программу с ошибкой
+fn read_qr_or_exit(path: &str) -> String {
+    match read_qr_image(path) {
+        Ok(url) => url,
+        Err(QrError::NotFound) => {
+            fail("Input", &format!("❌ Ошибка: на изображении {} не найден QR код", path), exit_code::INPUT);
+        }
+        Err(QrError::NotVpnUrl(text)) => {
+            fail("Input", &format!("❌ Ошибка: QR код не содержит VPN URL (прочитано: '{}')", text), exit_code::INPUT);
+        }
+        Err(e) => {
+            fail("Input", &format!("❌ Ошибка: не удалось прочитать QR код ({})", e), exit_code::INPUT);
+        }
+    }
+}
+
+/// Сравнивает две конфигурации и завершает программу с кодом как у diff(1):
+/// 0 — совпадают, 1 — различаются; ошибка декодирования — код 4
+fn run_diff(first: &str, second: &str, options: &Options) -> ! {
+    use std::io::Write;
+
+    let decode_url = |url: &str| match decode_with_options(url.trim(), &options.decode) {
+        Ok(config) => config,
+        Err(e) => {
+            fail(e.primary_cause().kind(), &format!("❌ Ошибка: {}", describe_decode_error(&e)), exit_code::DECODE);
+        }
+    };
+
+    let mut changes = diff(&decode_url(first), &decode_url(second));
+    if options.redact {
+        changes.iter_mut().for_each(|change| redact_change(change, &options.redact_keys));
+    }
+    if changes.is_empty() {
+        eprintln!("✅ Конфигурации совпадают");
+        std::process::exit(exit_code::OK);
+    }
+    let mut stdout = std::io::stdout().lock();
+    for change in &changes {
+        if let Err(e) = writeln!(stdout, "{}", change).and_then(|()| stdout.flush()) {
+            exit_with_run_error(&e);
+        }
+    }
+    eprintln!("Найдено отличий: {}", changes.len());
+    std::process::exit(exit_code::FAILURE);
+}
+
+/// Маскирует секреты в отличии для --diff --redact. Изменение секретного
+/// поля остаётся видно, но оба значения заменяются на [`MASK`]
+fn redact_change(change: &mut Change, extra_keys: &[String]) {
+    let (path, values) = match change {
+        Change::Added { path, value } | Change::Removed { path, value } => (path, vec![value]),
+        Change::Changed { path, old, new } => (path, vec![old, new]),
+    };
+    let key = path.rsplit('.').next().unwrap_or_default();
+    for value in values {
+        if is_sensitive(key, extra_keys) {
+            *value = Value::String(MASK.to_string());
+        } else {
+            redact(value, extra_keys);
+        }
+    }
+}
+
+/// Читает конфигурации для --merge (JSON, YAML или TOML — по --from или
+/// расширению) и глубоко объединяет их; при ошибке завершает программу
+fn merge_files_or_exit(base: &str, overrides: &str, options: &Options) -> Value {
+    let read = |path: &str| {
+        let format = options
+            .from
+            .or_else(|| DataFormat::from_extension(Path::new(path)))
+            .unwrap_or_default();
+        let text = read_file(path).unwrap_or_else(|e| {
+            fail("Input", &format!("❌ Ошибка: не удалось прочитать {} ({})", path, e), exit_code::INPUT);
+        });
+        let parsed = match format {
+            DataFormat::Json => from_str(strip_json_comments(&text)).map_err(|e| e.to_string()),
+            format => from_format(&text, format).map_err(|e| e.to_string()),
+        };
+        parsed.unwrap_or_else(|e| {
+            fail("Encode", &format!("❌ Ошибка: некорректный {} в {} ({})", format, path, e), exit_code::ENCODE);
+        })
+    };
+
+    let mut merged = read(base);
+    merge(&mut merged, read(overrides), options.merge_arrays);
+    merged
+}
+
+/// Пакетный режим: обрабатывает каждую непустую строку входа независимо
+fn run_batch(input: &str, options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::Write;
+
+    if let Some(mode @ ("validate" | "check" | "repair")) = options.explicit_mode.as_deref() {
+        fail("Usage", &format!("❌ Ошибка: --{} не поддерживается в пакетном режиме", mode), exit_code::USAGE);
+    }
+
+    if options.split.is_some() {
+        fail("Usage", "❌ Ошибка: --split не поддерживается в пакетном режиме", exit_code::USAGE);
+    }
+    // Каждая запись выводится одной строкой JSON: поле и YAML/TOML её бы разорвали
+    if options.field.is_some() || options.to != DataFormat::Json {
+        fail("Usage", "❌ Ошибка: --field и --to не поддерживаются в пакетном режиме", exit_code::USAGE);
+    }
+
+    // С --null записи разделяются NUL байтами, как у `find -print0`
+    let joined;
+    let mut records: Vec<&str> = if options.null {
+        input.split('\0').collect()
+    } else {
+        input.lines().collect()
+    };
+
+    // Части URL из --split собираются в одну запись на месте первой части
+    let prefix = options.decode.scheme.as_deref().unwrap_or(PREFIX);
+    let parts: Vec<usize> = (0..records.len()).filter(|&index| is_part(records[index], prefix)).collect();
+    if let Some(&first) = parts.first() {
+        let urls: Vec<&str> = parts.iter().map(|&index| records[index].trim()).collect();
+        joined = join_parts_or_exit(&urls, prefix);
+        records[first] = &joined;
+        for &index in parts[1..].iter().rev() {
+            records.remove(index);
+        }
+    }
+
+    let schema = load_schema(options);
+    let results = process_batch(&records, options, schema.as_ref())?;
+    let failed = results.iter().filter(|(_, result)| result.is_err()).count();
+
+    let output = format_batch(&results, options.json_array, options.output_format, options.null)?;
+    if options.null && !options.json_array && writes_to_stdout(options) {
+        // Каждая запись завершается NUL, без перевода строки для `xargs -0`
+        let mut stdout = std::io::stdout().lock();
+        write!(stdout, "{}", output)?;
+        stdout.flush()?;
+    } else {
+        write_output(options, &output)?;
+    }
+    // Сводка видна всегда, кроме --quiet без ошибок
+    if failed > 0 || !options.quiet {
+        eprintln!("📦 Обработано: {}, с ошибками: {}", results.len(), failed);
+    }
+    if failed > 0 {
+        ITEMS_FAILED.store(true, Ordering::Relaxed);
+    }
+    if options.report {
+        eprintln!("{}", batch_report(&results));
+    }
+    Ok(())
+}
+
+/// Сводка пакетной обработки для --report: сколько записей декодировано и
+/// закодировано, сколько ошибок каждого вида и размеры распакованного JSON
+/// (`null`, если ничего не декодировано). Пустые записи не учитываются
+fn batch_report(results: &[BatchResult]) -> Value {
+    let mut encoded = 0;
+    let mut sizes = Vec::new();
+    let mut errors = std::collections::BTreeMap::new();
+    for (_, result) in results {
+        match result {
+            Ok(Processed::Encoded { .. }) => encoded += 1,
+            Ok(Processed::Decoded { json_len, .. }) => sizes.push(*json_len),
+            Ok(Processed::Skipped) => {}
+            Err(e) => *errors.entry(e.kind).or_insert(0) += 1,
+        }
+    }
+
+    let failed: usize = errors.values().sum();
+    let decompressed = match (sizes.iter().min(), sizes.iter().max()) {
+        (Some(min), Some(max)) => serde_json::json!({
+            "min": min,
+            "max": max,
+            "avg": sizes.iter().sum::<usize>() as f64 / sizes.len() as f64,
+        }),
+        _ => Value::Null,
+    };
+    serde_json::json!({
+        "total": encoded + sizes.len() + failed,
+        "decoded": sizes.len(),
+        "encoded": encoded,
+        "failed": failed,
+        "errors": errors,
+        "decompressed_size": decompressed,
+    })
+}
+
+/// Интерактивный режим: читает stdin построчно до EOF, определяет режим
+/// каждой строки и сразу печатает результат. Ошибка в строке выводится в
+/// stderr и не прерывает работу; приглашение тоже печатается в stderr,
+/// чтобы stdout содержал только результаты
+fn run_repl(options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::{BufRead, Write};
+
+    if let Some(mode @ ("validate" | "check" | "repair")) = options.explicit_mode.as_deref() {
+        fail("Usage", &format!("❌ Ошибка: --{} не поддерживается в режиме --repl", mode), exit_code::USAGE);
+    }
+
+    let schema = load_schema(options);
+    let mut encoder = EncodeContext::new(options.encode.clone());
+    let mut stdin = std::io::stdin().lock();
+    let mut stdout = std::io::stdout();
+    let mut line = String::new();
+    loop {
+        eprint!("> ");
+        std::io::stderr().flush()?;
+        line.clear();
+        if stdin.read_line(&mut line)? == 0 {
+            // Перевод строки после приглашения, чтобы не сбить терминал
+            eprintln!();
+            return Ok(());
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        match process_item(&line, options.explicit_mode.as_deref(), options, schema.as_ref(), &mut encoder) {
+            Ok(Processed::Encoded { url, .. }) => writeln!(stdout, "{}", url)?,
+            Ok(Processed::Decoded { value, .. }) => match render_decoded(&value, options) {
+                Ok(output) => writeln!(stdout, "{}", output)?,
+                Err(e) => eprintln!("❌ Ошибка: {}", e),
+            },
+            Ok(Processed::Skipped) => {}
+            Err(e) => eprintln!("❌ Ошибка: {}", e),
+        }
+        stdout.flush()?;
+    }
+}
+
+/// Обрабатывает записи пакета параллельно в пуле из `options.jobs` потоков.
+/// Результаты идут в порядке входа и помечены номером записи (с 1)
+fn process_batch(
+    records: &[&str],
+    options: &Options,
+    schema: Option<&Value>,
+) -> Result<Vec<BatchResult>, rayon::ThreadPoolBuildError> {
+    use rayon::prelude::*;
+
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(options.jobs).build()?;
+    let progress = batch_progress(records.len(), options.quiet);
+    let results = pool.install(|| {
+        records
+            .par_iter()
+            .enumerate()
+            // Каждый поток переиспользует свой контекст кодирования
+            .map_init(
+                || EncodeContext::new(options.encode.clone()),
+                |encoder, (index, line)| {
+                    progress.inc(1);
+                    // В NDJSON строка входа соответствует строке выхода, даже пустая
+                    if line.trim().is_empty() {
+                        return options.ndjson.then_some((index + 1, Ok(Processed::Skipped)));
+                    }
+                    let mode = options.explicit_mode.as_deref();
+                    Some((index + 1, process_item(line, mode, options, schema, encoder)))
+                },
+            )
+            .flatten()
+            .collect()
+    });
+    progress.finish_and_clear();
+    Ok(results)
+}
+
+/// Индикатор прогресса пакетного режима в stderr: обработано/всего и
+/// оставшееся время. Скрыт с `quiet`, а также когда stderr не терминал —
+/// это indicatif проверяет сам
+fn batch_progress(total: usize, quiet: bool) -> indicatif::ProgressBar {
+    if quiet {
+        return indicatif::ProgressBar::hidden();
+    }
+    let style = indicatif::ProgressStyle::with_template("{bar:40} {pos}/{len} ETA {eta}")
+        .expect("valid progress template");
+    indicatif::ProgressBar::new(total as u64).with_style(style)
+}
+
+/// Кодирует или декодирует один элемент; режим `None` означает автодетект.
+/// Кодирование идёт через `encoder`, созданный с `options.encode`
+fn process_item(
+    input: &str,
+    mode: Option<&str>,
+    options: &Options,
+    schema: Option<&Value>,
+    encoder: &mut EncodeContext,
+) -> Result<Processed, ItemError> {
+    let mode = match mode {
+        Some(mode) => mode,
+        None => detect_mode(input, options)
+            .ok_or_else(|| match explain_unknown_input(input) {
+                Some(reason) => format!("не удалось определить тип входных данных: {}", reason),
+                None => "не удалось определить тип входных данных".to_string(),
+            })
+            .map_err(|message| ItemError::new("UnknownInput", message))?,
+    };
+
+    if mode == "encode" {
+        let text = strip_json_comments(input);
+        let mut config: Value = from_str(text)
+            .map_err(|e| ItemError::new("InvalidJson", describe_json_error(input, text, &e, false)))?;
+        if options.nfc {
+            normalize_nfc(&mut config);
+        }
+        if let Some(key) = &options.stamp {
+            stamp(&mut config, key);
+        }
+        let url = encoder.encode(&config).map_err(|e| ItemError::new(e.kind(), e.to_string()))?;
+        let name = match options.output_format {
+            BatchFormat::Plain => None,
+            _ => config_name(&config, options.name_field.as_deref()),
+        };
+        Ok(Processed::Encoded { url, name })
+    } else {
+        let input = add_missing_scheme(input, options).unwrap_or_else(|| input.to_string());
+        let (mut decoded, stats) = decode_with_stats(input.trim(), &options.decode)
+            .map_err(|e| ItemError::new(e.primary_cause().kind(), describe_decode_error(&e)))?;
+        if let Some(schema) = schema {
+            // Некорректная схема отсеяна в load_schema
+            let issues = validate_schema(&decoded, schema).unwrap_or_default();
+            if !issues.is_empty() {
+                let issues: Vec<String> = issues.iter().map(ToString::to_string).collect();
+                return Err(ItemError::new("Schema", format!("не соответствует схеме: {}", issues.join("; "))));
+            }
+        }
+        if options.decode_nested {
+            expand_nested(&mut decoded, MAX_NESTED_DEPTH);
+        }
+        if !options.preserve_order {
+            sort_keys(&mut decoded);
+        }
+        if options.redact {
+            redact(&mut decoded, &options.redact_keys);
+        }
+        Ok(Processed::Decoded { value: decoded, json_len: stats.json_len })
+    }
+}
+
+/// Определяет режим по формату входа из --from или расширению файла:
+/// JSON, YAML и TOML кодируются, `.txt` и `.vpn` считаются VPN URL.
+/// `None` — подсказки нет, режим определяется по содержимому
+fn hinted_mode(input_format: Option<DataFormat>, input_file: Option<&str>) -> Option<&'static str> {
+    if input_format.is_some() {
+        return Some("encode");
+    }
+    let extension = Path::new(input_file?).extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "txt" | "vpn" => Some("decode"),
+        _ => None,
+    }
+}
+
+/// Определяет режим по содержимому входа с учётом схемы из --scheme
+fn detect_mode(input: &str, options: &Options) -> Option<&'static str> {
+    if let Some(scheme) = &options.decode.scheme
+        && input.trim().starts_with(scheme.as_str())
+    {
+        return Some("decode");
+    }
+    match detect_input_type(input) {
+        InputType::VpnUrl | InputType::Base64Blob => Some("decode"),
+        InputType::JsonObject | InputType::JsonArray | InputType::JsonScalar => Some("encode"),
+        InputType::Unknown => None,
+    }
+}
+
+/// Подсказка, если явный режим -e/-d противоречит содержимому входа:
+/// JSON при декодировании или VPN URL при кодировании
+fn mode_mismatch_hint(mode: &str, input: &str, options: &Options) -> Option<&'static str> {
+    match (mode, detect_mode(input, options)?) {
+        ("decode", "encode") => Some("Похоже на JSON — может быть, нужен -e (кодирование)?"),
+        ("encode", "decode") => Some("Похоже на VPN URL — может быть, нужен -d (декодирование)?"),
+        _ => None,
+    }
+}
+
+/// Добавляет схему к Base64 блобу, скопированному без `vpn://`
+fn add_missing_scheme(input: &str, options: &Options) -> Option<String> {
+    let trimmed = input.trim();
+    if options.decode.no_prefix || detect_input_type(trimmed) != InputType::Base64Blob {
+        return None;
+    }
+    let scheme = options.decode.scheme.as_deref().unwrap_or(PREFIX);
+    Some(format!("{}{}", scheme, trimmed))
+}
+
+/// Имя конфигурации для --output-format: строка из поля `field` или,
+/// без него, из `description`, затем `name`. Пустые строки не считаются
+/// именем, табуляции и переводы строк заменяются пробелами
+fn config_name(config: &Value, field: Option<&str>) -> Option<String> {
+    let name = match field {
+        Some(path) => match select(config, path).ok()? {
+            Value::String(text) => text.clone(),
+            Value::Null => return None,
+            other => other.to_string(),
+        },
+        None => ["description", "name"]
+            .iter()
+            .filter_map(|key| config.get(key)?.as_str())
+            .find(|name| !name.trim().is_empty())?
+            .to_string(),
+    };
+    let name = name.replace(['\t', '\r', '\n'], " ");
+    (!name.trim().is_empty()).then_some(name)
+}
+
+/// Формирует вывод пакетного режима: по строке на элемент или JSON массив.
+/// Закодированные записи выводятся в виде из `format`. С `null` записи не
+/// разделяются переводами строк, а завершаются NUL байтом
+fn format_batch(
+    results: &[BatchResult],
+    json_array: bool,
+    format: BatchFormat,
+    null: bool,
+) -> Result<String, serde_json::Error> {
+    if json_array || format == BatchFormat::Json {
+        let items: Vec<Value> = results
+            .iter()
+            .map(|(line, result)| match result {
+                Ok(Processed::Encoded { url, name }) if format == BatchFormat::Json => {
+                    serde_json::json!({ "name": name, "url": url })
+                }
+                Ok(Processed::Encoded { url, .. }) => Value::String(url.clone()),
+                Ok(Processed::Decoded { value, .. }) => value.clone(),
+                Ok(Processed::Skipped) => Value::Null,
+                Err(e) => serde_json::json!({ "line": line, "error": e.message }),
+            })
+            .collect();
+        return to_string_pretty(&items);
+    }
+
+    let label = if null { "record" } else { "line" };
+    let mut lines = Vec::new();
+    for (line, result) in results {
+        lines.push(match result {
+            Ok(Processed::Encoded { url, name: Some(name) }) if format == BatchFormat::UrlList => {
+                format!("{}\t{}", name, url)
+            }
+            Ok(Processed::Encoded { url, .. }) => url.clone(),
+            Ok(Processed::Decoded { value, .. }) => to_string(value)?,
+            Ok(Processed::Skipped) => String::new(),
+            Err(e) => format!("error: {} {}: {}", label, line, e),
+        });
+    }
+    if null {
+        return Ok(lines.iter().map(|line| format!("{}\0", line)).collect());
+    }
+    Ok(lines.join("\n"))
+}
+
+/// Разбирает вид вывода пакетного режима для --output-format
+fn parse_batch_format(value: &str) -> BatchFormat {
+    match value {
+        "plain" => BatchFormat::Plain,
+        "url-list" => BatchFormat::UrlList,
+        "json" => BatchFormat::Json,
+        _ => {
+            fail("Usage", &format!("Ошибка: --output-format должен быть plain, url-list или json, получено '{}'", value), exit_code::USAGE);
+        }
+    }
+}
+
+/// Разбирает уровень сжатия zlib, допустимы значения 0–9
+fn parse_level(value: &str) -> u32 {
+    match value.parse::<u32>() {
+        Ok(level) if level <= 9 => level,
+        _ => {
+            fail("Usage", &format!("Ошибка: уровень сжатия должен быть числом от 0 до 9, получено '{}'", value), exit_code::USAGE);
+        }
+    }
+}
+
+/// Разбирает ширину строки для --wrap
+fn parse_wrap(value: &str) -> usize {
+    match value.parse::<usize>() {
+        Ok(width) if width > 0 => width,
+        _ => {
+            fail("Usage", &format!("Ошибка: ширина строки должна быть положительным числом, получено '{}'", value), exit_code::USAGE);
+        }
+    }
+}
+
+/// Разбирает ширину отступа для --indent, допустимо 0–16 пробелов
+fn parse_indent(value: &str) -> usize {
+    match value.parse::<usize>() {
+        Ok(width) if width <= 16 => width,
+        _ => {
+            fail("Usage", &format!("Ошибка: --indent должен быть числом от 0 до 16, получено '{}'", value), exit_code::USAGE);
+        }
+    }
+}
+
+/// Разбирает число потоков пакетного режима
+fn parse_jobs(value: &str) -> usize {
+    match value.parse::<usize>() {
+        Ok(jobs) if jobs > 0 => jobs,
+        _ => {
+            fail("Usage", &format!("Ошибка: --jobs должен быть положительным числом, получено '{}'", value), exit_code::USAGE);
+        }
+    }
+}
+
+/// Разбирает количество частей для --split
+fn parse_split(value: &str) -> usize {
+    match value.parse::<usize>() {
+        Ok(parts) if (2..=MAX_PARTS).contains(&parts) => parts,
+        _ => {
+            let text = format!("Ошибка: --split должен быть числом от 2 до {}, получено '{}'", MAX_PARTS, value);
+            fail("Usage", &text, exit_code::USAGE);
+        }
+    }
+}
+
+/// Разбирает лимит распакованного размера в байтах
+fn parse_max_size(value: &str) -> usize {
+    match value.parse::<usize>() {
+        Ok(size) if size > 0 => size,
+        _ => {
+            fail("Usage", &format!("Ошибка: --max-size должен быть положительным числом байт, получено '{}'", value), exit_code::USAGE);
+        }
+    }
+}
+
+/// Декодирует VPN URL или завершает программу с понятным сообщением об ошибке
+fn decode_or_exit(input: &str, options: &DecodeOptions) -> (Value, DecodeStats) {
+    // Одна часть из --split не декодируется: сообщаем, каких частей не хватает
+    let prefix = options.scheme.as_deref().unwrap_or(PREFIX);
+    if is_part(input, prefix) {
+        join_parts_or_exit(&[input.trim()], prefix);
+    }
+    for (key, value) in url_params(input.trim()) {
+        info!("🏷️  Параметр URL: {} = {}", key, value);
+    }
+    match decode_with_stats(input.trim(), options) {
+        Ok(decoded) => {
+            if decoded.1.layers > 1 {
+                info!("🔁 URL был закодирован повторно, снято слоёв: {}", decoded.1.layers);
+            }
+            decoded
+        }
+        Err(e) => exit_with_decode_error(&e),
+    }
+}
+
+/// Сколько байт данных после заголовка показывать в дампе --explain
+const EXPLAIN_DUMP_LIMIT: usize = 64;
+
+/// Выводит в stderr разбор слоёв URL (--explain). Повреждённый заголовок
+/// тоже разбирается; ошибку о нём затем сообщит обычное декодирование
+fn explain_or_exit(input: &str, options: &DecodeOptions) {
+    match explain_url(input, options) {
+        Ok(explanation) => eprintln!("{}", describe_explanation(&explanation)),
+        Err(e) => exit_with_decode_error(&e),
+    }
+}
+
+/// Аннотированный дамп полезной нагрузки: смещение, байты и их смысл
+fn describe_explanation(explanation: &Explanation) -> String {
+    let payload = &explanation.payload;
+    let mut lines = vec![format!(
+        "🔍 Base64 ({:?}): {} символов → {} байт",
+        explanation.alphabet,
+        explanation.encoded_len,
+        payload.len()
+    )];
+    if explanation.signed {
+        lines.push("   Подпись HMAC снята до разбора".to_string());
+    }
+
+    let mut offset = explanation.header_offset();
+    if explanation.format == Format::V2 {
+        lines.push(hex_line(0, &payload[..offset], "метка формата V2"));
+    }
+    if let Some(declared) = explanation.declared {
+        let note = format!("заголовок: длина JSON {} байт", declared);
+        lines.push(hex_line(offset, &payload[offset..offset + 4], &note));
+        offset += 4;
+    }
+
+    let body = &payload[offset..];
+    let shown = &body[..body.len().min(EXPLAIN_DUMP_LIMIT)];
+    for (index, chunk) in shown.chunks(16).enumerate() {
+        let note = match index {
+            0 if explanation.format == Format::Plain => format!("JSON без заголовка: {} байт", body.len()),
+            0 => format!("данные ({:?}): {} байт", explanation.compression, explanation.compressed_len),
+            _ => String::new(),
+        };
+        lines.push(hex_line(offset + index * 16, chunk, &note));
+    }
+    if body.len() > shown.len() {
+        lines.push(format!("   … ещё {} байт", body.len() - shown.len()));
+    }
+
+    lines.push(format!("   После распаковки: {} байт JSON", explanation.json_len));
+    lines.push(match (explanation.integrity, explanation.declared) {
+        (Some(true), _) => "   Проверка целостности: ✅ длина совпадает с заголовком".to_string(),
+        (Some(false), Some(declared)) => format!(
+            "   Проверка целостности: ❌ в заголовке {} байт, распаковано {}",
+            declared, explanation.json_len
+        ),
+        _ => "   Проверка целостности: не выполняется, заголовка длины нет".to_string(),
+    });
+    lines.join("\n")
 }
 
-/// Декодирует VPN URL обратно в JSON конфигурацию
-pub fn decode(vpn_url: &str) -> Result<Value, Box<dyn std::error::Error>> {
-    // Удаление префикса
-    let encoded_data = vpn_url.strip_prefix(PREFIX)
-        .ok_or("Invalid VPN URL: missing prefix")?;
-    
-    // Декодирование Base64
-    let decoded = decode_base64(encoded_data)?;
-    
-    // Попытка декодирования с заголовком и сжатием
-    match try_decode_compressed(&decoded) {
-        Ok(json) => Ok(json),
-        Err(_) => {
-            // Обратная совместимость: попытка декодирования как чистый Base64 JSON
-            try_decode_plain(&decoded)
+/// Строка шестнадцатеричного дампа: смещение, до 16 байт и пояснение
+fn hex_line(offset: usize, bytes: &[u8], note: &str) -> String {
+    let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    let line = format!("   {:08x}  {:<47}  {}", offset, hex.join(" "), note);
+    line.trim_end().to_string()
+}
+
+/// Полностью декодирует URL (заголовок, распаковка, длина, JSON), отбрасывая результат
+fn check_url(input: &str, options: &DecodeOptions) -> Result<(), DecodeError> {
+    decode_with_stats(input.trim(), options).map(|_| ())
+}
+
+/// Читает схему из --schema; если она не читается или некорректна,
+/// завершает программу
+fn load_schema(options: &Options) -> Option<Value> {
+    let path = options.schema.as_ref()?;
+    let schema = match read_file(path).map(|text| from_str::<Value>(&text)) {
+        Ok(Ok(schema)) => schema,
+        Ok(Err(e)) => {
+            fail("Input", &format!("❌ Ошибка: схема {} не является корректным JSON ({})", path, e), exit_code::INPUT);
+        }
+        Err(e) => {
+            fail("Input", &format!("❌ Ошибка: не удалось прочитать схему {} ({})", path, e), exit_code::INPUT);
         }
+    };
+    // Некорректная схема — ошибка аргументов, а не нарушение в конфигурации
+    if let Err(e) = validate_schema(&Value::Null, &schema) {
+        fail("Usage", &format!("❌ Ошибка: {}", e), exit_code::USAGE);
     }
+    Some(schema)
 }
 
-// === Helper функции ===
+/// Проверяет конфигурацию по схеме из --schema; при нарушениях завершает программу
+fn check_schema(config: &Value, options: &Options) {
+    let (Some(path), Some(schema)) = (&options.schema, load_schema(options)) else {
+        return;
+    };
+    // Некорректная схема отсеяна в load_schema
+    let issues = validate_schema(config, &schema).unwrap_or_default();
+    if issues.is_empty() {
+        eprintln!("✅ Конфигурация соответствует схеме {}", path);
+    } else {
+        let text = format!("❌ Конфигурация не соответствует схеме {}: нарушений {}", path, issues.len());
+        let text = issues.iter().fold(text, |text, issue| format!("{}\n   - {}", text, issue));
+        fail("Schema", &text, exit_code::VALIDATION);
+    }
+}
 
-/// Сжимает данные используя zlib
-fn compress_data(data: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
-    encoder.write_all(data)?;
-    Ok(encoder.finish()?)
+/// Кодирует конфигурацию или завершает программу с кодом ошибки кодирования
+fn encode_or_exit(config: &Value, options: &EncodeOptions) -> (String, EncodeStats) {
+    match encode_with_stats(config, options) {
+        Ok(encoded) => encoded,
+        Err(e) => {
+            fail(e.kind(), &format!("❌ Ошибка кодирования: {}", e), exit_code::ENCODE);
+        }
+    }
 }
 
-/// Распаковывает данные используя zlib
-fn decompress_data(data: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    let mut decoder = ZlibDecoder::new(data);
-    let mut decompressed = Vec::new();
-    decoder.read_to_end(&mut decompressed)?;
-    Ok(decompressed)
+/// Выводить ли ошибки одной строкой JSON (--json-errors). Флаг глобальный,
+/// потому что ошибки сообщаются из любого места, в том числе до разбора
+/// остальных аргументов
+static JSON_ERRORS: AtomicBool = AtomicBool::new(false);
+
+/// Была ли в пакетном режиме хотя бы одна запись с ошибкой; код выхода
+/// выставляется после всех заданий -i
+static ITEMS_FAILED: AtomicBool = AtomicBool::new(false);
+
+/// Сообщает об ошибке и завершает программу с кодом `code`. С --json-errors
+/// вместо текста выводится объект `{"error": ..., "kind": ..., "exit": ...}`,
+/// где `kind` — имя варианта ошибки (`IntegrityMismatch`, `Usage`, ...)
+pub(crate) fn fail(kind: &str, text: &str, code: i32) -> ! {
+    if JSON_ERRORS.load(Ordering::Relaxed) {
+        eprintln!("{}", json_error(kind, text, code));
+    } else {
+        eprintln!("{}", text);
+    }
+    std::process::exit(code);
 }
 
-/// Создает 4-байтовый заголовок с длиной данных (Big Endian)
-fn create_header(length: u32) -> [u8; 4] {
-    length.to_be_bytes()
+/// JSON объект ошибки: текст без значков и префикса «Ошибка:», строки
+/// подсказок склеены через пробел
+fn json_error(kind: &str, text: &str, code: i32) -> Value {
+    let message = text
+        .lines()
+        .map(|line| {
+            let line = line.trim().trim_start_matches("❌").trim_start();
+            line.strip_prefix("Ошибка: ").unwrap_or(line)
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    serde_json::json!({"error": message, "kind": kind, "exit": code})
 }
 
-/// Считывает длину из 4-байтового заголовка (Big Endian)
-fn read_header(header: &[u8]) -> u32 {
-    u32::from_be_bytes([header[0], header[1], header[2], header[3]])
+/// Делит закодированный URL на части или завершает программу
+fn split_or_exit(url: &str, parts: usize, options: &Options) -> Vec<String> {
+    let prefix = options.encode.scheme.as_deref().unwrap_or(PREFIX);
+    split_url(url, parts, prefix).unwrap_or_else(|e| {
+        fail("Split", &format!("❌ Ошибка: не удалось разделить URL ({})", e), exit_code::ENCODE);
+    })
 }
 
-/// Кодирует данные в Base64 URL-safe без padding
-fn encode_base64(data: &[u8]) -> String {
-    URL_SAFE_NO_PAD.encode(data)
+/// Собирает URL из частей или завершает программу
+fn join_parts_or_exit(urls: &[&str], prefix: &str) -> String {
+    join_parts(urls, prefix).unwrap_or_else(|e| {
+        fail("Split", &format!("❌ Ошибка: не удалось собрать URL из частей ({})", e), exit_code::INPUT);
+    })
 }
 
-/// Декодирует Base64 URL-safe (автоматически обрабатывает отсутствие padding)
-fn decode_base64(data: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    Ok(URL_SAFE_NO_PAD.decode(data)?)
+/// Сообщает об ошибке декодирования и завершает программу
+fn exit_with_decode_error(error: &DecodeError) -> ! {
+    fail(error.primary_cause().kind(), &format!("❌ Ошибка: {}", describe_decode_error(error)), exit_code::DECODE);
 }
 
-/// Пытается декодировать данные с заголовком и сжатием
-fn try_decode_compressed(data: &[u8]) -> Result<Value, Box<dyn std::error::Error>> {
-    if data.len() < 4 {
-        return Err("Data too short for header".into());
-    }
-    
-    // Считываем ожидаемую длину из заголовка
-    let expected_len = read_header(&data[..4]) as usize;
-    
-    // Распаковываем оставшиеся данные
-    let decompressed = decompress_data(&data[4..])?;
-    
-    // Проверка целостности
-    if decompressed.len() != expected_len {
-        return Err(format!(
-            "Data integrity check failed: expected {} bytes, got {}",
-            expected_len,
-            decompressed.len()
-        ).into());
+/// Отступ JSON вывода из --compact / --indent / --indent-tab;
+/// `None` — в одну строку
+fn json_indent(options: &Options) -> Option<&str> {
+    if options.compact {
+        None
+    } else {
+        Some(options.indent.as_deref().unwrap_or("  "))
     }
-    
-    // Десериализация JSON
-    let json_string = String::from_utf8(decompressed)?;
-    Ok(from_str(&json_string)?)
 }
 
-/// Пытается декодировать данные как чистый Base64 JSON (без сжатия)
-fn try_decode_plain(data: &[u8]) -> Result<Value, Box<dyn std::error::Error>> {
-    let json_string = String::from_utf8(data.to_vec())?;
-    Ok(from_str(&json_string)?)
+/// Сериализует декодированную конфигурацию: с отступом `indent` или в одну строку
+fn format_decoded(value: &Value, indent: Option<&str>) -> Result<String, serde_json::Error> {
+    let Some(indent) = indent else {
+        return to_string(value);
+    };
+    let mut output = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+    let mut serializer = serde_json::Serializer::with_formatter(&mut output, formatter);
+    serde::Serialize::serialize(value, &mut serializer)?;
+    Ok(String::from_utf8(output).expect("serde_json produces UTF-8"))
+}
+
+/// Краткая сводка конфигурации для --summary: контейнеры, контейнер по
+/// умолчанию и адрес сервера. Отсутствующие поля отмечаются, а не считаются ошибкой
+fn format_summary(config: &Value) -> String {
+    let text = |key: &str| match config.get(key) {
+        Some(Value::String(value)) => value.clone(),
+        Some(other) => other.to_string(),
+        None => "(не указан)".to_string(),
+    };
+
+    let containers = match config.get("containers") {
+        Some(Value::Array(list)) => {
+            let names: Vec<&str> = list
+                .iter()
+                .map(|c| c.get("container").and_then(Value::as_str).unwrap_or("(без типа)"))
+                .collect();
+            if names.is_empty() {
+                "0".to_string()
+            } else {
+                format!("{} ({})", names.len(), names.join(", "))
+            }
+        }
+        Some(_) => "(не массив)".to_string(),
+        None => "(не указаны)".to_string(),
+    };
+
+    [
+        format!("Сервер: {}", text("hostName")),
+        format!("Контейнеры: {}", containers),
+        format!("Контейнер по умолчанию: {}", text("defaultContainer")),
+    ]
+    .join("\n")
 }
 
-/// Автоматически определяет тип входных данных
-fn detect_input_type(input: &str) -> InputType {
-    let trimmed = input.trim();
-    
-    // Проверка на VPN URL
-    if trimmed.starts_with(PREFIX) {
-        return InputType::VpnUrl;
+/// Кодирует конфигурацию zlib и «сырым» deflate с уровнями 1, 6 и 9 и
+/// возвращает таблицу длин URL для --compare-size. Остальные параметры
+/// кодирования берутся из `options`
+fn compare_sizes(config: &Value, options: &EncodeOptions) -> Result<String, EncodeError> {
+    let mut lines = vec![format!("{:<10} {:>7} {:>8} {:>6}", "сжатие", "уровень", "данные", "URL")];
+    for (name, compression) in [("zlib", Compression::Zlib), ("deflate", Compression::Deflate)] {
+        for level in [1, 6, 9] {
+            let options = EncodeOptions { compression, level: Some(level), ..options.clone() };
+            let (_, stats) = encode_with_stats(config, &options)?;
+            lines.push(format!(
+                "{:<10} {:>7} {:>8} {:>6}",
+                name, level, stats.compressed_len, stats.url_len
+            ));
+        }
     }
-    
-    // Проверка на JSON
-    if (trimmed.starts_with('{') && trimmed.ends_with('}')) 
-        || (trimmed.starts_with('[') && trimmed.ends_with(']')) {
-        return InputType::Json;
+    Ok(lines.join("\n"))
+}
+
+/// Разбирает формат сжатия для --format
+fn parse_compression(value: &str) -> Compression {
+    match value {
+        "zlib" => Compression::Zlib,
+        "gzip" => Compression::Gzip,
+        "deflate" => Compression::Deflate,
+        _ => {
+            fail("Usage", &format!("Ошибка: формат сжатия должен быть zlib, gzip или deflate, получено '{}'", value), exit_code::USAGE);
+        }
     }
-    
-    // Попытка распарсить как JSON
-    if from_str::<Value>(trimmed).is_ok() {
-        return InputType::Json;
+}
+
+/// Разбирает текстовый формат конфигурации для `flag`
+fn parse_data_format(value: &str, flag: &str) -> DataFormat {
+    DataFormat::parse(value).unwrap_or_else(|| {
+        fail("Usage", &format!("Ошибка: формат для {} должен быть json, yaml или toml, получено '{}'", flag, value), exit_code::USAGE);
+    })
+}
+
+/// Разбирает порядок байтов заголовка для --endian
+fn parse_endian(value: &str) -> Endian {
+    match value {
+        "big" | "be" => Endian::Big,
+        "little" | "le" => Endian::Little,
+        _ => {
+            fail("Usage", &format!("Ошибка: порядок байтов должен быть big или little, получено '{}'", value), exit_code::USAGE);
+        }
     }
-    
-    InputType::Unknown
 }
 
-#[derive(Debug, PartialEq)]
-enum InputType {
-    VpnUrl,
-    Json,
-    Unknown,
+/// Разбирает алфавит Base64 для --base64
+fn parse_base64_alphabet(value: &str) -> Base64Alphabet {
+    match value {
+        "url-safe" | "urlsafe" => Base64Alphabet::UrlSafe,
+        "standard" => Base64Alphabet::Standard,
+        _ => {
+            fail("Usage", &format!("Ошибка: алфавит Base64 должен быть url-safe или standard, получено '{}'", value), exit_code::USAGE);
+        }
+    }
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args: Vec<String> = std::env::args().collect();
+/// Разбирает версию формата для --format-version
+fn parse_format_version(value: &str) -> FormatVersion {
+    match value {
+        "1" => FormatVersion::V1,
+        "2" => FormatVersion::V2,
+        _ => {
+            fail("Usage", &format!("Ошибка: версия формата должна быть 1 или 2, получено '{}'", value), exit_code::USAGE);
+        }
+    }
+}
 
-    let mut explicit_mode: Option<String> = None;
-    let mut input_file: Option<String> = None;
-    let mut output_file: Option<String> = None;
-    let mut direct_input: Vec<String> = Vec::new();
-    
-    let mut i = 1;
-    while i < args.len() {
-        match args[i].as_str() {
-            "-e" | "--encode" => explicit_mode = Some("encode".to_string()),
-            "-d" | "--decode" => explicit_mode = Some("decode".to_string()),
-            "-i" | "--input" => {
-                if i + 1 < args.len() {
-                    input_file = Some(args[i + 1].clone());
-                    i += 1;
-                } else {
-                    eprintln!("Ошибка: не указан файл для -i");
-                    std::process::exit(1);
-                }
-            }
-            "-o" | "--output" => {
-                if i + 1 < args.len() {
-                    output_file = Some(args[i + 1].clone());
-                    i += 1;
-                } else {
-                    eprintln!("Ошибка: не указан файл для -o");
-                    std::process::exit(1);
-                }
-            }
-            "-h" | "--help" => {
-                print_usage(&args[0]);
-                std::process::exit(0);
-            }
-            _ => direct_input.push(args[i].clone()),
+/// Разбирает формат экспорта для --export
+fn parse_export_format(value: &str) -> ExportFormat {
+    match value {
+        "wireguard" | "wg" => ExportFormat::WireGuard,
+        "openvpn" | "ovpn" => ExportFormat::OpenVpn,
+        "shadowsocks" | "ss" => ExportFormat::Shadowsocks,
+        _ => {
+            fail("Usage", &format!("Ошибка: неизвестный формат экспорта '{}' (доступны: wireguard, openvpn, shadowsocks)", value), exit_code::USAGE);
         }
-        i += 1;
     }
+}
 
-    // Получаем входные данные
-    let input = get_input(input_file, direct_input)?;
-    
-    // Определяем режим работы
-    let mode = if let Some(explicit) = explicit_mode {
-        explicit
+/// Нормализует схему URL: `amnezia` и `amnezia://` означают одно и то же
+fn parse_scheme(value: &str) -> String {
+    if value.is_empty() {
+        fail("Usage", "Ошибка: схема для --scheme не может быть пустой", exit_code::USAGE);
+    }
+    if value.ends_with("://") {
+        value.to_string()
     } else {
-        // Автодетект
-        match detect_input_type(&input) {
-            InputType::VpnUrl => {
-                eprintln!("🔍 Автодетект: обнаружен VPN URL, выполняется декодирование");
-                "decode".to_string()
-            }
-            InputType::Json => {
-                eprintln!("🔍 Автодетект: обнаружен JSON, выполняется кодирование");
-                "encode".to_string()
-            }
-            InputType::Unknown => {
-                eprintln!("❌ Ошибка: не удалось определить тип входных данных");
-                eprintln!("   Используйте -e для кодирования или -d для декодирования");
-                std::process::exit(1);
-            }
-        }
+        format!("{}://", value.trim_end_matches(':'))
+    }
+}
+
+/// Разбирает выражение `PATH=VALUE` для --set. Значение читается как JSON,
+/// а если это не JSON — как обычная строка
+fn parse_set(assignment: &str) -> (String, Value) {
+    let Some((path, value)) = assignment.split_once('=') else {
+        fail("Usage", &format!("Ошибка: --set ожидает PATH=VALUE, получено '{}'", assignment), exit_code::USAGE);
     };
+    let value = from_str(value).unwrap_or_else(|_| Value::String(value.to_string()));
+    (path.to_string(), value)
+}
 
-    // Выполняем операцию
-    match mode.as_str() {
-        "encode" => {
-            let config: Value = from_str(&input)?;
-            let encoded = encode(&config)?;
-            write_output(output_file, &encoded)?;
+/// Сколько символов строки показывать вокруг ошибки в JSON с каждой стороны
+const SNIPPET_RADIUS: usize = 40;
+
+/// Описывает ошибку разбора JSON со строкой и столбцом во входе `input`.
+/// `text` — часть `input`, переданная в serde_json (после
+/// [`strip_json_comments`]), поэтому позиция пересчитывается на исходный
+/// вход. С `snippet` добавляется фрагмент строки с указателем на ошибку
+fn describe_json_error(input: &str, text: &str, error: &serde_json::Error, snippet: bool) -> String {
+    // Сообщение serde_json без хвоста " at line L column C"
+    let message = error.to_string();
+    let suffix = format!(" at line {} column {}", error.line(), error.column());
+    let message = message.strip_suffix(&suffix).unwrap_or(&message);
+    if error.line() == 0 {
+        return format!("некорректный JSON ({})", message);
+    }
+
+    // Переводим позицию в `text` в позицию в `input`
+    let offset = text.as_ptr() as usize - input.as_ptr() as usize;
+    let before = &input[..offset];
+    let line = before.matches('\n').count() + error.line();
+    let mut byte_column = error.column();
+    if error.line() == 1 {
+        byte_column += offset - before.rfind('\n').map_or(0, |index| index + 1);
+    }
+
+    let line_text = input.lines().nth(line - 1).unwrap_or("");
+    let chars: Vec<char> = line_text.chars().collect();
+    // Столбец serde_json — позиция в байтах с 1, показываем в символах
+    let caret = line_text
+        .get(..byte_column.saturating_sub(1))
+        .map_or(chars.len(), |prefix| prefix.chars().count());
+    let mut description = format!("некорректный JSON в строке {}, столбец {}: {}", line, caret + 1, message);
+    if !snippet {
+        return description;
+    }
+
+    // Длинные строки (например, минифицированный JSON) обрезаются вокруг ошибки
+    let start = caret.saturating_sub(SNIPPET_RADIUS);
+    let end = (caret + SNIPPET_RADIUS).min(chars.len());
+    let ellipsis = if start > 0 { "…" } else { "" };
+    let fragment: String = chars[start..end].iter().collect();
+    // Табуляции сохраняются, чтобы указатель совпал с символом
+    let padding: String = ellipsis
+        .chars()
+        .chain(chars[start..caret.min(chars.len())].iter().copied())
+        .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+        .collect();
+    let number = line.to_string();
+    description.push_str(&format!("\n   {} | {}{}", number, ellipsis, fragment));
+    description.push_str(&format!("\n   {} | {}^", " ".repeat(number.len()), padding));
+    description
+}
+
+/// Разбирает NAME=VALUE из --var; значение берётся как текст
+fn parse_var(assignment: &str) -> (String, String) {
+    let Some((name, value)) = assignment.split_once('=') else {
+        fail("Usage", &format!("Ошибка: --var ожидает NAME=VALUE, получено '{}'", assignment), exit_code::USAGE);
+    };
+    (name.to_string(), value.to_string())
+}
+
+/// Применяет изменения из --set к конфигурации
+fn apply_sets(config: &mut Value, sets: &[(String, Value)]) {
+    for (field, value) in sets {
+        if let Err(e) = path::set(config, field, value.clone()) {
+            fail("Usage", &format!("❌ Ошибка: {}", e), exit_code::USAGE);
         }
-        "decode" => {
-            let vpn_url = input.trim().to_string();
-            let decoded = decode(&vpn_url)?;
-            let output = to_string_pretty(&decoded)?;
-            write_output(output_file, &output)?;
+    }
+}
+
+/// Сжимает вложенные конфигурации обратно в Base64 + gzip (--encode-nested):
+/// значения по путям из --nested-field, а без них — поля из `NESTED_FIELDS`
+fn encode_nested_or_exit(config: &mut Value, paths: &[String]) {
+    let result = if paths.is_empty() {
+        collapse_nested(config).map(|count| info!("🔍 Сжато вложенных конфигураций: {}", count))
+    } else {
+        paths.iter().try_for_each(|field| {
+            let value = select(config, field).unwrap_or_else(|e| {
+                fail("Usage", &format!("❌ Ошибка: {}", e), exit_code::USAGE);
+            });
+            let blob = collapse_value(value)?;
+            path::set(config, field, Value::String(blob)).expect("path exists: checked by select");
+            Ok(())
+        })
+    };
+    if let Err(e) = result {
+        fail("Encode", &format!("❌ Ошибка сжатия вложенной конфигурации: {}", e), exit_code::ENCODE);
+    }
+}
+
+/// Добавляет в объект конфигурации поле `key` с текущим временем UTC в
+/// RFC 3339. Возвращает `false`, если конфигурация не объект
+fn stamp(config: &mut Value, key: &str) -> bool {
+    let Some(object) = config.as_object_mut() else {
+        return false;
+    };
+    let now = humantime::format_rfc3339_seconds(std::time::SystemTime::now());
+    object.insert(key.to_string(), Value::String(now.to_string()));
+    true
+}
+
+/// Извлекает одно поле по пути из --field; строки выводятся без кавычек
+fn format_field(value: &Value, path: &str, indent: Option<&str>) -> Result<String, ItemError> {
+    match select(value, path) {
+        Ok(Value::String(text)) => Ok(text.clone()),
+        Ok(leaf) => format_decoded(leaf, indent).map_err(|e| ItemError::new("Serialize", e.to_string())),
+        Err(e) => Err(ItemError::new("Path", e.to_string())),
+    }
+}
+
+/// Выводит декодированную конфигурацию так, как задают --field, --to и
+/// отступы; общий путь обычного декодирования и --repl
+fn render_decoded(decoded: &Value, options: &Options) -> Result<String, ItemError> {
+    match (&options.field, options.to) {
+        (Some(path), _) => format_field(decoded, path, json_indent(options)),
+        (None, DataFormat::Json) => {
+            format_decoded(decoded, json_indent(options)).map_err(|e| ItemError::new("Serialize", e.to_string()))
         }
-        _ => unreachable!(),
+        (None, format) => match to_format(decoded, format) {
+            Ok(text) => Ok(text.trim_end().to_string()),
+            Err(e) => Err(ItemError::new("Convert", format!("не удалось преобразовать в {} ({})", format, e))),
+        },
     }
+}
 
-    Ok(())
+/// Формирует понятное пользователю сообщение для каждой ошибки декодирования
+fn describe_decode_error(error: &DecodeError) -> String {
+    match error {
+        DecodeError::MissingPrefix => {
+            "строка не начинается с ожидаемой схемы (по умолчанию vpn://) — это не VPN URL".to_string()
+        }
+        DecodeError::Base64(e) => {
+            format!("данные после схемы URL не являются корректным Base64 ({})", e)
+        }
+        DecodeError::TooShort => {
+            "данные слишком короткие: отсутствует 4-байтовый заголовок".to_string()
+        }
+        DecodeError::IntegrityMismatch { expected, got } => {
+            format!("нарушена целостность данных: ожидалось {} байт, получено {}", expected, got)
+        }
+        DecodeError::Decompress(e) => {
+            format!("не удалось распаковать данные zlib/gzip ({})", e)
+        }
+        DecodeError::TruncatedStream => {
+            "сжатые данные обрываются — URL скопирован не полностью".to_string()
+        }
+        DecodeError::ChecksumMismatch { expected, actual } => {
+            format!(
+                "контрольная сумма Adler-32 не совпадает (ожидалось {:#010x}, получено {:#010x})",
+                expected, actual
+            )
+        }
+        DecodeError::Utf8(e) => {
+            format!("распакованные данные не являются текстом UTF-8 ({})", e)
+        }
+        DecodeError::Json(e) => {
+            format!("распакованные данные не являются корректным JSON ({})", e)
+        }
+        DecodeError::Input(e) => {
+            format!("не удалось прочитать входные данные ({})", e)
+        }
+        DecodeError::Output(e) => {
+            format!("не удалось записать результат ({})", e)
+        }
+        DecodeError::SignatureMismatch => {
+            "подпись HMAC не совпадает: URL изменён или указан неверный ключ".to_string()
+        }
+        DecodeError::NotSigned => {
+            "URL не подписан, а для --verify требуется подпись".to_string()
+        }
+        DecodeError::ImplausibleLength { declared, compressed } => {
+            format!(
+                "заголовок заявляет {} байт, которые не могут получиться из {} байт сжатых данных",
+                declared, compressed
+            )
+        }
+        DecodeError::TooLarge(limit) => {
+            format!("распакованные данные превышают {} байт — увеличьте лимит через --max-size", limit)
+        }
+        DecodeError::RepairSigned => {
+            "подписанный URL нельзя исправить: подпись распространяется и на заголовок".to_string()
+        }
+        DecodeError::TooManyLayers(limit) => {
+            format!("URL закодирован более {} раз подряд — похоже на зацикливание", limit)
+        }
+        DecodeError::NoMatchingFormat(attempts) => {
+            let details: Vec<String> = attempts
+                .iter()
+                .map(|(format, e)| format!("{}: {}", format, describe_decode_error(e)))
+                .collect();
+            format!("не подошёл ни один формат данных ({})", details.join("; "))
+        }
+    }
 }
 
-fn get_input(file: Option<String>, direct: Vec<String>) -> Result<String, Box<dyn std::error::Error>> {
-    if let Some(filename) = file {
-        read_file(&filename)
-    } else if !direct.is_empty() {
+/// Переменная окружения с входными данными по умолчанию
+const DEFAULT_INPUT_ENV: &str = "AMNEZIA_INPUT";
+
+/// Читает входные данные. Приоритет: аргументы командной строки, затем
+/// файл -i, затем переменная окружения (--input-env или `AMNEZIA_INPUT`),
+/// затем stdin. Переменная окружения не видна в списке процессов, в
+/// отличие от аргументов
+fn get_input(
+    file: Option<String>,
+    direct: Vec<String>,
+    fetch: Option<&str>,
+    from_clipboard: bool,
+    input_env: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    if !direct.is_empty() {
         Ok(direct.join(" "))
+    } else if let Some(filename) = file {
+        read_file(&filename)
+    } else if let Some(url) = fetch {
+        fetch_url(url)
+    } else if from_clipboard {
+        read_clipboard()
+    } else if let Some(value) = std::env::var(input_env).ok().filter(|value| !value.is_empty()) {
+        Ok(value)
     } else {
         read_stdin()
     }
 }
 
-fn write_output(file: Option<String>, content: &str) -> Result<(), Box<dyn std::error::Error>> {
-    if let Some(filename) = file {
-        write_file(&filename, content)
+/// Имя файла `-` в -i и -o означает stdin и stdout
+const STDIO_PATH: &str = "-";
+
+/// Выводится ли результат в stdout, а не в файл, буфер обмена или временный
+/// файл. Явный `-o -` выводит в stdout и вместе с --clipboard
+fn writes_to_stdout(options: &Options) -> bool {
+    match options.output_file.as_deref() {
+        Some(STDIO_PATH) => !options.secure_output,
+        Some(_) => false,
+        None => !options.clipboard && !options.secure_output,
+    }
+}
+
+/// Записывает результат в файл и/или буфер обмена; без них или с --tee —
+/// ещё и в stdout
+fn write_output(options: &Options, content: &str) -> Result<(), Box<dyn std::error::Error>> {
+    write_output_with(options, content, content)
+}
+
+/// Как [`write_output`], но в stdout выводится `stdout_content` (например,
+/// JSON с подсветкой), а в файл и буфер обмена — `content`.
+///
+/// Переводы строк приводятся к --crlf / --lf; без флагов файлы пишутся с
+/// переводами строк платформы, а stdout и буфер обмена — как есть
+fn write_output_with(
+    options: &Options,
+    content: &str,
+    stdout_content: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::Write;
+
+    let file_ending = options.line_ending.unwrap_or_else(LineEnding::platform);
+    if options.secure_output {
+        // Вместо содержимого в stdout выводится только путь к файлу
+        let path = write_secure_file(&convert_newlines(content, file_ending), file_ending)?;
+        writeln!(std::io::stdout().lock(), "{}", path.display())?;
+        return Ok(());
+    }
+    if let Some(filename) = options.output_file.as_deref().filter(|filename| *filename != STDIO_PATH) {
+        let content = convert_newlines(content, file_ending);
+        if options.in_place {
+            replace_file(filename, &content)?;
+        } else if options.append {
+            append_file(filename, &content, file_ending)?;
+        } else {
+            write_file(filename, &content)?;
+        }
+    }
+    if options.clipboard {
+        copy_to_clipboard(content);
+    }
+    if writes_to_stdout(options) || options.tee {
+        let ending = options.line_ending.unwrap_or(LineEnding::Lf);
+        let mut stdout = std::io::stdout().lock();
+        write!(stdout, "{}{}", convert_newlines(stdout_content, ending), ending.as_str())?;
+        stdout.flush()?;
+    }
+    Ok(())
+}
+
+/// Приводит все переводы строк текста к `ending`
+fn convert_newlines(text: &str, ending: LineEnding) -> std::borrow::Cow<'_, str> {
+    let text = if text.contains("\r\n") {
+        std::borrow::Cow::Owned(text.replace("\r\n", "\n"))
     } else {
-        println!("{}", content);
-        Ok(())
+        std::borrow::Cow::Borrowed(text)
+    };
+    match ending {
+        LineEnding::Crlf if text.contains('\n') => std::borrow::Cow::Owned(text.replace('\n', "\r\n")),
+        _ => text,
+    }
+}
+
+/// Сколько ждать ответа сервера для --fetch, включая загрузку тела
+#[cfg(all(feature = "fetch", not(target_arch = "wasm32")))]
+const FETCH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Загружает вход по HTTP(S) (--fetch). Ответ не 200 и превышение
+/// [`FETCH_TIMEOUT`] считаются ошибкой
+fn fetch_url(url: &str) -> Result<String, Box<dyn std::error::Error>> {
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err(format!("--fetch поддерживает только http:// и https:// адреса, получено '{}'", url).into());
+    }
+
+    #[cfg(all(feature = "fetch", not(target_arch = "wasm32")))]
+    {
+        let agent: ureq::Agent = ureq::Agent::config_builder().timeout_global(Some(FETCH_TIMEOUT)).build().into();
+        let body = agent.get(url).call().and_then(|mut response| {
+            if response.status() != 200 {
+                return Err(ureq::Error::StatusCode(response.status().as_u16()));
+            }
+            response.body_mut().read_to_string()
+        });
+        body.map_err(|e| match e {
+            ureq::Error::StatusCode(code) => format!("сервер {} ответил HTTP {}", url, code).into(),
+            ureq::Error::Timeout(_) => {
+                format!("сервер {} не ответил за {} с", url, FETCH_TIMEOUT.as_secs()).into()
+            }
+            e => format!("не удалось загрузить {}: {}", url, e).into(),
+        })
+    }
+    #[cfg(not(all(feature = "fetch", not(target_arch = "wasm32"))))]
+    Err("программа собрана без поддержки --fetch (фича `fetch`)".into())
+}
+
+/// Читает текст из системного буфера обмена (--from-clipboard)
+fn read_clipboard() -> Result<String, Box<dyn std::error::Error>> {
+    #[cfg(not(target_arch = "wasm32"))]
+    let result = arboard::Clipboard::new().and_then(|mut clipboard| clipboard.get_text());
+    #[cfg(target_arch = "wasm32")]
+    let result: Result<String, &str> = Err("не поддерживается в WASM");
+    result.map_err(|e| {
+        format!("буфер обмена недоступен: {}; на системах без графической среды используйте аргументы, -i или stdin", e).into()
+    })
+}
+
+/// Копирует текст в системный буфер обмена или завершает программу с ошибкой
+#[cfg_attr(target_arch = "wasm32", allow(unused_variables))]
+fn copy_to_clipboard(content: &str) {
+    #[cfg(not(target_arch = "wasm32"))]
+    let result = arboard::Clipboard::new().and_then(|mut clipboard| clipboard.set_text(content));
+    #[cfg(target_arch = "wasm32")]
+    let result: Result<(), &str> = Err("не поддерживается в WASM");
+    match result {
+        Ok(()) => info!("📋 Результат скопирован в буфер обмена"),
+        Err(e) => {
+            let text = format!(
+                "❌ Ошибка: буфер обмена недоступен ({})\n   {}",
+                e, "На системах без графической среды используйте -o или stdout"
+            );
+            fail("Clipboard", &text, exit_code::OUTPUT);
+        }
     }
 }
 
+/// Читает файл; `-` — stdin
 fn read_file(filename: &str) -> Result<String, Box<dyn std::error::Error>> {
     use std::fs;
-    Ok(fs::read_to_string(filename)?)
+    let content = if filename == STDIO_PATH { read_stdin()? } else { fs::read_to_string(filename)? };
+    // BOM от Windows редакторов мешает и декодированию URL, и разбору JSON
+    Ok(match content.strip_prefix('\u{feff}') {
+        Some(rest) => rest.to_string(),
+        None => content,
+    })
 }
 
 fn write_file(filename: &str, content: &str) -> Result<(), Box<dyn std::error::Error>> {
@@ -263,39 +2171,102 @@ fn write_file(filename: &str, content: &str) -> Result<(), Box<dyn std::error::E
     Ok(())
 }
 
-fn print_usage(program: &str) {
-    eprintln!("VPN Config Encoder/Decoder");
-    eprintln!();
-    eprintln!("Использование:");
-    eprintln!("  {} [-e|-d] [-i <input>] [-o <output>] [<data>]", program);
-    eprintln!();
-    eprintln!("Опции:");
-    eprintln!("  -e, --encode       Явно указать режим кодирования");
-    eprintln!("  -d, --decode       Явно указать режим декодирования");
-    eprintln!("  -i, --input FILE   Читать из файла");
-    eprintln!("  -o, --output FILE  Записать в файл");
-    eprintln!("  -h, --help         Показать справку");
-    eprintln!();
-    eprintln!("Автодетект:");
-    eprintln!("  Если не указаны -e/-d, программа автоматически определит");
-    eprintln!("  тип данных (JSON или VPN URL) и выполнит нужную операцию.");
-    eprintln!();
-    eprintln!("Примеры:");
-    eprintln!("  # Автодетект с файлами");
-    eprintln!("  {} -i config.json -o vpn_url.txt", program);
-    eprintln!("  {} -i vpn_url.txt -o config.json", program);
-    eprintln!();
-    eprintln!("  # Автодетект с прямым вводом");
-    eprintln!("  {} '{{\"server\":\"example.com\"}}'", program);
-    eprintln!("  {} 'vpn://AAAAHXic...'", program);
-    eprintln!();
-    eprintln!("  # Явное указание режима");
-    eprintln!("  {} -e -i config.json", program);
-    eprintln!("  {} -d -i vpn_url.txt", program);
-    eprintln!();
-    eprintln!("  # Работа с stdin/stdout");
-    eprintln!("  cat config.json | {}", program);
-    eprintln!("  echo 'vpn://...' | {} -o decoded.json", program);
+/// Дописывает содержимое в конец файла, создавая его при необходимости.
+/// Каждая запись завершается переводом строки `ending`, а если файл
+/// кончается без него, перевод строки добавляется перед записью
+fn append_file(filename: &str, content: &str, ending: LineEnding) -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::{Read, Seek, SeekFrom, Write};
+    let mut file = std::fs::OpenOptions::new().read(true).append(true).create(true).open(filename)?;
+    if file.metadata()?.len() > 0 {
+        let mut last = [0u8];
+        file.seek(SeekFrom::End(-1))?;
+        file.read_exact(&mut last)?;
+        if last[0] != b'\n' {
+            file.write_all(ending.as_str().as_bytes())?;
+        }
+    }
+    file.write_all(content.as_bytes())?;
+    file.write_all(ending.as_str().as_bytes())?;
+    Ok(())
+}
+
+/// Образец конфигурации для --selftest: вложенные объекты, массивы, Unicode
+/// и числа, как в настоящих конфигурациях AmneziaVPN
+const SELFTEST_CONFIG: &str = r#"{
+    "containers": [{
+        "container": "amnezia-awg",
+        "awg": {
+            "port": "51820",
+            "transport_proto": "udp",
+            "last_config": "{\"H1\": \"1234\", \"Jc\": 4, \"mtu\": 1280}"
+        }
+    }],
+    "defaultContainer": "amnezia-awg",
+    "description": "Сервер · тест ✓",
+    "dns1": "1.1.1.1",
+    "dns2": "8.8.8.8",
+    "hostName": "vpn.example.com"
+}"#;
+
+/// Кодирует [`SELFTEST_CONFIG`] всеми способами сжатия и в обоих форматах,
+/// декодирует обратно и сравнивает с исходной конфигурацией (--selftest)
+fn selftest() -> Result<(), String> {
+    let config: Value = from_str(SELFTEST_CONFIG).map_err(|e| format!("образец не разбирается: {}", e))?;
+    let compressions = [Compression::Zlib, Compression::Gzip, Compression::Deflate, Compression::None];
+    for compression in compressions {
+        for format_version in [FormatVersion::V1, FormatVersion::V2] {
+            let options = EncodeOptions { compression, format_version, ..Default::default() };
+            let setting = format!("{:?}, {:?}", compression, format_version);
+            let url = encode_with_options(&config, &options)
+                .map_err(|e| format!("кодирование ({}): {}", setting, e))?;
+            let decoded = decode_with_options(&url, &DecodeOptions::default())
+                .map_err(|e| format!("декодирование ({}): {}", setting, e))?;
+            if decoded != config {
+                return Err(format!("декодированная конфигурация отличается от исходной ({})", setting));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Версия пакета и, если известен при сборке, хеш git коммита
+fn version_string() -> String {
+    let version = format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+    match option_env!("GIT_COMMIT") {
+        Some(commit) => format!("{} ({})", version, commit),
+        None => version,
+    }
+}
+
+/// Записывает содержимое в новый временный файл с уникальным именем и
+/// возвращает его путь. На Unix файл создаётся с правами 0600, на Windows —
+/// во временном каталоге пользователя, недоступном другим пользователям
+fn write_secure_file(content: &str, ending: LineEnding) -> Result<std::path::PathBuf, Box<dyn std::error::Error>> {
+    use std::io::Write;
+    let mut file = tempfile::Builder::new()
+        .prefix("amnezia-")
+        .suffix(".txt")
+        .tempfile()?;
+    file.write_all(content.as_bytes())?;
+    file.write_all(ending.as_str().as_bytes())?;
+    let (_, path) = file.keep()?;
+    Ok(path)
+}
+
+/// Атомарно заменяет содержимое файла: пишет во временный файл рядом и
+/// переименовывает его, так что при ошибке исходный файл не обрезается.
+/// Права доступа исходного файла сохраняются
+fn replace_file(filename: &str, content: &str) -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::Write;
+    let path = Path::new(filename);
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let mut file = tempfile::NamedTempFile::new_in(dir)?;
+    file.write_all(content.as_bytes())?;
+    if let Ok(metadata) = std::fs::metadata(path) {
+        file.as_file().set_permissions(metadata.permissions())?;
+    }
+    file.persist(path)?;
+    Ok(())
 }
 
 fn read_stdin() -> Result<String, Box<dyn std::error::Error>> {
@@ -311,55 +2282,279 @@ mod tests {
     use serde_json::json;
 
     #[test]
-    fn test_encode_decode() {
+    fn test_format_decoded() {
+        let config = json!({"hostName": "example.com", "containers": [{"container": "amnezia-awg"}]});
+
+        let compact = format_decoded(&config, None).unwrap();
+        assert!(!compact.contains('\n'));
+
+        let pretty = format_decoded(&config, Some("  ")).unwrap();
+        assert_eq!(pretty, to_string_pretty(&config).unwrap());
+        assert_eq!(from_str::<Value>(&compact).unwrap(), from_str::<Value>(&pretty).unwrap());
+
+        let four = format_decoded(&config, Some("    ")).unwrap();
+        assert!(four.contains("\n    \"containers\": [\n        {\n            \"container\""));
+        let tab = format_decoded(&config, Some("\t")).unwrap();
+        assert!(tab.contains("\n\t\"hostName\""));
+    }
+
+    #[test]
+    fn test_format_field() {
+        let config = json!({"containers": [{"container": "amnezia-awg", "awg": {"port": 51820}}]});
+        assert_eq!(format_field(&config, "containers.0.container", Some("  ")).unwrap(), "amnezia-awg");
+        assert_eq!(format_field(&config, "containers[0].awg", None).unwrap(), r#"{"port":51820}"#);
+    }
+
+    #[test]
+    fn test_parse_set() {
+        let set = |path: &str, value: Value| (path.to_string(), value);
+        assert_eq!(parse_set(r#"hostName="new.example.com""#), set("hostName", json!("new.example.com")));
+        assert_eq!(parse_set("containers.0.awg.port=51820"), set("containers.0.awg.port", json!(51820)));
+        assert_eq!(parse_set(r#"dns={"a":1}"#), set("dns", json!({"a": 1})));
+        assert_eq!(parse_set("name=Home"), set("name", json!("Home")));
+    }
+
+    #[test]
+    fn test_format_summary() {
         let config = json!({
-            "server": "example.com",
-            "port": 8080,
-            "protocol": "wireguard",
-            "key": "test_key_12345"
+            "containers": [{"container": "amnezia-awg"}, {"container": "amnezia-openvpn"}],
+            "defaultContainer": "amnezia-awg",
+            "hostName": "vpn.example.com"
         });
+        assert_eq!(
+            format_summary(&config),
+            "Сервер: vpn.example.com\nКонтейнеры: 2 (amnezia-awg, amnezia-openvpn)\nКонтейнер по умолчанию: amnezia-awg"
+        );
+
+        // Отсутствующие поля не приводят к панике
+        assert_eq!(
+            format_summary(&json!({"containers": "x"})),
+            "Сервер: (не указан)\nКонтейнеры: (не массив)\nКонтейнер по умолчанию: (не указан)"
+        );
+    }
+
+    #[test]
+    fn test_input_env() {
+        let name = "AMNEZIA_TEST_INPUT_ENV";
+        // SAFETY: переменная используется только этим тестом
+        unsafe { std::env::set_var(name, "vpn://from-env") };
+
+        assert_eq!(get_input(None, Vec::new(), None, false, name).unwrap(), "vpn://from-env");
+        // Аргументы важнее переменной окружения и буфера обмена
+        assert_eq!(get_input(None, vec!["vpn://arg".to_string()], None, false, name).unwrap(), "vpn://arg");
+        assert_eq!(get_input(None, vec!["vpn://arg".to_string()], None, true, name).unwrap(), "vpn://arg");
+
+        unsafe { std::env::remove_var(name) };
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_secure_file() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = write_secure_file("secret", LineEnding::Lf).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "secret\n");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_check_url() {
+        let options = DecodeOptions::default();
+        let url = encode_with_options(&json!({"hostName": "a.example.com"}), &EncodeOptions::default()).unwrap();
+        assert!(check_url(&url, &options).is_ok());
+
+        // Повреждённый URL: обрезанный сжатый поток
+        let corrupted = &url[..url.len() - 6];
+        assert!(check_url(corrupted, &options).is_err());
+    }
+
+    #[test]
+    fn test_plan_jobs() {
+        let args = |list: &[&str]| {
+            let mut args = vec!["prog".to_string()];
+            args.extend(list.iter().map(|arg| arg.to_string()));
+            parse_args(&args)
+        };
+
+        // Один вход и выход — одно задание
+        let options = args(&["-i", "a.json", "-o", "a.txt"]);
+        assert_eq!(plan_jobs(&options), vec![(Some("a.json".into()), Some("a.txt".into()))]);
+
+        // Без файлов — одно задание со stdin/stdout
+        assert_eq!(plan_jobs(&args(&[])), vec![(None, None)]);
+
+        // Несколько входов в общий вывод
+        let options = args(&["-i", "a.json", "-i", "b.json"]);
+        assert_eq!(plan_jobs(&options), vec![(Some("a.json".into()), None), (Some("b.json".into()), None)]);
+
+        // Попарно -i и -o
+        let options = args(&["-i", "a.json", "-o", "a.txt", "-i", "b.json", "-o", "b.txt"]);
+        assert_eq!(
+            plan_jobs(&options),
+            vec![
+                (Some("a.json".into()), Some("a.txt".into())),
+                (Some("b.json".into()), Some("b.txt".into()))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_replace_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        std::fs::write(&path, "old content that is longer").unwrap();
+        replace_file(path.to_str().unwrap(), "new").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+        // Временные файлы не остаются рядом
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_classify_error() {
+        let missing = write_file("/nonexistent/dir/config.json", "{}").unwrap_err();
+        assert_eq!(classify_error(missing.as_ref()).2, exit_code::OUTPUT);
+        let json: Box<dyn std::error::Error> = from_str::<Value>("{").unwrap_err().into();
+        assert_eq!(classify_error(json.as_ref()), ("Serialize", "Ошибка сериализации результата", exit_code::FAILURE));
+        let other: Box<dyn std::error::Error> = "неизвестно".into();
+        assert_eq!(classify_error(other.as_ref()).0, "Failure");
+    }
+
+    #[test]
+    fn test_describe_json_error() {
+        // Позиция считается по исходному входу, вместе с комментарием
+        let input = "// config\n{\n  \"a\": 1\n  \"ы\": 2\n}";
+        let text = strip_json_comments(input);
+        let error = from_str::<Value>(text).unwrap_err();
+        assert_eq!(
+            describe_json_error(input, text, &error, true),
+            "некорректный JSON в строке 4, столбец 3: expected `,` or `}`\n   4 |   \"ы\": 2\n     |   ^"
+        );
+
+        // В первой строке учитываются пробелы, срезанные перед разбором
+        let input = "  {\"ы\": 1 x}";
+        let text = strip_json_comments(input);
+        let error = from_str::<Value>(text).unwrap_err();
+        assert_eq!(
+            describe_json_error(input, text, &error, false),
+            "некорректный JSON в строке 1, столбец 11: expected `,` or `}`"
+        );
+
+        // Длинная строка обрезается вокруг ошибки
+        let input = format!("{{\"key\": \"{}\", oops}}", "x".repeat(100));
+        let error = from_str::<Value>(&input).unwrap_err();
+        let description = describe_json_error(&input, &input, &error, true);
+        let snippet: Vec<&str> = description.lines().skip(1).collect();
+        assert!(snippet[0].starts_with("   1 | …"));
+        let column = |line: &str, index: usize| line[..index].chars().count();
+        assert_eq!(column(snippet[0], snippet[0].find("oops").unwrap()), column(snippet[1], snippet[1].find('^').unwrap()));
+    }
+
+    #[test]
+    fn test_convert_newlines() {
+        assert_eq!(convert_newlines("{\n  \"a\": 1\n}", LineEnding::Crlf), "{\r\n  \"a\": 1\r\n}");
+        // Уже существующие CRLF не удваиваются
+        assert_eq!(convert_newlines("a\r\nb\n", LineEnding::Crlf), "a\r\nb\r\n");
+        assert_eq!(convert_newlines("a\r\nb\n", LineEnding::Lf), "a\nb\n");
+        assert!(matches!(convert_newlines("vpn://AAAA", LineEnding::Crlf), std::borrow::Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_append_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("urls.txt");
+        let filename = path.to_str().unwrap();
+
+        append_file(filename, "first", LineEnding::Lf).unwrap();
+        append_file(filename, "second", LineEnding::Lf).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "first\nsecond\n");
+
+        // Файл без завершающего перевода строки не склеивается с новой записью
+        std::fs::write(&path, "existing").unwrap();
+        append_file(filename, "new", LineEnding::Crlf).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "existing\r\nnew\r\n");
+    }
 
-        let encoded = encode(&config).unwrap();
-        assert!(encoded.starts_with(PREFIX));
+    #[test]
+    fn test_compare_sizes() {
+        let config = json!({"hostName": "example.com", "containers": [{"container": "amnezia-awg"}]});
+        let table = compare_sizes(&config, &EncodeOptions::default()).unwrap();
+        let rows: Vec<Vec<&str>> = table.lines().skip(1).map(|line| line.split_whitespace().collect()).collect();
+        let settings: Vec<(&str, &str)> = rows.iter().map(|row| (row[0], row[1])).collect();
+        assert_eq!(
+            settings,
+            [("zlib", "1"), ("zlib", "6"), ("zlib", "9"), ("deflate", "1"), ("deflate", "6"), ("deflate", "9")]
+        );
+        // URL в таблице совпадает с реальным кодированием
+        let options = EncodeOptions { level: Some(9), ..Default::default() };
+        assert_eq!(rows[2][3], encode_with_options(&config, &options).unwrap().len().to_string());
+    }
 
-        let decoded = decode(&encoded).unwrap();
-        assert_eq!(config, decoded);
+    #[test]
+    fn test_hinted_mode() {
+        assert_eq!(hinted_mode(Some(DataFormat::Yaml), Some("config.yaml")), Some("encode"));
+        assert_eq!(hinted_mode(Some(DataFormat::Json), None), Some("encode"));
+        assert_eq!(hinted_mode(None, Some("links/home.VPN")), Some("decode"));
+        assert_eq!(hinted_mode(None, Some("link.txt")), Some("decode"));
+        assert_eq!(hinted_mode(None, Some("config")), None);
+        assert_eq!(hinted_mode(None, None), None);
     }
 
     #[test]
-    fn test_detect_input_type() {
-        // JSON детект
-        assert_eq!(detect_input_type(r#"{"key": "value"}"#), InputType::Json);
-        assert_eq!(detect_input_type(r#"{"server":"test.com"}"#), InputType::Json);
-        assert_eq!(detect_input_type(r#"[1, 2, 3]"#), InputType::Json);
-        
-        // VPN URL детект
-        assert_eq!(detect_input_type("vpn://AAAAHXic"), InputType::VpnUrl);
-        assert_eq!(detect_input_type("vpn://test123"), InputType::VpnUrl);
-        
-        // Unknown
-        assert_eq!(detect_input_type("random text"), InputType::Unknown);
-        assert_eq!(detect_input_type(""), InputType::Unknown);
+    fn test_process_batch_order() {
+        let options = Options { jobs: 4, ..Default::default() };
+        let urls: Vec<String> = (0..200)
+            .map(|n| match n % 7 {
+                0 => "vpn://broken".to_string(),
+                3 => String::new(),
+                _ => encode_with_options(&json!({ "n": n }), &options.encode).unwrap(),
+            })
+            .collect();
+        let records: Vec<&str> = urls.iter().map(String::as_str).collect();
+
+        let results = process_batch(&records, &options, None).unwrap();
+        // Пустые строки пропускаются, остальные идут в исходном порядке
+        assert_eq!(results.len(), 200 - (0..200).filter(|n| n % 7 == 3).count());
+        for (line, result) in &results {
+            let n = line - 1;
+            match result {
+                Ok(Processed::Decoded { value, .. }) => assert_eq!(value, &json!({ "n": n })),
+                Err(_) => assert_eq!(n % 7, 0),
+                _ => panic!("unexpected result for line {}", line),
+            }
+        }
+        assert!(results.windows(2).all(|pair| pair[0].0 < pair[1].0));
     }
 
     #[test]
-    fn test_helper_functions() {
-        let data = b"Hello, World!";
-        
-        // Тест сжатия/распаковки
-        let compressed = compress_data(data).unwrap();
-        let decompressed = decompress_data(&compressed).unwrap();
-        assert_eq!(data, decompressed.as_slice());
-        
-        // Тест заголовка
-        let len = 12345u32;
-        let header = create_header(len);
-        let read_len = read_header(&header);
-        assert_eq!(len, read_len);
-        
-        // Тест Base64
-        let encoded = encode_base64(data);
-        let decoded = decode_base64(&encoded).unwrap();
-        assert_eq!(data, decoded.as_slice());
-    }
-}
\ No newline at end of file
+    fn test_batch_items() {
+        let options = Options::default();
+        let url = encode_with_options(&json!({"hostName": "a.example.com"}), &options.encode).unwrap();
+
+        let mut encoder = EncodeContext::new(options.encode.clone());
+        let results: Vec<_> = [url.as_str(), "vpn://broken", r#"{"hostName":"b"}"#]
+            .iter()
+            .enumerate()
+            .map(|(i, line)| (i + 1, process_item(line, None, &options, None, &mut encoder)))
+            .collect();
+
+        let output = format_batch(&results, false, BatchFormat::Plain, false).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], r#"{"hostName":"a.example.com"}"#);
+        assert!(lines[1].starts_with("error: line 2:"));
+        assert!(lines[2].starts_with("vpn://"));
+
+        let array: Value = from_str(&format_batch(&results, true, BatchFormat::Plain, false).unwrap()).unwrap();
+        assert_eq!(array[1]["line"], 2);
+        assert!(array[1]["error"].is_string());
+
+        // NUL-разделённый вывод: каждая запись завершается NUL
+        let output = format_batch(&results, false, BatchFormat::Plain, true).unwrap();
+        let records: Vec<&str> = output.split_terminator('\0').collect();
+        assert_eq!(records.len(), 3);
+        assert!(output.ends_with('\0'));
+        assert!(records[1].starts_with("error: record 2:"));
+    }
+}
@@ -0,0 +1,107 @@
+use serde_json::Value;
+
+/// Как объединять массивы, которые есть в обеих конфигурациях
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ArrayMerge {
+    /// Массив из переопределений заменяет исходный (по умолчанию)
+    #[default]
+    Replace,
+    /// Элементы массива из переопределений добавляются в конец исходного
+    Concat,
+}
+
+impl ArrayMerge {
+    /// Разбирает значение `replace` или `concat`
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "replace" => Some(ArrayMerge::Replace),
+            "concat" => Some(ArrayMerge::Concat),
+            _ => None,
+        }
+    }
+}
+
+/// Глубоко объединяет `overrides` с `base`, изменяя `base`:
+///
+/// - если оба значения — объекты, ключи объединяются рекурсивно; ключи,
+///   которых нет в `overrides`, остаются из `base`;
+/// - если оба значения — массивы, они заменяются или склеиваются по `arrays`;
+///   элементы массивов между собой не объединяются;
+/// - в остальных случаях значение из `overrides` заменяет исходное, в том
+///   числе `null` (ключ при этом не удаляется) и значение другого типа
+pub fn merge(base: &mut Value, overrides: Value, arrays: ArrayMerge) {
+    match (base, overrides) {
+        (Value::Object(base), Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value, arrays),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (Value::Array(base), Value::Array(overrides)) if arrays == ArrayMerge::Concat => base.extend(overrides),
+        (base, overrides) => *base = overrides,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_merge_objects() {
+        let mut base = json!({
+            "hostName": "old.example.com",
+            "dns1": "1.1.1.1",
+            "awg": {"port": "51820", "mtu": 1280, "keys": {"public": "abc"}},
+            "description": "home"
+        });
+        let overrides = json!({
+            "hostName": "new.example.com",
+            "awg": {"port": "443", "keys": {"private": "xyz"}},
+            "description": null,
+            "dns2": "8.8.8.8"
+        });
+        merge(&mut base, overrides, ArrayMerge::Replace);
+        assert_eq!(
+            base,
+            json!({
+                "hostName": "new.example.com",
+                "dns1": "1.1.1.1",
+                "awg": {"port": "443", "mtu": 1280, "keys": {"public": "abc", "private": "xyz"}},
+                "description": null,
+                "dns2": "8.8.8.8"
+            })
+        );
+
+        // Другой тип значения просто заменяет исходное
+        let mut base = json!({"awg": {"port": "51820"}});
+        merge(&mut base, json!({"awg": "disabled"}), ArrayMerge::Replace);
+        assert_eq!(base, json!({"awg": "disabled"}));
+    }
+
+    #[test]
+    fn test_merge_arrays() {
+        let base = json!({"containers": [{"container": "amnezia-awg"}], "nested": {"list": [1, 2]}});
+        let overrides = json!({"containers": [{"container": "amnezia-openvpn"}], "nested": {"list": [3]}});
+
+        let mut replaced = base.clone();
+        merge(&mut replaced, overrides.clone(), ArrayMerge::Replace);
+        assert_eq!(replaced["containers"], json!([{"container": "amnezia-openvpn"}]));
+        assert_eq!(replaced["nested"]["list"], json!([3]));
+
+        let mut concatenated = base;
+        merge(&mut concatenated, overrides, ArrayMerge::Concat);
+        assert_eq!(
+            concatenated["containers"],
+            json!([{"container": "amnezia-awg"}, {"container": "amnezia-openvpn"}])
+        );
+        assert_eq!(concatenated["nested"]["list"], json!([1, 2, 3]));
+
+        assert_eq!(ArrayMerge::parse("concat"), Some(ArrayMerge::Concat));
+        assert_eq!(ArrayMerge::parse("append"), None);
+    }
+}
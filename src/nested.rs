@@ -0,0 +1,156 @@
+use crate::error::EncodeError;
+use crate::{compress_gzip, decode_base64, decompress_gzip, encode_base64, GZIP_MAGIC};
+use serde_json::Value;
+
+/// Максимальная глубина вложенности блобов, которые раскрывает [`expand_nested`]
+pub const MAX_NESTED_DEPTH: usize = 8;
+
+/// Поля, которые [`collapse_nested`] сжимает обратно: настройки контейнеров
+/// Amnezia (`containers[].<протокол>.last_config`) на любой глубине
+pub const NESTED_FIELDS: &[&str] = &["last_config"];
+
+/// Минимальная длина строки, которую имеет смысл проверять: Base64 от
+/// пустого gzip потока (20 байт) занимает 27 символов
+const MIN_BLOB_LEN: usize = 27;
+
+/// Раскрывает строки с вложенными блобами Base64 + gzip.
+///
+/// Строка считается блобом, если после Base64 начинается с магических байтов
+/// gzip. Распакованный JSON подставляется на место строки значением, другой
+/// текст — строкой. Блобы внутри раскрытых значений обрабатываются до
+/// глубины `max_depth`, глубже строки остаются как есть. Возвращает число
+/// раскрытых блобов
+pub fn expand_nested(value: &mut Value, max_depth: usize) -> usize {
+    match value {
+        Value::Object(map) => map.values_mut().map(|item| expand_nested(item, max_depth)).sum(),
+        Value::Array(items) => items.iter_mut().map(|item| expand_nested(item, max_depth)).sum(),
+        Value::String(text) if max_depth > 0 => match decode_blob(text) {
+            Some(expanded) => {
+                *value = expanded;
+                1 + expand_nested(value, max_depth - 1)
+            }
+            None => 0,
+        },
+        _ => 0,
+    }
+}
+
+/// Сжимает раскрытые значения полей из [`NESTED_FIELDS`] обратно в строки
+/// Base64 + gzip — обратная операция к [`expand_nested`].
+///
+/// Сжимаются только объекты и массивы: строковое значение уже является
+/// блобом или JSON строкой. Вложенные блобы внутри поля сжимаются раньше
+/// внешнего. Возвращает число сжатых полей
+pub fn collapse_nested(value: &mut Value) -> Result<usize, EncodeError> {
+    let mut collapsed = 0;
+    match value {
+        Value::Object(map) => {
+            for (key, item) in map.iter_mut() {
+                collapsed += collapse_nested(item)?;
+                if NESTED_FIELDS.contains(&key.as_str()) && (item.is_object() || item.is_array()) {
+                    *item = Value::String(collapse_value(item)?);
+                    collapsed += 1;
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                collapsed += collapse_nested(item)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(collapsed)
+}
+
+/// Сжимает значение в строку Base64 + gzip. Строки сжимаются как текст,
+/// остальное — как компактный JSON
+pub fn collapse_value(value: &Value) -> Result<String, EncodeError> {
+    let text = match value {
+        Value::String(text) => text.clone(),
+        other => serde_json::to_string(other)?,
+    };
+    let compressed = compress_gzip(text.as_bytes(), flate2::Compression::default())?;
+    Ok(encode_base64(&compressed))
+}
+
+/// Декодирует строку как Base64 + gzip; `None`, если это не блоб
+fn decode_blob(text: &str) -> Option<Value> {
+    let text = text.trim();
+    if text.len() < MIN_BLOB_LEN || text.contains(char::is_whitespace) {
+        return None;
+    }
+    let bytes = decode_base64(text).ok()?;
+    if !bytes.starts_with(&GZIP_MAGIC) {
+        return None;
+    }
+    let inflated = String::from_utf8(decompress_gzip(&bytes).ok()?).ok()?;
+    Some(serde_json::from_str(&inflated).unwrap_or(Value::String(inflated)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn blob(text: &str) -> String {
+        encode_base64(&compress_gzip(text.as_bytes(), flate2::Compression::default()).unwrap())
+    }
+
+    #[test]
+    fn test_expand_nested() {
+        let inner = json!({"port": "51820", "client_priv_key": "key"});
+        let mut config = json!({
+            "hostName": "example.com",
+            "containers": [{
+                "container": "amnezia-awg",
+                "awg": {"last_config": blob(&inner.to_string())},
+                "openvpn": {"profile": blob("client\nremote example.com 1194\n")}
+            }]
+        });
+        assert_eq!(expand_nested(&mut config, MAX_NESTED_DEPTH), 2);
+        assert_eq!(config["containers"][0]["awg"]["last_config"], inner);
+        assert_eq!(config["containers"][0]["openvpn"]["profile"], "client\nremote example.com 1194\n");
+        assert_eq!(config["hostName"], "example.com");
+
+        // Обычный Base64 без gzip не трогаем
+        let mut plain = json!({"key": encode_base64(b"not a gzip stream at all, just bytes")});
+        assert_eq!(expand_nested(&mut plain, MAX_NESTED_DEPTH), 0);
+    }
+
+    #[test]
+    fn test_collapse_nested() {
+        let inner = json!({"port": "51820", "nested": {"last_config": {"deep": true}}});
+        let mut config = json!({
+            "containers": [
+                {"awg": {"last_config": inner.clone()}},
+                {"openvpn": {"last_config": "{\"already\": \"a string\"}"}}
+            ]
+        });
+        assert_eq!(collapse_nested(&mut config).unwrap(), 2);
+        assert!(config["containers"][0]["awg"]["last_config"].is_string());
+        assert_eq!(config["containers"][1]["openvpn"]["last_config"], "{\"already\": \"a string\"}");
+
+        // Обратная операция восстанавливает исходные значения
+        assert_eq!(expand_nested(&mut config, MAX_NESTED_DEPTH), 2);
+        assert_eq!(config["containers"][0]["awg"]["last_config"], inner);
+
+        let text = collapse_value(&json!("client\nremote example.com\n")).unwrap();
+        assert_eq!(decode_blob(&text), Some(json!("client\nremote example.com\n")));
+    }
+
+    #[test]
+    fn test_expand_nested_depth() {
+        // Блоб внутри блоба внутри блоба
+        let level2 = json!({"secret": blob(r#"{"deep": true}"#)}).to_string();
+        let level1 = json!({"inner": blob(&level2)}).to_string();
+
+        let mut config = json!({"outer": blob(&level1)});
+        assert_eq!(expand_nested(&mut config, MAX_NESTED_DEPTH), 3);
+        assert_eq!(config["outer"]["inner"]["secret"]["deep"], true);
+
+        let mut config = json!({"outer": blob(&level1)});
+        assert_eq!(expand_nested(&mut config, 2), 2);
+        assert!(config["outer"]["inner"]["secret"].is_string());
+    }
+}
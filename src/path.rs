@@ -0,0 +1,173 @@
+use crate::error::PathError;
+use serde_json::Value;
+
+/// Сегмент пути к полю: ключ объекта или индекс массива
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// Разбирает путь вида `containers.0.awg.port` или `containers[0].awg.port`.
+///
+/// Числовой сегмент после точки считается индексом массива, но для объектов
+/// он также работает как ключ
+pub fn parse_path(path: &str) -> Result<Vec<Segment>, PathError> {
+    let mut segments = Vec::new();
+    for part in path.split('.') {
+        let (key, indices) = match part.find('[') {
+            Some(start) => part.split_at(start),
+            None => (part, ""),
+        };
+
+        if !key.is_empty() {
+            segments.push(match key.parse::<usize>() {
+                Ok(index) => Segment::Index(index),
+                Err(_) => Segment::Key(key.to_string()),
+            });
+        } else if indices.is_empty() {
+            return Err(PathError::Invalid(path.to_string()));
+        }
+
+        // Индексы в квадратных скобках: `[0][1]`
+        let mut rest = indices;
+        while let Some(tail) = rest.strip_prefix('[') {
+            let (index, tail) = tail
+                .split_once(']')
+                .ok_or_else(|| PathError::Invalid(path.to_string()))?;
+            let index = index
+                .parse::<usize>()
+                .map_err(|_| PathError::Invalid(path.to_string()))?;
+            segments.push(Segment::Index(index));
+            rest = tail;
+        }
+        if !rest.is_empty() {
+            return Err(PathError::Invalid(path.to_string()));
+        }
+    }
+    Ok(segments)
+}
+
+/// Возвращает значение по пути или ошибку с указанием, где путь оборвался
+pub fn select<'a>(value: &'a Value, path: &str) -> Result<&'a Value, PathError> {
+    let mut current = value;
+    let mut walked = Vec::new();
+    for segment in parse_path(path)? {
+        walked.push(segment_name(&segment));
+        current = child(current, &segment).ok_or_else(|| PathError::NotFound {
+            path: path.to_string(),
+            at: walked.join("."),
+        })?;
+    }
+    Ok(current)
+}
+
+/// Устанавливает значение по пути.
+///
+/// Недостающие ключи объектов создаются (промежуточные — пустыми объектами);
+/// индекс массива должен существовать либо быть равен длине массива (добавление)
+pub fn set(value: &mut Value, path: &str, new_value: Value) -> Result<(), PathError> {
+    let segments = parse_path(path)?;
+    let mut current = value;
+    for (depth, segment) in segments.iter().enumerate() {
+        let not_found = || PathError::NotFound {
+            path: path.to_string(),
+            at: segments[..=depth].iter().map(segment_name).collect::<Vec<_>>().join("."),
+        };
+        let is_last = depth + 1 == segments.len();
+
+        current = match (current, segment) {
+            (Value::Object(map), segment) => {
+                let entry = map.entry(segment_name(segment));
+                if is_last {
+                    *entry.or_insert(Value::Null) = new_value;
+                    return Ok(());
+                }
+                entry.or_insert_with(|| Value::Object(Default::default()))
+            }
+            (Value::Array(items), Segment::Index(index)) => {
+                if *index == items.len() {
+                    items.push(Value::Null);
+                }
+                let item = items.get_mut(*index).ok_or_else(not_found)?;
+                if is_last {
+                    *item = new_value;
+                    return Ok(());
+                }
+                item
+            }
+            _ => return Err(not_found()),
+        };
+    }
+
+    // Пустой путь невозможен: parse_path возвращает хотя бы один сегмент
+    *current = new_value;
+    Ok(())
+}
+
+/// Возвращает дочернее значение для одного сегмента пути
+fn child<'a>(value: &'a Value, segment: &Segment) -> Option<&'a Value> {
+    match (value, segment) {
+        (Value::Object(map), Segment::Key(key)) => map.get(key),
+        (Value::Object(map), Segment::Index(index)) => map.get(&index.to_string()),
+        (Value::Array(items), Segment::Index(index)) => items.get(*index),
+        _ => None,
+    }
+}
+
+fn segment_name(segment: &Segment) -> String {
+    match segment {
+        Segment::Key(key) => key.clone(),
+        Segment::Index(index) => index.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_select() {
+        let config = json!({
+            "hostName": "example.com",
+            "containers": [{"wireguard": {"client_pub_key": "abc="}}],
+            "ports": {"0": 51820}
+        });
+
+        assert_eq!(select(&config, "hostName").unwrap(), "example.com");
+        assert_eq!(select(&config, "containers.0.wireguard.client_pub_key").unwrap(), "abc=");
+        assert_eq!(select(&config, "containers[0].wireguard.client_pub_key").unwrap(), "abc=");
+        assert_eq!(select(&config, "ports.0").unwrap(), 51820);
+
+        match select(&config, "containers.1.wireguard") {
+            Err(PathError::NotFound { at, .. }) => assert_eq!(at, "containers.1"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(matches!(select(&config, "containers..x"), Err(PathError::Invalid(_))));
+        assert!(matches!(select(&config, "containers[x]"), Err(PathError::Invalid(_))));
+    }
+
+    #[test]
+    fn test_set() {
+        let mut config = json!({"hostName": "old.example.com", "containers": [{"awg": {}}]});
+
+        set(&mut config, "hostName", json!("new.example.com")).unwrap();
+        set(&mut config, "containers.0.awg.port", json!(51820)).unwrap();
+        set(&mut config, "containers[1]", json!({"container": "amnezia-xray"})).unwrap();
+        set(&mut config, "dns.primary", json!("1.1.1.1")).unwrap();
+
+        assert_eq!(config, json!({
+            "hostName": "new.example.com",
+            "containers": [{"awg": {"port": 51820}}, {"container": "amnezia-xray"}],
+            "dns": {"primary": "1.1.1.1"}
+        }));
+
+        assert!(matches!(set(&mut config, "containers.5", json!(1)), Err(PathError::NotFound { .. })));
+        assert!(matches!(set(&mut config, "hostName.x", json!(1)), Err(PathError::NotFound { .. })));
+
+        // Изменённая конфигурация проходит кодирование и декодирование
+        let decoded = crate::decode(&crate::encode(&config).unwrap()).unwrap();
+        assert_eq!(decoded["containers"][0]["awg"]["port"], 51820);
+    }
+}
@@ -0,0 +1,89 @@
+use crate::error::QrError;
+use qrcode::render::unicode::Dense1x2;
+use qrcode::types::QrError as QrCodeError;
+use qrcode::QrCode;
+#[cfg(feature = "qr-image")]
+use {crate::PREFIX, image::Luma, std::path::Path};
+
+/// Строит QR код для VPN URL, проверяя что URL помещается в один код
+fn build_qr(url: &str) -> Result<QrCode, QrError> {
+    QrCode::new(url.as_bytes()).map_err(|e| match e {
+        QrCodeError::DataTooLong => QrError::TooLarge { length: url.len() },
+        other => QrError::Encode(other),
+    })
+}
+
+/// Рисует QR код Unicode-блоками для вывода в терминал
+pub fn render_qr_unicode(url: &str) -> Result<String, QrError> {
+    let code = build_qr(url)?;
+    Ok(code
+        .render::<Dense1x2>()
+        .dark_color(Dense1x2::Light)
+        .light_color(Dense1x2::Dark)
+        .build())
+}
+
+/// Сохраняет QR код в PNG файл (фича `qr-image`)
+#[cfg(feature = "qr-image")]
+pub fn save_qr_png(url: &str, path: impl AsRef<Path>) -> Result<(), QrError> {
+    let code = build_qr(url)?;
+    let image = code.render::<Luma<u8>>().min_dimensions(256, 256).build();
+    image.save(path)?;
+    Ok(())
+}
+
+/// Считывает VPN URL из QR кода на изображении (фича `qr-image`)
+#[cfg(feature = "qr-image")]
+pub fn read_qr_image(path: impl AsRef<Path>) -> Result<String, QrError> {
+    let image = image::open(path)?.to_luma8();
+    let mut prepared = rqrr::PreparedImage::prepare_from_greyscale(
+        image.width() as usize,
+        image.height() as usize,
+        |x, y| image.get_pixel(x as u32, y as u32).0[0],
+    );
+
+    let grid = prepared.detect_grids().into_iter().next().ok_or(QrError::NotFound)?;
+    let (_, text) = grid.decode().map_err(|e| QrError::Unreadable(e.to_string()))?;
+
+    let text = text.trim().to_string();
+    if !text.starts_with(PREFIX) {
+        return Err(QrError::NotVpnUrl(text));
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_qr() {
+        let rendered = render_qr_unicode("vpn://AAAADHicq-ZSUFBKVLJSMOSqBQAP5wJ9").unwrap();
+        assert!(rendered.lines().count() > 10);
+
+        // Слишком длинный URL не помещается в один QR код
+        let huge = format!("vpn://{}", "A".repeat(8000));
+        assert!(matches!(render_qr_unicode(&huge), Err(QrError::TooLarge { length: 8006 })));
+    }
+
+    #[test]
+    #[cfg(feature = "qr-image")]
+    fn test_read_qr_image() {
+        let dir = std::env::temp_dir();
+        let url = "vpn://AAAADHicq-ZSUFBKVLJSMOSqBQAP5wJ9";
+
+        let path = dir.join(format!("amnezia-qr-url-{}.png", std::process::id()));
+        save_qr_png(url, &path).unwrap();
+        assert_eq!(read_qr_image(&path).unwrap(), url);
+
+        // QR код с текстом, который не является VPN URL
+        save_qr_png("https://example.com", &path).unwrap();
+        assert!(matches!(read_qr_image(&path), Err(QrError::NotVpnUrl(text)) if text == "https://example.com"));
+
+        // Изображение без QR кода
+        image::GrayImage::from_pixel(64, 64, Luma([255])).save(&path).unwrap();
+        assert!(matches!(read_qr_image(&path), Err(QrError::NotFound)));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,157 @@
+use serde_json::Value;
+
+/// Маска, которой заменяются значения секретных полей
+pub const MASK: &str = "***";
+
+/// Имена секретных полей (без учёта регистра)
+pub const SENSITIVE_KEYS: &[&str] = &[
+    "password",
+    "psk",
+    "psk_key",
+    "privatekey",
+    "private_key",
+    "presharedkey",
+    "secret",
+];
+
+/// Встроенные блоки профиля `.ovpn` с ключами
+const SENSITIVE_BLOCKS: &[&str] = &["key", "tls-auth", "tls-crypt", "tls-crypt-v2", "secret"];
+
+/// Суффиксы имён секретных полей, например `client_priv_key`
+const SENSITIVE_SUFFIXES: &[&str] = &["_priv_key", "_password"];
+
+/// Проверяет, является ли поле секретным: по встроенному списку или по
+/// дополнительным именам `extra_keys` (без учёта регистра)
+pub fn is_sensitive(key: &str, extra_keys: &[String]) -> bool {
+    let key = key.to_lowercase();
+    SENSITIVE_KEYS.contains(&key.as_str())
+        || SENSITIVE_SUFFIXES.iter().any(|suffix| key.ends_with(suffix))
+        || extra_keys.iter().any(|extra| extra.to_lowercase() == key)
+}
+
+/// Заменяет значения секретных полей на [`MASK`].
+///
+/// Amnezia хранит настройки контейнеров (`last_config`) JSON строкой внутри
+/// JSON, а профили клиентов — текстом `Key = value`, поэтому секреты
+/// маскируются и внутри таких строк
+pub fn redact(value: &mut Value, extra_keys: &[String]) {
+    match value {
+        Value::Object(map) => {
+            for (key, item) in map.iter_mut() {
+                if is_sensitive(key, extra_keys) {
+                    *item = Value::String(MASK.to_string());
+                } else {
+                    redact(item, extra_keys);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| redact(item, extra_keys)),
+        Value::String(text) => {
+            if let Some(redacted) = redact_embedded(text, extra_keys) {
+                *text = redacted;
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Маскирует секреты в тексте профиля клиента (`.conf`, `.ovpn`): значения
+/// строк `Key = value` с секретными ключами и содержимое блоков `<key>`,
+/// `<tls-auth>` и подобных
+pub fn redact_profile(text: &str, extra_keys: &[String]) -> String {
+    let text = redact_embedded(text, extra_keys).unwrap_or_else(|| text.to_string());
+    let mut inside = None;
+    let mut lines = Vec::new();
+    for line in text.split('\n') {
+        let tag = line.trim().strip_prefix('<').and_then(|rest| rest.strip_suffix('>'));
+        match (inside, tag) {
+            (None, Some(tag)) if SENSITIVE_BLOCKS.contains(&tag) => {
+                inside = Some(tag);
+                lines.push(line.to_string());
+                lines.push(MASK.to_string());
+            }
+            (Some(open), Some(tag)) if tag.strip_prefix('/') == Some(open) => {
+                inside = None;
+                lines.push(line.to_string());
+            }
+            (Some(_), _) => {}
+            (None, _) => lines.push(line.to_string()),
+        }
+    }
+    lines.join("\n")
+}
+
+/// Маскирует секреты во вложенном JSON или в тексте `Key = value`.
+/// Возвращает `None`, если строку менять не нужно
+fn redact_embedded(text: &str, extra_keys: &[String]) -> Option<String> {
+    // Текст профиля тоже может начинаться с `[` (`[Interface]`), поэтому
+    // вложенным JSON считаем только то, что действительно разбирается
+    let trimmed = text.trim_start();
+    if (trimmed.starts_with('{') || trimmed.starts_with('['))
+        && let Ok(mut nested) = serde_json::from_str::<Value>(text)
+    {
+        redact(&mut nested, extra_keys);
+        return Some(nested.to_string());
+    }
+
+    if !text.contains('=') {
+        return None;
+    }
+    let mut changed = false;
+    let lines: Vec<String> = text
+        .split('\n')
+        .map(|line| match line.split_once('=') {
+            Some((key, _)) if is_sensitive(key.trim(), extra_keys) => {
+                changed = true;
+                format!("{}= {}", key, MASK)
+            }
+            _ => line.to_string(),
+        })
+        .collect();
+    changed.then(|| lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_redact() {
+        let last_config = json!({"client_priv_key": "secret1", "client_ip": "10.8.1.2"});
+        let mut config = json!({
+            "hostName": "example.com",
+            "Password": "hunter2",
+            "containers": [{
+                "container": "amnezia-wireguard",
+                "wireguard": {
+                    "last_config": last_config.to_string(),
+                    "config": "[Interface]\nPrivateKey = abc\nAddress = 10.8.1.2/32",
+                    "psk_key": "psk"
+                }
+            }],
+            "token": "t0ken"
+        });
+        redact(&mut config, &["TOKEN".to_string()]);
+
+        assert_eq!(config["hostName"], "example.com");
+        assert_eq!(config["Password"], MASK);
+        assert_eq!(config["token"], MASK);
+
+        let wireguard = &config["containers"][0]["wireguard"];
+        assert_eq!(wireguard["psk_key"], MASK);
+        assert_eq!(wireguard["config"], "[Interface]\nPrivateKey = ***\nAddress = 10.8.1.2/32");
+        let nested: Value = serde_json::from_str(wireguard["last_config"].as_str().unwrap()).unwrap();
+        assert_eq!(nested, json!({"client_priv_key": MASK, "client_ip": "10.8.1.2"}));
+    }
+
+    #[test]
+    fn test_redact_profile() {
+        let profile = "client\nremote vpn.example.com 1194\n<ca>\nCA\n</ca>\n<key>\nKEY1\nKEY2\n</key>\n<tls-crypt>\nTLS\n</tls-crypt>\n";
+        assert_eq!(
+            redact_profile(profile, &[]),
+            "client\nremote vpn.example.com 1194\n<ca>\nCA\n</ca>\n<key>\n***\n</key>\n<tls-crypt>\n***\n</tls-crypt>\n"
+        );
+        assert_eq!(redact_profile("[Interface]\nPrivateKey = abc\n", &[]), "[Interface]\nPrivateKey = ***\n");
+    }
+}
@@ -0,0 +1,62 @@
+//! Подпись полезной нагрузки HMAC-SHA256.
+//!
+//! Подписанная полезная нагрузка (до Base64):
+//! `[0xA3][длина JSON: u32 BE][данные][тег HMAC-SHA256: 32 байта]`.
+//! Тег вычисляется по заголовку длины и сжатым данным, т.е. по всему, что
+//! лежит между меткой и тегом. Метка отличает подписанные URL от V1 и V2
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+
+/// Метка подписанной полезной нагрузки
+pub const SIGNED_MAGIC: u8 = 0xA3;
+
+/// Длина тега HMAC-SHA256, байт
+pub const TAG_LEN: usize = 32;
+
+/// Вычисляет тег HMAC-SHA256 данных
+pub fn compute_tag(key: &[u8], data: &[u8]) -> [u8; TAG_LEN] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().into()
+}
+
+/// Проверяет тег HMAC-SHA256 данных за постоянное время
+pub fn verify_tag(key: &[u8], data: &[u8], tag: &[u8]) -> bool {
+    tags_equal(&compute_tag(key, data), tag)
+}
+
+/// Сравнивает MAC теги за время, не зависящее от содержимого.
+///
+/// Обычное `==` завершается на первом несовпавшем байте, и по времени ответа
+/// можно подбирать тег побайтно. Длина тега не секретна, поэтому теги разной
+/// длины сразу считаются несовпадающими
+pub fn tags_equal(expected: &[u8], actual: &[u8]) -> bool {
+    expected.len() == actual.len() && bool::from(expected.ct_eq(actual))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tags_equal() {
+        let tag = [0x5a_u8; 32];
+        let mut other = tag;
+        assert!(tags_equal(&tag, &other));
+
+        other[31] ^= 1;
+        assert!(!tags_equal(&tag, &other));
+        assert!(!tags_equal(&tag, &tag[..31]));
+        assert!(tags_equal(&[], &[]));
+    }
+
+    #[test]
+    fn test_verify_tag() {
+        let tag = compute_tag(b"key", b"payload");
+        assert!(verify_tag(b"key", b"payload", &tag));
+        assert!(!verify_tag(b"other", b"payload", &tag));
+        assert!(!verify_tag(b"key", b"payloaD", &tag));
+    }
+}
@@ -0,0 +1,150 @@
+//! Деление длинного URL на части для QR кодов и мессенджеров.
+//!
+//! Каждая часть — обычный URL со схемой, полезная нагрузка которого (до
+//! Base64): `[0xA5][id: u32 BE][номер части: u8][всего частей: u8][фрагмент]`.
+//! Фрагменты — куски полезной нагрузки исходного URL после Base64, поэтому
+//! склеенные по порядку части дают исходный URL. `id` — первые четыре байта
+//! SHA-256 полезной нагрузки, чтобы части разных конфигураций не смешались
+
+use crate::error::SplitError;
+use sha2::{Digest, Sha256};
+
+/// Метка части разделённого URL
+pub const PART_MAGIC: u8 = 0xA5;
+
+/// Длина заголовка части: метка, id, номер и количество частей
+pub const PART_HEADER_LEN: usize = 7;
+
+/// Наибольшее количество частей: номер хранится в одном байте
+pub const MAX_PARTS: usize = u8::MAX as usize;
+
+/// Разобранная часть разделённого URL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Part {
+    /// Общий для всех частей идентификатор
+    pub id: u32,
+    /// Номер части, начиная с 1
+    pub index: u8,
+    /// Всего частей
+    pub total: u8,
+    /// Фрагмент полезной нагрузки исходного URL
+    pub data: Vec<u8>,
+}
+
+/// Делит URL на `parts` частей примерно равной длины
+pub fn split_url(url: &str, parts: usize, prefix: &str) -> Result<Vec<String>, SplitError> {
+    if !(2..=MAX_PARTS).contains(&parts) {
+        return Err(SplitError::InvalidCount(parts));
+    }
+    let encoded = url.trim().strip_prefix(prefix).ok_or(crate::DecodeError::MissingPrefix)?;
+    let payload = crate::decode_base64(encoded)?;
+    if parts > payload.len() {
+        return Err(SplitError::TooManyParts { parts, len: payload.len() });
+    }
+
+    let digest = Sha256::digest(&payload);
+    let id = *digest.first_chunk::<4>().expect("SHA-256 digest is 32 bytes");
+    // Первые `len % parts` частей на байт длиннее остальных
+    let (size, longer) = (payload.len() / parts, payload.len() % parts);
+    let mut rest = &payload[..];
+    let urls = (0..parts)
+        .map(|index| {
+            let (chunk, tail) = rest.split_at(size + usize::from(index < longer));
+            rest = tail;
+            let header = [&[PART_MAGIC][..], &id, &[index as u8 + 1, parts as u8]].concat();
+            format!("{}{}", prefix, crate::encode_base64(&[&header[..], chunk].concat()))
+        })
+        .collect();
+    Ok(urls)
+}
+
+/// Разбирает часть разделённого URL; `None` — это не часть
+pub fn parse_part(url: &str, prefix: &str) -> Option<Part> {
+    let encoded = url.trim().strip_prefix(prefix)?;
+    let payload = crate::decode_base64(encoded).ok()?;
+    let (header, data) = payload.split_first_chunk::<PART_HEADER_LEN>()?;
+    let [PART_MAGIC, a, b, c, d, index, total] = *header else {
+        return None;
+    };
+    if index == 0 || index > total {
+        return None;
+    }
+    Some(Part { id: u32::from_be_bytes([a, b, c, d]), index, total, data: data.to_vec() })
+}
+
+/// Является ли строка частью разделённого URL
+pub fn is_part(url: &str, prefix: &str) -> bool {
+    parse_part(url, prefix).is_some()
+}
+
+/// Собирает исходный URL из частей в любом порядке. Все части должны
+/// относиться к одному URL и присутствовать ровно по одному разу
+pub fn join_parts(urls: &[&str], prefix: &str) -> Result<String, SplitError> {
+    let mut parts = urls
+        .iter()
+        .enumerate()
+        .map(|(position, url)| parse_part(url, prefix).ok_or(SplitError::NotAPart(position + 1)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let Some(first) = parts.first() else {
+        return Err(SplitError::Empty);
+    };
+    let (id, total) = (first.id, first.total);
+    if let Some(part) = parts.iter().find(|part| part.id != id) {
+        return Err(SplitError::MixedIds { expected: id, got: part.id });
+    }
+    if let Some(part) = parts.iter().find(|part| part.total != total) {
+        return Err(SplitError::TotalMismatch { expected: total, got: part.total });
+    }
+
+    parts.sort_by_key(|part| part.index);
+    if let Some(pair) = parts.windows(2).find(|pair| pair[0].index == pair[1].index) {
+        return Err(SplitError::Duplicate(pair[0].index));
+    }
+    let missing: Vec<u8> = (1..=total).filter(|index| parts.iter().all(|part| part.index != *index)).collect();
+    if !missing.is_empty() {
+        return Err(SplitError::Missing(missing));
+    }
+
+    let payload: Vec<u8> = parts.into_iter().flat_map(|part| part.data).collect();
+    Ok(format!("{}{}", prefix, crate::encode_base64(&payload)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PREFIX;
+    use serde_json::json;
+
+    #[test]
+    fn test_split_and_join() {
+        let config = json!({"hostName": "example.com", "containers": [{"container": "amnezia-awg"}]});
+        let url = crate::encode(&config).unwrap();
+
+        let parts = split_url(&url, 3, PREFIX).unwrap();
+        assert_eq!(parts.len(), 3);
+        let parsed: Vec<Part> = parts.iter().map(|part| parse_part(part, PREFIX).unwrap()).collect();
+        assert!(parsed.iter().all(|part| part.id == parsed[0].id && part.total == 3));
+        assert_eq!(parsed.iter().map(|part| part.index).collect::<Vec<_>>(), [1, 2, 3]);
+        assert!(!is_part(&url, PREFIX));
+
+        // Порядок частей не важен
+        let shuffled = [parts[2].as_str(), parts[0].as_str(), parts[1].as_str()];
+        let joined = join_parts(&shuffled, PREFIX).unwrap();
+        assert_eq!(joined, url);
+        assert_eq!(crate::decode(&joined).unwrap(), config);
+    }
+
+    #[test]
+    fn test_join_errors() {
+        let first = split_url(&crate::encode(&json!({"hostName": "a.example.com"})).unwrap(), 3, PREFIX).unwrap();
+        let second = split_url(&crate::encode(&json!({"hostName": "b.example.com"})).unwrap(), 3, PREFIX).unwrap();
+
+        assert!(matches!(join_parts(&[&first[0], &first[2]], PREFIX), Err(SplitError::Missing(missing)) if missing == [2]));
+        assert!(matches!(join_parts(&[&first[0], &first[0], &first[1]], PREFIX), Err(SplitError::Duplicate(1))));
+        assert!(matches!(join_parts(&[&first[0], &second[1], &first[2]], PREFIX), Err(SplitError::MixedIds { .. })));
+        assert!(matches!(join_parts(&[&first[0], "vpn://AAAA"], PREFIX), Err(SplitError::NotAPart(2))));
+
+        assert!(matches!(split_url("vpn://AAAA", 1, PREFIX), Err(SplitError::InvalidCount(1))));
+        assert!(matches!(split_url("vpn://AAAA", 4, PREFIX), Err(SplitError::TooManyParts { parts: 4, len: 3 })));
+    }
+}
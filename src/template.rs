@@ -0,0 +1,98 @@
+use crate::error::TemplateError;
+
+/// Подставляет значения переменных вместо `${NAME}` в тексте шаблона.
+///
+/// Имя состоит из латинских букв, цифр и `_` и не начинается с цифры;
+/// `$${` выводится как литеральный `${`. Значения подставляются как есть,
+/// без экранирования, поэтому внутри JSON строки кавычки в значении нужно
+/// экранировать самому. Если переменной нет в `vars`, с `strict` это
+/// ошибка, иначе плейсхолдер остаётся в тексте без изменений
+pub fn substitute(text: &str, vars: &[(String, String)], strict: bool) -> Result<String, TemplateError> {
+    let mut output = String::with_capacity(text.len());
+    let mut unresolved: Vec<String> = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find("${") {
+        // `$${` — экранированный `${`
+        if rest[..start].ends_with('$') {
+            output.push_str(&rest[..start - 1]);
+            output.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find('}') else {
+            if strict {
+                return Err(TemplateError::Unterminated(text.len() - rest.len() + start));
+            }
+            rest = &rest[start..];
+            break;
+        };
+
+        let name = &after[..end];
+        if !is_valid_name(name) {
+            if strict {
+                return Err(TemplateError::InvalidName(name.to_string()));
+            }
+            output.push_str("${");
+            rest = after;
+            continue;
+        }
+        match vars.iter().rev().find(|(var, _)| var == name) {
+            Some((_, value)) => output.push_str(value),
+            None => {
+                if !unresolved.iter().any(|missing| missing == name) {
+                    unresolved.push(name.to_string());
+                }
+                output.push_str(&rest[start..start + 3 + end]);
+            }
+        }
+        rest = &after[end + 1..];
+    }
+    output.push_str(rest);
+
+    if strict && !unresolved.is_empty() {
+        return Err(TemplateError::Unresolved(unresolved));
+    }
+    Ok(output)
+}
+
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn test_substitute() {
+        let vars = vars(&[("SERVER", "vpn.example.com"), ("KEY", "abc"), ("KEY", "xyz")]);
+        let template = r#"{"hostName": "${SERVER}", "key": "${KEY}", "cost": "$${SERVER}"}"#;
+        assert_eq!(
+            substitute(template, &vars, true).unwrap(),
+            r#"{"hostName": "vpn.example.com", "key": "xyz", "cost": "${SERVER}"}"#
+        );
+
+        // Без strict неизвестные и неполные плейсхолдеры остаются как есть
+        let text = "${SERVER} ${PORT} ${not a name} ${open";
+        assert_eq!(substitute(text, &vars, false).unwrap(), "vpn.example.com ${PORT} ${not a name} ${open");
+    }
+
+    #[test]
+    fn test_substitute_strict() {
+        let error = substitute("${SERVER}:${PORT} ${DNS} ${PORT}", &[], true).unwrap_err();
+        assert!(matches!(&error, TemplateError::Unresolved(names) if names == &["SERVER", "PORT", "DNS"]));
+        assert_eq!(error.to_string(), "Unresolved template variables: SERVER, PORT, DNS");
+
+        assert!(matches!(substitute("ok ${open", &[], true), Err(TemplateError::Unterminated(3))));
+        assert!(matches!(substitute("${1x}", &[], true), Err(TemplateError::InvalidName(name)) if name == "1x"));
+    }
+}
@@ -0,0 +1,248 @@
+//! Справка командной строки на русском и английском
+
+/// Язык справки
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    Ru,
+    En,
+}
+
+impl Lang {
+    /// Разбирает значение --lang
+    pub fn parse(value: &str) -> Option<Lang> {
+        match value {
+            "ru" => Some(Lang::Ru),
+            "en" => Some(Lang::En),
+            _ => None,
+        }
+    }
+
+    /// Определяет язык по переменным локали. Русский — для русской локали
+    /// и когда локаль не задана (исторически справка была на русском)
+    pub fn detect() -> Lang {
+        let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty());
+        match locale {
+            Some(locale) if !locale.starts_with("ru") && locale != "C" && locale != "POSIX" => Lang::En,
+            _ => Lang::Ru,
+        }
+    }
+}
+
+/// Описание опции: флаги и текст на каждом языке. Перевод строки в тексте
+/// продолжает описание с отступом
+struct OptionHelp {
+    flags: &'static str,
+    ru: &'static str,
+    en: &'static str,
+}
+
+const fn opt(flags: &'static str, ru: &'static str, en: &'static str) -> OptionHelp {
+    OptionHelp { flags, ru, en }
+}
+
+/// Все опции в порядке вывода
+const OPTIONS: &[OptionHelp] = &[
+    opt("-e, --encode", "Явно указать режим кодирования", "Force encode mode"),
+    opt("-d, --decode", "Явно указать режим декодирования", "Force decode mode"),
+    opt("--validate", "Декодировать и проверить структуру конфигурации Amnezia", "Decode and check the Amnezia config structure"),
+    opt("--strict", "При --validate завершаться с ошибкой, если есть проблемы", "Exit with an error if --validate finds problems"),
+    opt("--repair", "Пересчитать заголовок длины по распакованным данным и вывести\nисправленный URL (отключает проверку целостности!)", "Recompute the length header from the decompressed data and print\nthe corrected URL (overrides the integrity check!)"),
+    opt("--check", "Только проверить, что URL корректен: OK или ошибка в stderr,\nбез вывода JSON", "Only check that the URL is well-formed: OK or an error on stderr,\nno JSON output"),
+    opt("--hash sha256", "Вывести SHA-256 нормализованной конфигурации (ключи\nотсортированы, JSON компактный) вместо результата", "Print the SHA-256 of the normalized config (sorted keys,\nminified JSON) instead of the result"),
+    opt("--summary", "Вывести краткую сводку: сервер, контейнеры, контейнер по умолчанию", "Print a short summary: server, containers, default container"),
+    opt("--schema FILE", "Проверить декодированную конфигурацию по JSON Schema из файла", "Validate the decoded config against a JSON Schema file"),
+    opt("--decode-nested", "Раскрыть вложенные блобы Base64 + gzip (настройки\nконтейнеров) в JSON или текст", "Expand nested Base64 + gzip blobs (container settings)\ninto JSON or text"),
+    opt("--encode-nested", "Перед кодированием сжать в Base64 + gzip объекты в полях\nlast_config (обратно к --decode-nested)", "Before encoding, compress objects in last_config fields\nto Base64 + gzip (inverse of --decode-nested)"),
+    opt("--nested-field PATH", "Сжать для --encode-nested значение по пути вместо\nlast_config (можно указать несколько раз)", "Compress the value at PATH for --encode-nested instead\nof last_config (repeatable)"),
+    opt("--redact", "Заменить значения секретных полей (ключи, пароли) на \"***\";\nдействует и с --export, --diff", "Replace secret values (keys, passwords) with \"***\";\nalso applies to --export and --diff"),
+    opt("--redact-key NAME", "Дополнительное секретное поле для --redact\n(можно указать несколько раз)", "Extra secret field name for --redact (repeatable)"),
+    opt("-i, --input FILE", "Читать из файла (можно указать несколько раз); - — stdin", "Read input from a file (repeatable); - for stdin"),
+    opt("--input-env NAME", "Читать из переменной окружения (по умолчанию AMNEZIA_INPUT).\nПриоритет входа: аргументы > -i > --from-clipboard >\nпеременная окружения > stdin", "Read input from an environment variable (default AMNEZIA_INPUT).\nInput precedence: arguments > -i > --from-clipboard >\nenvironment variable > stdin"),
+    opt("--fetch URL", "Загрузить вход по HTTP(S), например JSON или vpn:// по\nraw ссылке (сборка с --features fetch)", "Download the input over HTTP(S), e.g. JSON or a vpn://\nfrom a raw link (build with --features fetch)"),
+    opt("--from-clipboard", "Читать вход из системного буфера обмена", "Read input from the system clipboard"),
+    opt("-o, --output FILE", "Записать в файл (при нескольких -i — по одному на каждый);\n- — stdout", "Write output to a file (one per -i when -i is repeated);\n- for stdout"),
+    opt("--in-place", "Записать результат обратно в файл из -i (атомарно,\nпри ошибке файл не меняется)", "Write the result back to the -i file (atomically,\nthe file is kept intact on error)"),
+    opt("--append", "Дописать результат в конец файла -o, а не перезаписать", "Append the result to the -o file instead of overwriting it"),
+    opt("--tee", "Вместе с -o вывести результат ещё и в stdout", "With -o, also print the result to stdout"),
+    opt("--secure-output", "Записать результат в новый временный файл с правами 0600\nи вывести его путь", "Write the result to a fresh 0600 temp file and print its path"),
+    opt("--clipboard", "Скопировать результат в буфер обмена вместо stdout", "Copy the result to the clipboard instead of stdout"),
+    opt("--level N", "Уровень сжатия zlib (0–9) при кодировании", "zlib compression level (0-9) when encoding"),
+    opt("--format FORMAT", "Формат сжатия при кодировании: zlib (по умолчанию), gzip\nили deflate", "Compression when encoding: zlib (default), gzip or deflate"),
+    opt("--deflate-raw", "Сжимать «сырым» deflate без заголовка zlib (как wbits=-15),\nто же, что --format deflate", "Compress with raw deflate without the zlib header (wbits=-15),\nsame as --format deflate"),
+    opt("--no-compress", "Кодировать без сжатия: заголовок + JSON (для отладки,\nURL получается намного длиннее)", "Encode without compression: header + JSON (for debugging,\nthe URL gets much longer)"),
+    opt("--minify", "Сжимать JSON без отступов: URL короче, рекомендуется для обмена", "Compress JSON without indentation: shorter URL, recommended for sharing"),
+    opt("--endian ORDER", "Порядок байтов заголовка длины: big (по умолчанию, как в\nAmneziaVPN) или little. При декодировании обратный порядок\nпробуется, если длина не сошлась", "Byte order of the length header: big (default, as in\nAmneziaVPN) or little. Decoding falls back to the other\norder if the length does not match"),
+    opt("--deterministic", "Воспроизводимый URL: сортировка ключей, компактный JSON,\nzlib уровня 9, V1, Base64 URL-safe (перекрывает --level,\n--format, --minify, --preserve-order)", "Reproducible URL: sorted keys, minified JSON, zlib level 9,\nV1, URL-safe Base64 (overrides --level, --format, --minify,\n--preserve-order)"),
+    opt("--base64 ALPHABET", "Алфавит Base64 при кодировании: url-safe (по умолчанию) или standard", "Base64 alphabet when encoding: url-safe (default) or standard"),
+    opt("--format-version N", "Версия формата при кодировании: 1 (по умолчанию, как в AmneziaVPN)\nили 2 (с меткой версии)", "Payload format when encoding: 1 (default, as in AmneziaVPN)\nor 2 (with a version tag)"),
+    opt("--sign KEY", "Подписать URL HMAC-SHA256 при кодировании", "Sign the URL with HMAC-SHA256 when encoding"),
+    opt("--verify KEY", "Проверить подпись HMAC-SHA256 при декодировании", "Verify the HMAC-SHA256 signature when decoding"),
+    opt("--wrap N", "Переносить закодированный URL каждые N символов", "Wrap the encoded URL every N characters"),
+    opt("--no-prefix", "Вход — только Base64 блоб без vpn://: декодируется целиком,\nбез удаления схемы", "Input is a bare Base64 blob without vpn://: decode it whole,\nwithout stripping a scheme"),
+    opt("--scheme SCHEME", "Схема URL вместо vpn:// (например amnezia://)", "URL scheme instead of vpn:// (e.g. amnezia://)"),
+    opt("--indent N", "Отступ JSON при декодировании: N пробелов (по умолчанию 2)", "JSON indentation when decoding: N spaces (default 2)"),
+    opt("--indent-tab", "Отступ JSON при декодировании — табуляция", "Indent decoded JSON with tabs"),
+    opt("--preserve-order", "Сохранять исходный порядок ключей JSON\n(меняет байты URL, но не смысл конфигурации)", "Keep the original JSON key order\n(changes URL bytes, not the config meaning)"),
+    opt("--sort-keys", "Рекурсивно сортировать ключи на всех уровнях (по умолчанию;\nотменяет --preserve-order, указанный раньше)", "Sort keys recursively at every level (the default;\noverrides an earlier --preserve-order)"),
+    opt("--compact", "Вывести декодированный JSON в одну строку", "Print decoded JSON on one line"),
+    opt("--pretty", "Вывести декодированный JSON с отступами (по умолчанию)", "Print decoded JSON indented (default)"),
+    opt("--field PATH", "Вывести только поле по пути, например containers.0.container", "Print only the field at PATH, e.g. containers.0.container"),
+    opt("--set PATH=VALUE", "Изменить поле (значение в JSON) и закодировать заново;\nможно указывать несколько раз", "Set a field (JSON value) and re-encode; repeatable"),
+    opt("--raw", "Вывести декодированный JSON байт в байт, как он хранится в URL", "Print decoded JSON byte-for-byte as stored in the URL"),
+    opt("--recursive", "Декодировать повторно закодированные URL (vpn://vpn://...),\nне более 5 слоёв", "Decode doubly-encoded URLs (vpn://vpn://...), up to 5 layers"),
+    opt("--max-size BYTES", "Предел размера распакованного JSON (по умолчанию 16 МиБ)", "Limit on decompressed JSON size (default 16 MiB)"),
+    opt("--from FORMAT", "Формат входа при кодировании: json, yaml или toml\n(по умолчанию по расширению файла из -i, иначе json)", "Input format when encoding: json, yaml or toml\n(defaults to the -i file extension, otherwise json)"),
+    opt("--to FORMAT", "Формат вывода при декодировании: json (по умолчанию), yaml\nили toml", "Output format when decoding: json (default), yaml or toml"),
+    opt("--export FORMAT", "Экспортировать контейнер в формат клиента: wireguard,\nopenvpn, shadowsocks (ссылка ss://)", "Export a container for another client: wireguard,\nopenvpn, shadowsocks (an ss:// link)"),
+    opt("--diff URL1 URL2", "Сравнить две конфигурации (код выхода 1, если различаются)", "Compare two configs (exit code 1 if they differ)"),
+    opt("--split N", "Разделить закодированный URL на N частей (2–255), по одной\nв строке; части собираются при декодировании из нескольких\n-i или в --batch", "Split the encoded URL into N parts (2-255), one per line;\nparts are joined when decoding several -i or in --batch"),
+    opt("--qr", "Вывести QR код закодированного URL в терминал", "Print a QR code of the encoded URL to the terminal"),
+    opt("--qr-png FILE", "Сохранить QR код закодированного URL в PNG", "Save a QR code of the encoded URL as PNG"),
+    opt("--qr-in FILE", "Прочитать VPN URL из QR кода на изображении и декодировать", "Read a VPN URL from a QR code image and decode it"),
+    opt("--stats", "Вывести в stderr размеры данных и коэффициент сжатия", "Print data sizes and compression ratio to stderr"),
+    opt("--explain", "Декодировать и показать в stderr слои URL: Base64,\nзаголовок, сжатые данные, проверку целостности", "Decode and show URL layers on stderr: Base64,\nheader, compressed data, integrity check"),
+    opt("--compare-size", "Сравнить в stderr длину URL для zlib и deflate\nс уровнями 1, 6 и 9", "Compare URL lengths for zlib and deflate at levels 1, 6\nand 9 on stderr"),
+    opt("--nfc", "Перед кодированием привести строковые значения к Unicode NFC", "Normalize string values to Unicode NFC before encoding"),
+    opt("--merge BASE OVERRIDES", "Глубоко объединить две конфигурации и закодировать результат:\nобъекты объединяются по ключам, остальные значения (и null)\nиз OVERRIDES заменяют значения из BASE", "Deep-merge two configs and encode the result: objects are merged\nby key, other values (including null) from OVERRIDES replace\nthose from BASE"),
+    opt("--merge-arrays MODE", "Массивы при --merge: replace (по умолчанию) или concat", "Arrays in --merge: replace (default) or concat"),
+    opt("--template", "Подставить ${NAME} из --var в текст входа перед кодированием", "Substitute ${NAME} from --var into the input text before encoding"),
+    opt("--var NAME=VALUE", "Значение переменной шаблона; можно указывать несколько раз", "Template variable value; repeatable"),
+    opt("--strict-vars", "Ошибка, если в шаблоне остались неизвестные переменные", "Fail on unresolved template variables"),
+    opt("--stamp KEY", "Добавить поле KEY с текущим временем UTC (RFC 3339)\nперед кодированием", "Add a KEY field with the current UTC time (RFC 3339)\nbefore encoding"),
+    opt("--batch", "Обработать каждую строку входа отдельно", "Process each input line separately"),
+    opt("--crlf", "Переводы строк CRLF в выводе", "Use CRLF newlines in the output"),
+    opt("--lf", "Переводы строк LF в выводе (по умолчанию для stdout;\nфайлы — по умолчанию платформы)", "Use LF newlines in the output (default for stdout;\nfiles use the platform default)"),
+    opt("-q, --quiet", "Не показывать индикатор прогресса пакетного режима", "Hide the batch progress bar"),
+    opt("--repl", "Интерактивно обрабатывать строки stdin до EOF", "Interactively process stdin lines until EOF"),
+    opt("-j, --jobs N", "Число потоков пакетного режима (по умолчанию по числу ядер)", "Batch mode thread count (defaults to the number of cores)"),
+    opt("--ndjson", "Кодировать NDJSON: по URL на каждую строку JSON,\nошибочные строки заменяются сообщением", "Encode NDJSON: one URL per JSON line,\nmalformed lines become error placeholders"),
+    opt("--json-array", "В пакетном режиме вывести результаты JSON массивом", "Print batch results as a JSON array"),
+    opt("--output-format FORMAT", "Вид закодированных записей пакета: plain (только URL),\nurl-list (имя<TAB>URL) или json (массив {name, url})", "Batch output for encoded records: plain (URLs only),\nurl-list (name<TAB>URL) or json (array of {name, url})"),
+    opt("--name-field PATH", "Поле с именем для --output-format (по умолчанию\ndescription, затем name)", "Name field for --output-format (default: description,\nthen name)"),
+    opt("--report", "В конце пакета вывести в stderr JSON сводку: успехи,\nошибки по видам, размеры распакованного JSON", "After a batch, print a JSON summary to stderr: successes,\nerrors by kind, decompressed JSON sizes"),
+    opt("-0, --null", "Пакетный режим с записями, разделёнными NUL\n(для find -print0 / xargs -0)", "Batch mode with NUL-separated records\n(for find -print0 / xargs -0)"),
+    opt("--no-color", "Не подсвечивать JSON в терминале (также NO_COLOR=1)", "Disable JSON highlighting in a terminal (also NO_COLOR=1)"),
+    opt("--json-errors", "Выводить ошибки в stderr одной строкой JSON\n{\"error\", \"kind\", \"exit\"}", "Print errors to stderr as one-line JSON\n{\"error\", \"kind\", \"exit\"}"),
+    opt("-v, --verbose", "Подробный журнал этапов обработки в stderr; -vv — ещё подробнее", "Log processing stages to stderr; -vv for even more detail"),
+    opt("--lang LANG", "Язык справки: ru или en (по умолчанию по переменной LANG)", "Help language: ru or en (defaults to LANG)"),
+    opt("-h, --help", "Показать справку", "Show this help"),
+    opt("--selftest", "Проверить кодирование и декодирование на встроенном образце\n(PASS/FAIL, код выхода 0/1)", "Round-trip a built-in sample config (prints PASS/FAIL,\nexit code 0/1)"),
+    opt("-V, --version", "Показать версию и коммит сборки", "Show version and build commit"),
+];
+
+/// Ширина колонки с флагами
+const FLAGS_WIDTH: usize = 18;
+
+/// Формирует полный текст справки
+pub fn usage(program: &str, lang: Lang) -> String {
+    let mut lines = Vec::new();
+    let ru = lang == Lang::Ru;
+
+    lines.push("VPN Config Encoder/Decoder".to_string());
+    lines.push(String::new());
+    lines.push(if ru { "Использование:" } else { "Usage:" }.to_string());
+    lines.push(format!("  {} [-e|-d] [-i <input>] [-o <output>] [<data>]", program));
+    lines.push(format!("  {} <command> [options] [<data>]", program));
+    lines.push(String::new());
+    let commands: &[(&str, &str, &str)] = &[
+        ("encode", "Кодировать JSON в VPN URL (как -e)", "Encode JSON into a VPN URL (same as -e)"),
+        ("decode", "Декодировать VPN URL в JSON (как -d)", "Decode a VPN URL into JSON (same as -d)"),
+        ("validate", "Проверить структуру конфигурации (как --validate)", "Check the config structure (same as --validate)"),
+        ("export FORMAT", "Экспортировать контейнер (как -d --export FORMAT)", "Export a container (same as -d --export FORMAT)"),
+    ];
+    lines.push(if ru { "Команды:" } else { "Commands:" }.to_string());
+    for (command, text_ru, text_en) in commands {
+        lines.push(format!("  {:<width$} {}", command, if ru { text_ru } else { text_en }, width = FLAGS_WIDTH));
+    }
+    lines.push(if ru {
+        "  Без команды режим определяется флагами или автодетектом. Значения\n  флагов можно писать через пробел или `=`: --level=9"
+    } else {
+        "  Without a command the mode comes from flags or auto-detection. Flag\n  values may follow a space or `=`: --level=9"
+    }.to_string());
+    lines.push(String::new());
+    lines.push(if ru { "Опции:" } else { "Options:" }.to_string());
+    for option in OPTIONS {
+        let text = if ru { option.ru } else { option.en };
+        for (index, line) in text.split('\n').enumerate() {
+            let flags = if index == 0 { option.flags } else { "" };
+            lines.push(format!("  {:<width$} {}", flags, line, width = FLAGS_WIDTH));
+        }
+    }
+    lines.push(String::new());
+
+    if ru {
+        lines.push("Автодетект:".to_string());
+        lines.push("  Если не указаны -e/-d, программа автоматически определит".to_string());
+        lines.push("  тип данных (JSON или VPN URL) и выполнит нужную операцию.".to_string());
+        lines.push("  Порядок: -e/-d, затем --from, затем расширение файла из -i".to_string());
+        lines.push("  (.json/.yaml/.yml/.toml — кодирование, .txt/.vpn — декодирование),".to_string());
+        lines.push("  затем содержимое входа.".to_string());
+    } else {
+        lines.push("Auto-detection:".to_string());
+        lines.push("  Without -e/-d the input type (JSON or VPN URL) is detected".to_string());
+        lines.push("  automatically and the matching operation is performed.".to_string());
+        lines.push("  Precedence: -e/-d, then --from, then the -i file extension".to_string());
+        lines.push("  (.json/.yaml/.yml/.toml encode, .txt/.vpn decode),".to_string());
+        lines.push("  then the input content.".to_string());
+    }
+    lines.push(String::new());
+
+    let exit_codes: &[(u8, &str, &str)] = &[
+        (0, "успех", "success"),
+        (1, "прочие ошибки; для --diff — конфигурации различаются", "other errors; for --diff the configs differ"),
+        (2, "неверные аргументы", "bad arguments"),
+        (3, "вход не найден, не читается или не распознан", "input not found, unreadable or not recognized"),
+        (4, "ошибка декодирования URL", "URL decoding failed"),
+        (5, "конфигурация не прошла проверку", "config validation failed"),
+        (6, "некорректный JSON или ошибка кодирования", "invalid JSON or encoding failed"),
+        (7, "ошибка записи результата", "writing the result failed"),
+    ];
+    lines.push(if ru { "Коды выхода:" } else { "Exit codes:" }.to_string());
+    for (code, text_ru, text_en) in exit_codes {
+        lines.push(format!("  {}  {}", code, if ru { text_ru } else { text_en }));
+    }
+    lines.push(String::new());
+
+    let examples: &[(&str, &str, &[&str])] = &[
+        ("Автодетект с файлами", "Auto-detect with files", &["{} -i config.json -o vpn_url.txt", "{} -i vpn_url.txt -o config.json"]),
+        ("Автодетект с прямым вводом", "Auto-detect with direct input", &["{} '{\"server\":\"example.com\"}'", "{} 'vpn://AAAAHXic...'"]),
+        ("Явное указание режима", "Explicit mode", &["{} -e -i config.json", "{} decode -i vpn_url.txt"]),
+        ("Работа с stdin/stdout", "Using stdin/stdout", &["cat config.json | {}", "echo 'vpn://...' | {} -o decoded.json"]),
+    ];
+    lines.push(if ru { "Примеры:" } else { "Examples:" }.to_string());
+    for (index, (title_ru, title_en, commands)) in examples.iter().enumerate() {
+        if index > 0 {
+            lines.push(String::new());
+        }
+        lines.push(format!("  # {}", if ru { title_ru } else { title_en }));
+        for command in commands.iter() {
+            lines.push(format!("  {}", command.replace("{}", program)));
+        }
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usage() {
+        for lang in [Lang::Ru, Lang::En] {
+            let text = usage("prog", lang);
+            for option in OPTIONS {
+                assert!(text.contains(option.flags), "{:?} help is missing {}", lang, option.flags);
+            }
+            assert!(text.contains("prog -i config.json -o vpn_url.txt"));
+        }
+        assert!(usage("prog", Lang::En).contains("Options:"));
+        assert!(usage("prog", Lang::Ru).contains("Опции:"));
+        assert!(usage("prog", Lang::En).contains("export FORMAT"));
+        assert_eq!(Lang::parse("en"), Some(Lang::En));
+        assert_eq!(Lang::parse("de"), None);
+    }
+}
@@ -0,0 +1,160 @@
+#[cfg(feature = "schema")]
+use crate::error::SchemaError;
+use serde_json::Value;
+use std::fmt;
+
+/// Обязательные поля верхнего уровня конфигурации AmneziaVPN
+pub const REQUIRED_FIELDS: &[&str] = &["containers", "defaultContainer", "hostName"];
+
+/// Проблема, найденная при проверке структуры конфигурации
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationIssue {
+    /// Конфигурация не является JSON объектом
+    NotAnObject,
+    /// Отсутствует обязательное поле
+    MissingField(&'static str),
+    /// Поле имеет неожиданный тип
+    WrongType { field: &'static str, expected: &'static str },
+    /// `defaultContainer` не найден среди `containers`
+    UnknownDefaultContainer(String),
+    /// Нарушение пользовательской JSON Schema по пути `path` (JSON Pointer)
+    Schema { path: String, message: String },
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationIssue::NotAnObject => write!(f, "config is not a JSON object"),
+            ValidationIssue::MissingField(field) => write!(f, "missing required field '{}'", field),
+            ValidationIssue::WrongType { field, expected } => {
+                write!(f, "field '{}' must be {}", field, expected)
+            }
+            ValidationIssue::UnknownDefaultContainer(name) => {
+                write!(f, "defaultContainer '{}' is not listed in containers", name)
+            }
+            ValidationIssue::Schema { path, message } => {
+                let path = if path.is_empty() { "(root)" } else { path };
+                write!(f, "{}: {}", path, message)
+            }
+        }
+    }
+}
+
+/// Проверяет структуру конфигурации AmneziaVPN и возвращает список проблем
+pub fn validate_config(config: &Value) -> Vec<ValidationIssue> {
+    let Some(object) = config.as_object() else {
+        return vec![ValidationIssue::NotAnObject];
+    };
+
+    let mut issues = Vec::new();
+    for &field in REQUIRED_FIELDS {
+        if !object.contains_key(field) {
+            issues.push(ValidationIssue::MissingField(field));
+        }
+    }
+
+    let containers = object.get("containers");
+    if containers.is_some_and(|c| !c.is_array()) {
+        issues.push(ValidationIssue::WrongType { field: "containers", expected: "an array" });
+    }
+    if object.get("hostName").is_some_and(|h| !h.is_string()) {
+        issues.push(ValidationIssue::WrongType { field: "hostName", expected: "a string" });
+    }
+
+    match object.get("defaultContainer") {
+        Some(Value::String(name)) => {
+            // Ссылка проверяется только если список контейнеров корректен
+            if let Some(Value::Array(list)) = containers {
+                let listed = list
+                    .iter()
+                    .any(|c| c.get("container").and_then(Value::as_str) == Some(name));
+                if !listed {
+                    issues.push(ValidationIssue::UnknownDefaultContainer(name.clone()));
+                }
+            }
+        }
+        Some(_) => issues.push(ValidationIssue::WrongType {
+            field: "defaultContainer",
+            expected: "a string",
+        }),
+        None => {}
+    }
+
+    issues
+}
+
+/// Проверяет конфигурацию по JSON Schema и возвращает все нарушения
+/// (фича `schema`)
+#[cfg(feature = "schema")]
+pub fn validate_schema(config: &Value, schema: &Value) -> Result<Vec<ValidationIssue>, SchemaError> {
+    let validator = jsonschema::validator_for(schema).map_err(|e| SchemaError::Invalid(e.to_string()))?;
+    Ok(validator
+        .iter_errors(config)
+        .map(|error| ValidationIssue::Schema {
+            path: error.instance_path().to_string(),
+            message: error.to_string(),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_validate_config() {
+        let valid = json!({
+            "containers": [{"container": "amnezia-awg"}],
+            "defaultContainer": "amnezia-awg",
+            "hostName": "example.com"
+        });
+        assert!(validate_config(&valid).is_empty());
+
+        let partial = json!({"hostName": 42, "containers": [], "defaultContainer": "amnezia-awg"});
+        assert_eq!(
+            validate_config(&partial),
+            vec![
+                ValidationIssue::WrongType { field: "hostName", expected: "a string" },
+                ValidationIssue::UnknownDefaultContainer("amnezia-awg".to_string()),
+            ]
+        );
+
+        assert_eq!(
+            validate_config(&json!({})),
+            REQUIRED_FIELDS.iter().map(|f| ValidationIssue::MissingField(f)).collect::<Vec<_>>()
+        );
+        assert_eq!(validate_config(&json!([1])), vec![ValidationIssue::NotAnObject]);
+    }
+
+    #[test]
+    #[cfg(feature = "schema")]
+    fn test_validate_schema() {
+        let schema = json!({
+            "type": "object",
+            "required": ["hostName"],
+            "properties": {
+                "hostName": {"type": "string"},
+                "containers": {"type": "array", "items": {"type": "object"}}
+            }
+        });
+        assert!(validate_schema(&json!({"hostName": "example.com"}), &schema).unwrap().is_empty());
+
+        let issues = validate_schema(&json!({"hostName": 1, "containers": [{}, 2]}), &schema).unwrap();
+        let paths: Vec<&str> = issues
+            .iter()
+            .map(|issue| match issue {
+                ValidationIssue::Schema { path, .. } => path.as_str(),
+                other => panic!("unexpected issue {:?}", other),
+            })
+            .collect();
+        assert_eq!(paths.len(), 2);
+        assert!(paths.contains(&"/hostName"));
+        assert!(paths.contains(&"/containers/1"));
+
+        assert!(matches!(
+            validate_schema(&json!({}), &json!({"type": 12})),
+            Err(SchemaError::Invalid(_))
+        ));
+    }
+}
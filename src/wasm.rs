@@ -0,0 +1,57 @@
+//! Функции кодека для браузера.
+//!
+//! При сборке под `wasm32-unknown-unknown` функции экспортируются через
+//! `wasm-bindgen` под именами `encode` и `decode`; ошибка выбрасывается в JS
+//! строкой с описанием:
+//!
+//! ```text
+//! cargo rustc --lib --release --no-default-features --crate-type cdylib \
+//!     --target wasm32-unknown-unknown
+//! wasm-bindgen --target web --out-dir pkg \
+//!     target/wasm32-unknown-unknown/release/amnezia_config_decoder_rust.wasm
+//! ```
+//!
+//! ```js
+//! import init, { encode, decode } from "./pkg/amnezia_config_decoder_rust.js";
+//! await init();
+//! const url = encode('{"hostName": "example.com"}');
+//! const json = decode(url);
+//! ```
+//!
+//! На остальных платформах это обычные функции Rust
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::wasm_bindgen;
+
+/// Кодирует JSON конфигурацию в `vpn://` URL
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = encode))]
+pub fn encode_json(json: &str) -> Result<String, String> {
+    let config: serde_json::Value =
+        serde_json::from_str(crate::strip_json_comments(json)).map_err(|e| format!("Invalid JSON: {}", e))?;
+    crate::encode(&config).map_err(|e| e.to_string())
+}
+
+/// Декодирует `vpn://` URL в отформатированный JSON
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = decode))]
+pub fn decode_url(url: &str) -> Result<String, String> {
+    let config = crate::decode(url.trim()).map_err(|e| e.to_string())?;
+    serde_json::to_string_pretty(&config).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wasm_exports() {
+        let url = encode_json(r#"{"hostName": "example.com", "containers": []}"#).unwrap();
+        assert!(url.starts_with(crate::PREFIX));
+
+        let json = decode_url(&format!("{}\n", url)).unwrap();
+        let config: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(config["hostName"], "example.com");
+
+        assert!(encode_json("{not json}").unwrap_err().starts_with("Invalid JSON"));
+        assert!(decode_url("vpn://AAAA").is_err());
+    }
+}
@@ -0,0 +1,1051 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use serde_json::{json, Value};
+
+/// Команда для запуска собранного бинарника
+fn binary() -> Command {
+    Command::cargo_bin("amnezia-config-decoder-rust").unwrap()
+}
+
+#[test]
+fn test_help_lists_all_flags() {
+    const FLAGS: &[&str] = &[
+        "--encode", "--decode", "--validate", "--strict", "--check", "--repair", "--hash", "--summary", "--schema", "--decode-nested", "--encode-nested", "--nested-field",
+        "--redact", "--redact-key", "--input", "--input-env", "--fetch", "--from-clipboard", "--output", "--in-place", "--append", "--tee", "--secure-output",
+        "--clipboard", "--level", "--format", "--no-compress", "--minify", "--base64", "--endian", "--deterministic",
+        "--deflate-raw", "--format-version", "--sign", "--verify", "--wrap", "--scheme", "--no-prefix", "--indent", "--indent-tab", "--preserve-order", "--sort-keys",
+        "--compact", "--pretty", "--field", "--set", "--raw", "--recursive", "--max-size", "--from", "--to", "--export", "--diff",
+        "--split", "--qr", "--qr-png", "--qr-in", "--stats", "--explain", "--compare-size", "--template", "--var", "--strict-vars", "--stamp", "--nfc", "--merge", "--merge-arrays", "--batch", "--crlf", "--lf", "--quiet", "--repl", "--jobs", "--ndjson", "--json-array", "--output-format", "--name-field", "--report", "--null", "--verbose", "--no-color", "--json-errors",
+        "--lang", "--help", "--version", "--selftest",
+    ];
+
+    for lang in ["ru", "en"] {
+        let output = binary().args(["--help", "--lang", lang]).output().unwrap();
+        assert!(output.status.success());
+        let help = String::from_utf8(output.stdout).unwrap();
+        for flag in FLAGS {
+            assert!(help.contains(flag), "--lang {} help is missing {}", lang, flag);
+        }
+    }
+
+    let output = binary().args(["-h", "--lang", "en"]).output().unwrap();
+    assert!(String::from_utf8(output.stdout).unwrap().contains("Options:"));
+}
+
+#[test]
+fn test_exit_codes() {
+    // 2 — неверные аргументы
+    binary().args(["--level", "42"]).assert().code(2);
+    binary().arg("--level").assert().code(2);
+
+    // 3 — входной файл не найден или тип входа не распознан
+    binary().args(["-i", "/nonexistent/config.json"]).assert().code(3);
+    binary().arg("random text").assert().code(3);
+
+    // 4 — ошибка декодирования
+    binary().args(["-d", "vpn://AAAA"]).assert().code(4);
+
+    // 5 — конфигурация не прошла проверку
+    let url = binary().args(["-e", r#"{"hostName": 1}"#]).output().unwrap().stdout;
+    let url = String::from_utf8(url).unwrap();
+    binary().args(["--validate", "--strict", url.trim()]).assert().code(5);
+    binary().args(["--validate", url.trim()]).assert().code(0);
+
+    // 6 — некорректный JSON при кодировании
+    binary().args(["-e", "{not json}"]).assert().code(6);
+}
+
+/// Кодирует конфигурацию через CLI и возвращает URL
+fn encode(config: &Value) -> String {
+    let output = binary().args(["-e", &config.to_string()]).output().unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap().trim().to_string()
+}
+
+#[test]
+fn test_encode_file_to_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("config.json");
+    let output = dir.path().join("url.txt");
+    std::fs::write(&input, r#"{"hostName": "example.com", "port": 443}"#).unwrap();
+
+    binary()
+        .args(["-e", "-i", input.to_str().unwrap(), "-o", output.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty());
+
+    let url = std::fs::read_to_string(&output).unwrap();
+    assert!(url.starts_with("vpn://"));
+
+    // И обратно: из файла в stdout
+    binary()
+        .args(["-d", "--compact", "-i", output.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(r#"{"hostName":"example.com","port":443}"#.to_string() + "\n");
+}
+
+#[test]
+fn test_decode_from_stdin() {
+    let url = encode(&json!({"hostName": "stdin.example.com"}));
+    binary()
+        .args(["-d", "--compact"])
+        .write_stdin(url)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""hostName":"stdin.example.com""#));
+}
+
+#[test]
+fn test_autodetect() {
+    // JSON кодируется, URL декодируется
+    binary()
+        .arg(r#"{"a": 1}"#)
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("vpn://"));
+
+    let url = encode(&json!({"a": 1}));
+    binary()
+        .args(["--compact", "-v"])
+        .write_stdin(url)
+        .assert()
+        .success()
+        .stdout("{\"a\":1}\n")
+        .stderr(predicate::str::contains("Автодетект"));
+}
+
+#[test]
+fn test_bad_prefix() {
+    binary()
+        .args(["-d", "https://example.com"])
+        .assert()
+        .code(4)
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::contains("vpn://"));
+}
+
+#[test]
+fn test_missing_argument() {
+    for flag in ["-i", "-o", "--level", "--field", "--set", "--scheme"] {
+        binary()
+            .arg(flag)
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("не указан"));
+    }
+}
+
+#[test]
+fn test_ndjson() {
+    let input = "{\"a\": 1}\n{oops\n\n{\"b\": 2}\n";
+    let output = binary().arg("--ndjson").write_stdin(input).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("📦 Обработано: 4, с ошибками: 1"));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert!(lines[0].starts_with("vpn://"));
+    assert!(lines[1].starts_with("error: line 2:"));
+    assert_eq!(lines[2], "");
+    assert!(lines[3].starts_with("vpn://"));
+
+    binary().args(["--ndjson", "-d"]).assert().code(2);
+}
+
+#[test]
+fn test_repl() {
+    let url = encode(&json!({"hostName": "example.com"}));
+    let input = format!("{{\"a\": 1}}\nnot a config\n\n{}\n", url);
+    let output = binary().args(["--repl", "--compact"]).write_stdin(input).output().unwrap();
+    assert!(output.status.success());
+
+    // Ошибка во второй строке не прерывает обработку остальных
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("vpn://"));
+    assert_eq!(lines[1], r#"{"hostName":"example.com"}"#);
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("> "));
+    assert_eq!(stderr.matches("Ошибка").count(), 1);
+
+    // --field, --to и --redact работают так же, как при обычном декодировании
+    let secret = encode(&json!({"hostName": "example.com", "password": "hunter2"}));
+    let output = binary().args(["--repl", "--field", "hostName"]).write_stdin(format!("{}\n", secret)).output().unwrap();
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "example.com\n");
+    let output = binary().args(["--repl", "--to", "yaml", "--redact"]).write_stdin(format!("{}\n", secret)).output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("hostName: example.com") && !stdout.contains("hunter2"), "{}", stdout);
+}
+
+#[test]
+fn test_crlf() {
+    let url = encode(&json!({"hostName": "example.com", "port": 51820}));
+    binary()
+        .args(["-d", "--crlf"])
+        .write_stdin(url.clone())
+        .assert()
+        .success()
+        .stdout("{\r\n  \"hostName\": \"example.com\",\r\n  \"port\": 51820\r\n}\r\n");
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.json");
+    binary().args(["-d", "--crlf", "-o"]).arg(&path).write_stdin(url.clone()).assert().success();
+    let written = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(written.matches("\r\n").count(), 3);
+    assert_eq!(written.matches('\n').count(), 3);
+
+    binary().args(["-d", "--lf", "-o"]).arg(&path).write_stdin(url).assert().success();
+    assert!(!std::fs::read_to_string(&path).unwrap().contains('\r'));
+}
+
+#[test]
+fn test_template() {
+    let template = r#"{"hostName": "${SERVER}", "containers": [{"awg": {"client_priv_key": "${KEY}"}}]}"#;
+    let output = binary()
+        .args(["--var", "SERVER=vpn.example.com", "--var", "KEY=secret=="])
+        .write_stdin(template)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let url = String::from_utf8(output.stdout).unwrap();
+    let config = amnezia_config_decoder_rust::decode(url.trim()).unwrap();
+    assert_eq!(config["hostName"], "vpn.example.com");
+    assert_eq!(config["containers"][0]["awg"]["client_priv_key"], "secret==");
+
+    binary()
+        .args(["--strict-vars", "--var", "SERVER=vpn.example.com"])
+        .write_stdin(template)
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("KEY"));
+}
+
+#[test]
+fn test_output_format_url_list() {
+    let input = concat!(
+        "{\"description\": \"Home server\", \"hostName\": \"a.example.com\"}\n",
+        "{\"hostName\": \"b.example.com\"}\n",
+        "{\"name\": \"Office\", \"hostName\": \"c.example.com\"}\n",
+    );
+    let output = binary().args(["--output-format", "url-list"]).write_stdin(input).output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 3);
+
+    let (name, url) = lines[0].split_once('\t').unwrap();
+    assert_eq!(name, "Home server");
+    assert_eq!(amnezia_config_decoder_rust::decode(url).unwrap()["hostName"], "a.example.com");
+    // Без имени — только URL
+    assert!(lines[1].starts_with("vpn://") && !lines[1].contains('\t'));
+    assert!(lines[2].starts_with("Office\tvpn://"));
+
+    // Имя из другого поля
+    let output = binary()
+        .args(["--output-format", "url-list", "--name-field", "hostName"])
+        .write_stdin(input)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.lines().nth(1).unwrap().starts_with("b.example.com\tvpn://"));
+
+    let output = binary().args(["--output-format", "json"]).write_stdin(input).output().unwrap();
+    let items: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(items[0]["name"], "Home server");
+    assert!(items[1]["name"].is_null());
+    assert!(items[2]["url"].as_str().unwrap().starts_with("vpn://"));
+}
+
+#[test]
+fn test_malformed_json_position() {
+    let input = "{\n  \"hostName\": \"example.com\"\n  \"dns1\": \"1.1.1.1\"\n}\n";
+    binary()
+        .arg("-e")
+        .write_stdin(input)
+        .assert()
+        .code(6)
+        .stderr(predicate::str::contains("в строке 3, столбец 3"))
+        .stderr(predicate::str::contains("3 |   \"dns1\": \"1.1.1.1\""));
+}
+
+#[test]
+fn test_mode_mismatch_hint() {
+    binary()
+        .arg("-d")
+        .write_stdin(r#"{"hostName": "example.com"}"#)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Похоже на JSON — может быть, нужен -e"));
+
+    let url = encode(&json!({"hostName": "example.com"}));
+    binary()
+        .arg("-e")
+        .write_stdin(url.clone())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Похоже на VPN URL — может быть, нужен -d"));
+
+    // Режим совпадает с входом — подсказки нет
+    binary().arg("-d").write_stdin(url).assert().success().stderr(predicate::str::contains("Похоже").not());
+}
+
+#[test]
+fn test_flag_equals_value() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("config.json");
+    let output = dir.path().join("url.txt");
+    std::fs::write(&input, r#"{"hostName": "example.com"}"#).unwrap();
+
+    binary()
+        .args(["-e", "--level=0", "--format=gzip"])
+        .arg(format!("--input={}", input.display()))
+        .arg(format!("--output={}", output.display()))
+        .assert()
+        .success();
+    let url = std::fs::read_to_string(&output).unwrap();
+    let detailed = amnezia_config_decoder_rust::decode_detailed(&url).unwrap();
+    assert_eq!(detailed.compression, amnezia_config_decoder_rust::Compression::Gzip);
+
+    // Объединённые короткие флаги и --flag=value при декодировании
+    binary()
+        .args(["-dq", "--indent=0", &url])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\"hostName\": \"example.com\""));
+    binary().args(["--level=42", "-e", "{}"]).assert().code(2);
+}
+
+#[test]
+fn test_subcommands() {
+    let output = binary().args(["encode", r#"{"hostName": "example.com"}"#]).output().unwrap();
+    assert!(output.status.success());
+    let url = String::from_utf8(output.stdout).unwrap();
+
+    binary()
+        .args(["decode", "--compact", url.trim()])
+        .assert()
+        .success()
+        .stdout("{\"hostName\":\"example.com\"}\n");
+    binary().args(["validate", "--strict", url.trim()]).assert().code(5);
+    binary()
+        .args(["export", "wireguard", url.trim()])
+        .assert()
+        .code(5)
+        .stderr(predicate::str::contains("WireGuard"));
+    binary().args(["decode", "-h"]).assert().success().stdout(predicate::str::contains("--decode"));
+
+    // Неизвестный флаг — ошибка аргументов, а не входные данные
+    binary().args(["--levle", "3"]).assert().code(2).stderr(predicate::str::contains("--level"));
+}
+
+#[test]
+fn test_dash_is_stdio() {
+    let url = encode(&json!({"hostName": "example.com"}));
+
+    // `-i -` читает stdin даже при заданной переменной окружения
+    binary()
+        .args(["-i", "-", "--compact"])
+        .env("AMNEZIA_INPUT", "vpn://from-env")
+        .write_stdin(url.clone())
+        .assert()
+        .success()
+        .stdout("{\"hostName\":\"example.com\"}\n");
+
+    // `-o -` пишет в stdout, а не в файл с именем `-`
+    let dir = tempfile::tempdir().unwrap();
+    binary()
+        .current_dir(dir.path())
+        .args(["-d", "--compact", "-o", "-", &url])
+        .assert()
+        .success()
+        .stdout("{\"hostName\":\"example.com\"}\n");
+    assert!(!dir.path().join("-").exists());
+
+    binary().args(["-i", "-", "--in-place"]).assert().code(2);
+}
+
+#[test]
+fn test_append() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("urls.txt");
+    for config in [r#"{"hostName": "a.example.com"}"#, r#"{"hostName": "b.example.com"}"#] {
+        binary().args(["-e", "--append", "-o"]).arg(&path).arg(config).assert().success();
+    }
+
+    let written = std::fs::read_to_string(&path).unwrap();
+    let hosts: Vec<String> = written
+        .lines()
+        .map(|url| amnezia_config_decoder_rust::decode(url).unwrap()["hostName"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(hosts, ["a.example.com", "b.example.com"]);
+
+    // Без --append файл перезаписывается
+    binary().args(["-e", "-o"]).arg(&path).arg("{}").assert().success();
+    assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 1);
+}
+
+#[test]
+fn test_json_errors() {
+    use amnezia_config_decoder_rust::{compress_data, create_header, encode_base64};
+
+    let json = r#"{"hostName": "example.com"}"#;
+    let compressed = compress_data(json.as_bytes(), Default::default()).unwrap();
+    let payload = [&create_header(json.len() as u32 + 3)[..], &compressed].concat();
+    let broken = format!("vpn://{}", encode_base64(&payload));
+
+    let output = binary().args(["-d", "--json-errors", &broken]).output().unwrap();
+    assert_eq!(output.status.code(), Some(4));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr.lines().count(), 1);
+    let error: Value = serde_json::from_str(&stderr).unwrap();
+    assert!(error["error"].as_str().unwrap().contains("целостность"));
+    assert_eq!(error["kind"], "IntegrityMismatch");
+    assert_eq!(error["exit"], 4);
+
+    // Ошибки разбора аргументов — тоже JSON
+    let output = binary().args(["--json-errors", "--levle", "9"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let error: Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(error["kind"], "Usage");
+    assert_eq!(error["exit"], 2);
+
+    // Без флага — прежний текст
+    binary().args(["-d", &broken]).assert().code(4).stderr(predicate::str::starts_with("❌ Ошибка:"));
+}
+
+#[test]
+fn test_explain() {
+    let config = json!({"hostName": "example.com", "containers": []});
+    let json_len = serde_json::to_string_pretty(&config).unwrap().len();
+    let output = binary().args(["--explain", &encode(&config)]).output().unwrap();
+    assert!(output.status.success());
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    // Заголовок — четыре байта длины JSON в big endian по смещению 0
+    let bytes: Vec<String> = (json_len as u32).to_be_bytes().iter().map(|byte| format!("{:02x}", byte)).collect();
+    let header = stderr.lines().find(|line| line.contains("заголовок")).unwrap();
+    assert!(header.trim_start().starts_with(&format!("00000000  {}", bytes.join(" "))), "{}", header);
+    assert!(header.ends_with(&format!("длина JSON {} байт", json_len)), "{}", header);
+    assert!(stderr.contains(&format!("После распаковки: {} байт JSON", json_len)));
+    assert!(stderr.contains("✅"));
+
+    let decoded: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(decoded, config);
+
+    // Разбор слоёв выводится и с другими видами вывода декодирования
+    for flags in [&["--hash", "sha256"][..], &["--summary"], &["--raw"]] {
+        binary()
+            .args(["--explain", &encode(&config)])
+            .args(flags)
+            .assert()
+            .success()
+            .stderr(predicate::str::contains("заголовок"));
+    }
+
+    binary().args(["-e", "--explain", "{}"]).assert().code(2);
+}
+
+#[test]
+fn test_split() {
+    let config = json!({"hostName": "example.com", "containers": [{"container": "amnezia-awg"}]});
+    let output = binary().args(["-e", "--split", "3", &config.to_string()]).output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let parts: Vec<&str> = stdout.lines().collect();
+    assert_eq!(parts.len(), 3);
+    assert!(parts.iter().all(|part| part.starts_with("vpn://")));
+
+    // --stats и --compare-size описывают URL до деления
+    binary()
+        .args(["-e", "--split", "3", "--stats", "--compare-size", &config.to_string()])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("📊 JSON:").and(predicate::str::contains("zlib")));
+
+    // Сборка из нескольких -i в любом порядке
+    let dir = tempfile::tempdir().unwrap();
+    let paths: Vec<_> = (0..3).map(|index| dir.path().join(format!("part{}.txt", index))).collect();
+    for (path, part) in paths.iter().zip(&parts) {
+        std::fs::write(path, part).unwrap();
+    }
+    let output = binary().args(["-d", "-i"]).arg(&paths[2]).arg("-i").arg(&paths[0]).arg("-i").arg(&paths[1]).output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(serde_json::from_slice::<Value>(&output.stdout).unwrap(), config);
+
+    // И в пакетном режиме
+    let output = binary().arg("--batch").write_stdin(stdout.clone()).output().unwrap();
+    assert!(output.status.success());
+    let decoded: Value = serde_json::from_str(String::from_utf8(output.stdout).unwrap().trim()).unwrap();
+    assert_eq!(decoded, config);
+
+    // Без одной из частей — ошибка с номером недостающей
+    binary()
+        .args(["-d", "-i"])
+        .arg(&paths[0])
+        .arg("-i")
+        .arg(&paths[2])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("Missing parts: 2"));
+    binary().args(["-d", parts[0]]).assert().code(3).stderr(predicate::str::contains("Missing parts: 2, 3"));
+}
+
+#[test]
+fn test_batch_report() {
+    use amnezia_config_decoder_rust::{compress_data, create_header, encode_base64};
+
+    let small = r#"{"a":1}"#;
+    let large = r#"{"hostName":"example.com","dns1":"1.1.1.1"}"#;
+    let url = |json: &str, declared: usize| {
+        let compressed = compress_data(json.as_bytes(), Default::default()).unwrap();
+        format!("vpn://{}", encode_base64(&[&create_header(declared as u32)[..], &compressed].concat()))
+    };
+    let records = [
+        url(small, small.len()),
+        url(large, large.len()),
+        url(small, small.len() + 5),
+        url(large, large.len() - 1),
+        "vpn://!!!".to_string(),
+        String::new(),
+    ];
+
+    let output = binary().args(["--report", "-d"]).write_stdin(records.join("\n")).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let report: Value = serde_json::from_str(stderr.lines().last().unwrap()).unwrap();
+    assert_eq!(report["total"], 5);
+    assert_eq!(report["decoded"], 2);
+    assert_eq!(report["encoded"], 0);
+    assert_eq!(report["failed"], 3);
+    assert_eq!(report["errors"], json!({"IntegrityMismatch": 2, "Base64": 1}));
+    assert_eq!(report["decompressed_size"], json!({"min": small.len(), "max": large.len(), "avg": 25.0}));
+}
+
+#[test]
+fn test_batch_redact() {
+    let config = json!({"hostName": "example.com", "password": "hunter2", "client_priv_key": "c2VjcmV0"});
+    let url = encode(&config);
+    let output = binary().args(["--batch", "--redact"]).write_stdin(url.clone()).output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!stdout.contains("hunter2") && !stdout.contains("c2VjcmV0"), "{}", stdout);
+    assert!(stdout.contains("example.com"));
+
+    binary().args(["-d", "--raw", "--redact", &url]).assert().code(2);
+    binary().args(["--batch", "--field", "hostName"]).write_stdin(url.clone()).assert().code(2);
+    binary().args(["--batch", "--to", "yaml"]).write_stdin(url).assert().code(2);
+}
+
+#[test]
+fn test_export_redact() {
+    let last_config = json!({
+        "client_priv_key": "cPrivKey=",
+        "client_ip": "10.8.1.2",
+        "server_pub_key": "sPubKey=",
+        "psk_key": "pskKey=",
+        "hostName": "vpn.example.com",
+        "port": 51820
+    });
+    let config = json!({
+        "containers": [{"container": "amnezia-wireguard", "wireguard": {"last_config": last_config.to_string()}}],
+        "defaultContainer": "amnezia-wireguard",
+        "hostName": "vpn.example.com"
+    });
+    let output = binary().args(["--export", "wireguard", "--redact", &encode(&config)]).output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("PrivateKey = ***") && stdout.contains("PresharedKey = ***"), "{}", stdout);
+    assert!(!stdout.contains("cPrivKey=") && !stdout.contains("pskKey="), "{}", stdout);
+    assert!(stdout.contains("PublicKey = sPubKey="));
+}
+
+#[test]
+fn test_schema() {
+    let dir = tempfile::tempdir().unwrap();
+    let schema = dir.path().join("schema.json");
+    std::fs::write(&schema, r#"{"required": ["dns1"]}"#).unwrap();
+    let schema = schema.to_str().unwrap();
+
+    let last_config = json!({"client_priv_key": "a", "client_ip": "10.8.1.2", "server_pub_key": "b", "hostName": "h", "port": 1});
+    let invalid = encode(&json!({
+        "containers": [{"container": "amnezia-wireguard", "wireguard": {"last_config": last_config.to_string()}}],
+        "hostName": "vpn.example.com"
+    }));
+    let valid = encode(&json!({"hostName": "vpn.example.com", "dns1": "1.1.1.1"}));
+
+    // Проверка по схеме действует при любом виде вывода декодирования
+    binary().args(["-d", "--schema", schema, &invalid]).assert().code(5);
+    binary().args(["--summary", "--schema", schema, &invalid]).assert().code(5);
+    binary().args(["--export", "wireguard", "--schema", schema, &invalid]).assert().code(5);
+    binary().args(["--schema", schema, &invalid, "--hash", "sha256"]).assert().code(5);
+    binary().args(["--raw", "--schema", schema, &invalid]).assert().code(2);
+
+    // В пакетном режиме запись с нарушением считается ошибкой
+    let output = binary().args(["--batch", "--schema", schema]).write_stdin(format!("{}\n{}", invalid, valid)).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert!(lines[0].starts_with("error: line 1: не соответствует схеме"), "{}", stdout);
+    assert!(lines[1].contains("1.1.1.1"));
+
+    let broken = dir.path().join("broken.json");
+    std::fs::write(&broken, r#"{"type": 12}"#).unwrap();
+    binary().args(["--batch", "--schema", broken.to_str().unwrap()]).write_stdin(valid).assert().code(2);
+}
+
+#[test]
+fn test_diff_redact() {
+    let first = encode(&json!({"hostName": "a.example.com", "password": "hunter2"}));
+    let second = encode(&json!({"hostName": "b.example.com", "password": "other"}));
+    let output = binary().args(["--diff", &first, &second, "--redact"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    // Изменение секрета видно, но без значений
+    assert!(stdout.contains("~ password: \"***\" -> \"***\""), "{}", stdout);
+    assert!(!stdout.contains("hunter2") && !stdout.contains("other"), "{}", stdout);
+    assert!(stdout.contains("~ hostName: \"a.example.com\" -> \"b.example.com\""));
+}
+
+#[test]
+fn test_stamp() {
+    let encoded = binary().args(["-e", "--stamp", "createdAt", r#"{"a": 1}"#]).output().unwrap();
+    assert!(encoded.status.success());
+    let decoded = binary().args(["-d", "--compact"]).write_stdin(encoded.stdout).output().unwrap();
+    let config: Value = serde_json::from_slice(&decoded.stdout).unwrap();
+
+    assert_eq!(config["a"], 1);
+    let stamp = config["createdAt"].as_str().unwrap();
+    assert!(stamp.ends_with('Z'));
+    assert!(humantime::parse_rfc3339(stamp).is_ok());
+}
+
+#[test]
+fn test_encode_yaml() {
+    let yaml = "hostName: example.com\ncontainers:\n  - container: amnezia-awg\n    awg:\n      port: '51820'\n";
+    let expected = json!({
+        "containers": [{"awg": {"port": "51820"}, "container": "amnezia-awg"}],
+        "hostName": "example.com"
+    });
+
+    // По расширению файла, без -e и --from
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("config.yaml");
+    std::fs::write(&input, yaml).unwrap();
+    let url = binary().args(["-i", input.to_str().unwrap()]).output().unwrap();
+    assert!(url.status.success());
+
+    let decoded = binary().args(["-d", "--compact"]).write_stdin(url.stdout).output().unwrap();
+    assert_eq!(serde_json::from_slice::<Value>(&decoded.stdout).unwrap(), expected);
+
+    // Из stdin с явным --from
+    let url = binary().args(["--from", "yaml"]).write_stdin(yaml).output().unwrap();
+    let decoded = binary().args(["-d"]).write_stdin(url.stdout).output().unwrap();
+    assert_eq!(serde_json::from_slice::<Value>(&decoded.stdout).unwrap(), expected);
+
+    binary().args(["--from", "toml"]).write_stdin("a = ").assert().code(6);
+}
+
+#[test]
+fn test_extension_hint() {
+    let dir = tempfile::tempdir().unwrap();
+    let url = encode(&json!({"hostName": "example.com"}));
+
+    // .vpn — декодирование, даже если URL скопирован без схемы
+    let link = dir.path().join("home.vpn");
+    std::fs::write(&link, url.strip_prefix("vpn://").unwrap()).unwrap();
+    binary()
+        .args(["--compact", "-i", link.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout("{\"hostName\":\"example.com\"}\n");
+
+    // .yaml — кодирование YAML
+    let config = dir.path().join("config.yaml");
+    std::fs::write(&config, "hostName: example.com\n").unwrap();
+    binary()
+        .args(["-i", config.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(format!("{}\n", url));
+}
+
+#[test]
+fn test_bom_json_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("config");
+    std::fs::write(&input, "\u{feff}// экспорт\n{\"hostName\": \"example.com\"}\n").unwrap();
+
+    binary()
+        .args(["-i", input.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(format!("{}\n", encode(&json!({"hostName": "example.com"}))));
+
+    let link = dir.path().join("link");
+    std::fs::write(&link, format!("\u{feff}{}\n", encode(&json!({"a": 1})))).unwrap();
+    binary().args(["--compact", "-i", link.to_str().unwrap()]).assert().success().stdout("{\"a\":1}\n");
+}
+
+#[test]
+fn test_tee() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("url.txt");
+    let result = binary()
+        .args(["--tee", "-o", output.to_str().unwrap(), r#"{"a": 1}"#])
+        .output()
+        .unwrap();
+    assert!(result.status.success());
+
+    // В stdout то же, что в файле, плюс перевод строки
+    let saved = std::fs::read_to_string(&output).unwrap();
+    assert!(saved.starts_with("vpn://"));
+    assert_eq!(String::from_utf8(result.stdout).unwrap(), format!("{}\n", saved));
+}
+
+#[test]
+fn test_in_place() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("link.txt");
+    std::fs::write(&path, encode(&json!({"hostName": "example.com"}))).unwrap();
+
+    binary().args(["--in-place", "--compact", "-i", path.to_str().unwrap()]).assert().success();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), r#"{"hostName":"example.com"}"#);
+
+    // Неудачное преобразование не трогает исходный файл
+    std::fs::write(&path, "vpn://broken").unwrap();
+    binary()
+        .args(["--in-place", "-d", "-i", path.to_str().unwrap()])
+        .assert()
+        .code(4);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "vpn://broken");
+
+    binary().args(["--in-place", "-d"]).write_stdin("vpn://x").assert().code(2);
+}
+
+/// Проверяет, что ключи всех объектов идут в лексикографическом порядке
+fn keys_sorted(value: &Value) -> bool {
+    match value {
+        Value::Object(map) => {
+            let keys: Vec<&String> = map.keys().collect();
+            keys.windows(2).all(|pair| pair[0] < pair[1]) && map.values().all(keys_sorted)
+        }
+        Value::Array(items) => items.iter().all(keys_sorted),
+        _ => true,
+    }
+}
+
+#[test]
+fn test_sort_keys() {
+    let config = r#"{"z": 1, "containers": [{"port": 1, "awg": {"b": 1, "a": 2}}], "a": {"y": [{"d": 1, "c": 2}], "x": 0}}"#;
+    let url = binary().args(["-e", "--preserve-order", config]).output().unwrap();
+    let url = String::from_utf8(url.stdout).unwrap();
+
+    let unsorted = binary().args(["-d", "--preserve-order", url.trim()]).output().unwrap();
+    assert!(!keys_sorted(&serde_json::from_slice(&unsorted.stdout).unwrap()));
+
+    let sorted = binary().args(["-d", "--preserve-order", "--sort-keys", url.trim()]).output().unwrap();
+    let sorted: Value = serde_json::from_slice(&sorted.stdout).unwrap();
+    assert!(keys_sorted(&sorted));
+    assert_eq!(sorted, serde_json::from_str::<Value>(config).unwrap());
+}
+
+#[test]
+fn test_indent() {
+    let url = encode(&json!({"containers": [{"container": "amnezia-awg"}]}));
+    binary()
+        .args(["-d", "--indent", "4", &url])
+        .assert()
+        .success()
+        .stdout("{\n    \"containers\": [\n        {\n            \"container\": \"amnezia-awg\"\n        }\n    ]\n}\n");
+    binary()
+        .args(["-d", "--indent-tab", &url])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("{\n\t\"containers\": [\n\t\t{"));
+}
+
+#[test]
+fn test_unknown_input_reason() {
+    binary()
+        .arg("ss://YWVzOnBhc3M@example.com:8388")
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("'ss://' which is not a recognized VPN scheme"));
+}
+
+#[test]
+fn test_nested_round_trip() {
+    use amnezia_config_decoder_rust::nested::collapse_value;
+
+    let last_config = json!({"port": "51820", "client_priv_key": "key"});
+    let profile = json!("client\nremote example.com 1194\n");
+    let url = encode(&json!({
+        "hostName": "example.com",
+        "containers": [{
+            "container": "amnezia-awg",
+            "awg": {"last_config": collapse_value(&last_config).unwrap()},
+            "openvpn": {"profile": collapse_value(&profile).unwrap()}
+        }]
+    }));
+
+    let output = binary().args(["-d", "--decode-nested", &url]).output().unwrap();
+    assert!(output.status.success());
+    let expanded: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(expanded["containers"][0]["awg"]["last_config"], last_config);
+    assert_eq!(expanded["containers"][0]["openvpn"]["profile"], profile);
+
+    // Текстовый профиль не в last_config, поэтому его путь указывается явно
+    for args in [
+        vec!["-e", "--encode-nested"],
+        vec!["-e", "--nested-field", "containers[0].awg.last_config", "--nested-field", "containers[0].openvpn.profile"],
+    ] {
+        let output = binary().args(&args).write_stdin(expanded.to_string()).output().unwrap();
+        assert!(output.status.success());
+        let reencoded = String::from_utf8(output.stdout).unwrap();
+
+        let output = binary().args(["-d", reencoded.trim()]).output().unwrap();
+        let collapsed: Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert!(collapsed["containers"][0]["awg"]["last_config"].is_string());
+
+        let output = binary().args(["-d", "--decode-nested", reencoded.trim()]).output().unwrap();
+        assert_eq!(serde_json::from_slice::<Value>(&output.stdout).unwrap(), expanded);
+    }
+
+    binary()
+        .args(["-e", "--nested-field", "containers[5].awg"])
+        .write_stdin(expanded.to_string())
+        .assert()
+        .code(2);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_from_clipboard_headless() {
+    // Без графической среды буфер обмена недоступен: понятная ошибка и код 3
+    binary()
+        .arg("--from-clipboard")
+        .env_remove("DISPLAY")
+        .env_remove("WAYLAND_DISPLAY")
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("буфер обмена недоступен"));
+
+    // Аргументы важнее буфера обмена
+    let url = encode(&json!({"hostName": "example.com"}));
+    binary()
+        .args(["--from-clipboard", "-d", &url])
+        .env_remove("DISPLAY")
+        .env_remove("WAYLAND_DISPLAY")
+        .assert()
+        .success();
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_stdout_errors() {
+    use std::process::{Command, Stdio};
+
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("url.txt");
+    // Вывод больше буфера канала, чтобы запись точно упёрлась в закрытый конец
+    let url = amnezia_config_decoder_rust::encode(&json!({"hostName": "x".repeat(1 << 18)})).unwrap();
+    std::fs::write(&input, url).unwrap();
+    let program = assert_cmd::cargo::cargo_bin("amnezia-config-decoder-rust");
+
+    // Читатель закрыл stdout (`| head`): тихое завершение без паники
+    let mut child = Command::new(&program).arg("-i").arg(&input).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().unwrap();
+    drop(child.stdout.take());
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stderr.is_empty(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    // Прочие ошибки записи в stdout — код 7
+    let full = std::fs::OpenOptions::new().write(true).open("/dev/full").unwrap();
+    let output = Command::new(&program).arg("-i").arg(&input).stdout(full).output().unwrap();
+    assert_eq!(output.status.code(), Some(7));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Ошибка записи результата"));
+}
+
+/// Простой HTTP сервер на случайном порту: отвечает на один запрос
+/// `status` и `body` и возвращает адрес
+#[cfg(feature = "fetch")]
+fn serve_once(status: &'static str, body: &'static str) -> String {
+    use std::io::{BufRead, BufReader, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        // Заголовки запроса заканчиваются пустой строкой
+        let mut line = String::new();
+        while reader.read_line(&mut line).unwrap() > 2 {
+            line.clear();
+        }
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+        reader.get_mut().write_all(response.as_bytes()).unwrap();
+    });
+    format!("http://{}/config.json", address)
+}
+
+#[cfg(feature = "fetch")]
+#[test]
+fn test_fetch() {
+    let url = serve_once("200 OK", r#"{"hostName": "fetched.example.com"}"#);
+    let output = binary().args(["--fetch", &url]).output().unwrap();
+    assert!(output.status.success());
+    let config = amnezia_config_decoder_rust::decode(String::from_utf8(output.stdout).unwrap().trim()).unwrap();
+    assert_eq!(config["hostName"], "fetched.example.com");
+
+    let url = serve_once("404 Not Found", "not found");
+    binary()
+        .args(["--fetch", &url])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("HTTP 404"));
+
+    binary()
+        .args(["--fetch", "ftp://example.com/config.json"])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("только http:// и https://"));
+}
+
+#[test]
+fn test_selftest() {
+    binary().arg("--selftest").assert().code(0).stdout("PASS\n");
+}
+
+#[test]
+fn test_repair() {
+    use amnezia_config_decoder_rust::{compress_data, create_header, encode_base64};
+
+    let json = r#"{"hostName": "example.com"}"#;
+    let compressed = compress_data(json.as_bytes(), Default::default()).unwrap();
+    let payload = [&create_header(json.len() as u32 + 3)[..], &compressed].concat();
+    let broken = format!("vpn://{}", encode_base64(&payload));
+    binary().args(["-d", &broken]).assert().code(4);
+
+    let output = binary().args(["--repair", &broken]).output().unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("проверка целостности отключена"));
+    let repaired = String::from_utf8(output.stdout).unwrap();
+    binary()
+        .args(["-d", repaired.trim()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("example.com"));
+
+    // Корректный URL выводится без изменений и без предупреждения
+    let output = binary().args(["--repair", repaired.trim()]).output().unwrap();
+    assert_eq!(output.stdout, repaired.as_bytes());
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn test_no_color_when_piped() {
+    // stdout теста — канал, а не терминал: ANSI последовательностей нет
+    let url = encode(&json!({"hostName": "example.com", "port": 51820, "on": true}));
+    for args in [vec!["-d", url.as_str()], vec!["-d", "--no-color", url.as_str()]] {
+        let output = binary().args(&args).env_remove("NO_COLOR").output().unwrap();
+        assert!(output.status.success());
+        assert!(!output.stdout.contains(&0x1b), "unexpected ANSI codes in {:?}", args);
+    }
+}
+
+#[test]
+fn test_merge() {
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path().join("base.json");
+    let overrides = dir.path().join("overrides.yaml");
+    std::fs::write(
+        &base,
+        r#"{"hostName": "old.example.com", "dns1": "1.1.1.1", "containers": [{"container": "amnezia-awg"}]}"#,
+    )
+    .unwrap();
+    std::fs::write(&overrides, "hostName: new.example.com\ncontainers:\n  - container: amnezia-openvpn\n").unwrap();
+
+    for (mode, containers) in [("replace", 1), ("concat", 2)] {
+        let output = binary()
+            .args(["--merge", base.to_str().unwrap(), overrides.to_str().unwrap(), "--merge-arrays", mode])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let url = String::from_utf8(output.stdout).unwrap();
+
+        let output = binary().args(["-d", url.trim()]).output().unwrap();
+        let merged: Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(merged["hostName"], "new.example.com");
+        assert_eq!(merged["dns1"], "1.1.1.1");
+        assert_eq!(merged["containers"].as_array().unwrap().len(), containers);
+    }
+
+    binary()
+        .args(["--merge", base.to_str().unwrap(), overrides.to_str().unwrap(), "-d"])
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn test_deterministic() {
+    let encode_with = |json: &str, extra: &[&str]| {
+        let output = binary().args(["-e", "--deterministic"]).args(extra).arg(json).output().unwrap();
+        assert!(output.status.success());
+        output.stdout
+    };
+    let url = encode_with(r#"{"hostName": "example.com", "port": 51820}"#, &[]);
+    assert_eq!(url, encode_with("{\"port\":51820,\n\"hostName\":\"example.com\"}", &[]));
+    // Флаги сжатия и порядка ключей не влияют на результат
+    assert_eq!(url, encode_with(r#"{"port": 51820, "hostName": "example.com"}"#, &["--level", "1", "--preserve-order", "--format", "gzip"]));
+}
+
+#[test]
+fn test_hash() {
+    let hash = |args: &[&str]| {
+        let output = binary().args(args).output().unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap().trim().to_string()
+    };
+
+    let config = r#"{"hostName": "example.com", "port": 51820}"#;
+    let zlib = hash(&["-e", config]);
+    let gzip = hash(&["-e", "--format", "gzip", "--level", "1", config]);
+    assert_ne!(zlib, gzip);
+
+    // Два разных URL с одной конфигурацией — один хеш, и он совпадает с хешем при кодировании
+    let digest = hash(&["--hash", "sha256", &zlib]);
+    assert_eq!(digest.len(), 64);
+    assert!(digest.chars().all(|c| c.is_ascii_hexdigit()));
+    assert_eq!(hash(&["--hash", "sha256", &gzip]), digest);
+    assert_eq!(hash(&["--hash", "sha256", "-e", r#"{"port": 51820, "hostName": "example.com"}"#]), digest);
+
+    binary().args(["--hash", "md5", &zlib]).assert().code(2);
+}
+
+#[test]
+fn test_no_prefix() {
+    let url = encode(&json!({"hostName": "example.com"}));
+    let blob = url.strip_prefix("vpn://").unwrap();
+
+    // Файл только с Base64, разбитым на строки
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("payload.b64");
+    let (head, tail) = blob.split_at(blob.len() / 2);
+    std::fs::write(&path, format!("{}\n{}\n", head, tail)).unwrap();
+
+    binary()
+        .args(["--no-prefix", "-i", path.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("example.com"));
+    binary().args(["--no-prefix", &url]).assert().code(4);
+}
@@ -0,0 +1,52 @@
+//! Проверка декодера на произвольных входных данных: любой мусор должен
+//! приводить к ошибке, а не к панике или зависанию
+
+use amnezia_config_decoder_rust::{
+    create_header, decode, decode_base64, decode_to_writer, decompress_data, decompress_gzip,
+    encode_base64, PREFIX,
+};
+use proptest::prelude::*;
+
+/// Полезная нагрузка, похожая на настоящую: заголовок длины, затем zlib или gzip
+/// с испорченным хвостом
+fn payload() -> impl Strategy<Value = Vec<u8>> {
+    let magic = prop_oneof![
+        Just(vec![]),
+        Just(vec![0x78, 0x9c]),
+        Just(vec![0x78, 0xda]),
+        Just(vec![0x1f, 0x8b, 0x08]),
+    ];
+    (any::<u32>(), magic, proptest::collection::vec(any::<u8>(), 0..256)).prop_map(
+        |(length, magic, tail)| [&create_header(length)[..], &magic, &tail].concat(),
+    )
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig { cases: 2048, timeout: 5000, ..ProptestConfig::default() })]
+
+    #[test]
+    fn test_decode_arbitrary_string(input in any::<String>()) {
+        // Без префикса декодирование обязано завершиться ошибкой
+        if !input.starts_with(PREFIX) {
+            prop_assert!(decode(&input).is_err());
+        }
+        let _ = decode(&format!("{}{}", PREFIX, input));
+    }
+
+    #[test]
+    fn test_decode_arbitrary_bytes(bytes in proptest::collection::vec(any::<u8>(), 0..512)) {
+        // Мусор после корректного Base64 доходит до распаковки и разбора JSON
+        let url = format!("{}{}", PREFIX, encode_base64(&bytes));
+        let _ = decode(&url);
+        let _ = decode_to_writer(&url, std::io::sink());
+        let _ = decompress_data(&bytes);
+        let _ = decompress_gzip(&bytes);
+    }
+
+    #[test]
+    fn test_decode_arbitrary_payload(data in payload()) {
+        let url = format!("{}{}", PREFIX, encode_base64(&data));
+        let _ = decode(&url);
+        let _ = decode_base64(&encode_base64(&data));
+    }
+}